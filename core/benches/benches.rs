@@ -1,8 +1,10 @@
 mod types;
 
 use criterion::{criterion_group, criterion_main, Criterion};
-use rand::{rngs::StdRng, SeedableRng};
-use serde_intermediate::{Change, DiffOptimizationHint, DiffOptions, ReflectIntermediate};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde_intermediate::{
+    Change, DiffOptimizationHint, DiffOptions, Intermediate, ReflectIntermediate,
+};
 use std::hint::black_box;
 use types::*;
 
@@ -193,6 +195,31 @@ fn dlcs(c: &mut Criterion) {
     });
 }
 
+fn sequences(c: &mut Criterion) {
+    let mut rng = randomizer();
+    let prev = (0..5000)
+        .map(|_| Intermediate::U8(rng.random_range(0..16)))
+        .collect::<Vec<_>>();
+    let mut next = prev.to_owned();
+    for _ in 0..100 {
+        let index = rng.random_range(0..next.len());
+        match rng.random_range(0..3) {
+            0 => {
+                next.remove(index);
+            }
+            1 => next.insert(index, Intermediate::U8(rng.random_range(0..16))),
+            _ => next[index] = Intermediate::U8(rng.random_range(0..16)),
+        }
+    }
+    let options = &DiffOptions::default();
+
+    c.bench_function("Calculate sequence change", |b| {
+        b.iter(|| {
+            let _ = Change::sequence_difference(black_box(&prev), black_box(&next), options);
+        })
+    });
+}
+
 criterion_group!(
     benches,
     serialize,
@@ -200,5 +227,6 @@ criterion_group!(
     patching,
     patching_optimized,
     dlcs,
+    sequences,
 );
 criterion_main!(benches);
@@ -154,6 +154,7 @@ impl<'de> serde::de::Deserializer<'de> for Deserializer<'de> {
         self.deserialize_any(visitor)
     }
 
+    #[allow(clippy::collapsible_match)]
     fn deserialize_enum<V>(
         self,
         _: &'static str,
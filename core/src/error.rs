@@ -1,4 +1,7 @@
-use crate::value::intermediate::Intermediate;
+use crate::{
+    value::{intermediate::Intermediate, path::IntermediatePath},
+    versioning::Change,
+};
 use std::fmt::Display;
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -24,6 +27,14 @@ pub enum Error {
     NoNextTokens,
     InvalidTokens(String),
     CannotParse(String),
+    InvalidPath(String),
+    PathNotFound(IntermediatePath),
+    /// (first change, next change)
+    CannotCompose(Box<Change>, Box<Change>),
+    UnsupportedPatchOperation(String),
+    /// (location, reason)
+    InvalidPatch(IntermediatePath, String),
+    UnknownCheckpoint(String),
 }
 
 impl serde::ser::Error for Error {
@@ -59,6 +70,16 @@ impl Display for Error {
             Error::NoNextTokens => formatter.write_str("no next tokens"),
             Error::InvalidTokens(_) => formatter.write_str("invalid tokens"),
             Error::CannotParse(_) => formatter.write_str("cannot parse"),
+            Error::InvalidPath(_) => formatter.write_str("invalid path"),
+            Error::PathNotFound(path) => write!(formatter, "path not found: `{}`", path),
+            Error::CannotCompose(_, _) => formatter.write_str("cannot compose changes"),
+            Error::UnsupportedPatchOperation(op) => {
+                write!(formatter, "unsupported patch operation: `{}`", op)
+            }
+            Error::InvalidPatch(path, reason) => {
+                write!(formatter, "invalid patch at `{}`: {}", path, reason)
+            }
+            Error::UnknownCheckpoint(name) => write!(formatter, "unknown checkpoint: `{}`", name),
         }
     }
 }
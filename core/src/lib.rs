@@ -49,6 +49,13 @@
 //!     serde-intermediate = { version = "*", default-features = false }
 //!     ```
 //!
+//!     `ReflectIntermediate` and `DiffIntermediate` can be enabled for `indexmap` and `smallvec` collections with features of the same names:
+//!
+//!     ```toml
+//!     [dependencies]
+//!     serde-intermediate = { version = "*", features = ["indexmap", "smallvec"] }
+//!     ```
+//!
 //! 1. Crate that adds support for tagged intermediate value (to embed tagged `Intermediate` in other serializable data with `TaggedIntermediate` type):
 //!
 //!     ```toml
@@ -129,15 +136,27 @@ pub use crate::{
         text::{from_str, from_str_as, intermediate_from_str},
     },
     error::Error,
-    reflect::ReflectIntermediate,
+    reflect::{DiffIntermediate, PatchReport, ReflectIntermediate},
     schema::{SchemaIdContainer, SchemaIntermediate, SchemaPackage},
     ser::{
         intermediate::serialize as to_intermediate,
         object::serialize as to_object,
         text::{to_string, to_string_compact, to_string_pretty, TextConfig, TextConfigStyle},
     },
-    value::{intermediate::Intermediate, object::Object},
-    versioning::*,
+    value::{
+        intermediate::Intermediate,
+        object::Object,
+        path::{
+            IntermediatePath, IntermediatePathFilter, IntermediatePathMatch,
+            IntermediatePathPattern, IntermediatePathPatternSegment, IntermediatePathSegment,
+        },
+    },
+    versioning::{
+        history::ChangeHistory,
+        json_patch::JsonPatchOperation,
+        tracked::{Tracked, TrackedMut},
+        *,
+    },
 };
 
 #[cfg(feature = "derive")]
@@ -1,7 +1,11 @@
-use crate::{from_intermediate, Change, Intermediate};
-use serde::de::DeserializeOwned;
+use crate::{
+    error::*, from_intermediate, to_intermediate, Change, DiffOptions, Intermediate,
+    IntermediatePath, IntermediatePathSegment,
+};
+use serde::{de::DeserializeOwned, Serialize};
 use std::{
-    cell::Cell,
+    borrow::Cow,
+    cell::{Cell, OnceCell, RefCell},
     collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, LinkedList, VecDeque},
     hash::Hash,
     marker::PhantomData,
@@ -11,12 +15,13 @@ use std::{
     },
     ops::{Range, RangeInclusive},
     path::PathBuf,
+    rc::Rc,
     sync::{
         atomic::{
             AtomicBool, AtomicI16, AtomicI32, AtomicI64, AtomicI8, AtomicIsize, AtomicU16,
             AtomicU32, AtomicU64, AtomicU8, AtomicUsize,
         },
-        Mutex,
+        Arc, Mutex, RwLock,
     },
 };
 
@@ -40,54 +45,383 @@ use std::{
 pub trait ReflectIntermediate {
     fn patch_change(&mut self, _change: &Change) {}
 
+    /// Patches change into data, recording locations of applied and rejected changes.
+    /// Default implementation falls back to `patch_change` and reports change as applied.
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+        self.patch_change(change);
+        if !change.is_same() {
+            report.apply();
+        }
+    }
+
+    /// Patches change into data, listing applied and rejected changes. Changes that can't be
+    /// applied are skipped, while the rest of change gets applied.
+    ///
+    /// # Example
+    /// ```rust
+    /// use serde::{Serialize, Deserialize};
+    /// use serde_intermediate::{Change, ReflectIntermediate};
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate)]
+    /// struct Foo {
+    ///     a: bool,
+    ///     b: Vec<i32>,
+    /// }
+    ///
+    /// let mut data = Foo { a: false, b: vec![] };
+    /// let change = Change::partial_struct()
+    ///     .partial_struct_item("a", Change::changed(true))
+    ///     .partial_struct_item("b", Change::partial_seq().partial_seq_item(3, Change::Removed));
+    /// let report = data.try_patch_change(&change);
+    /// assert_eq!(report.applied, vec!["a".parse().unwrap()]);
+    /// assert_eq!(report.rejected[0].0, "b[3]".parse().unwrap());
+    /// assert_eq!(data, Foo { a: true, b: vec![] });
+    ///
+    /// let mut data = Foo { a: false, b: vec![] };
+    /// assert!(data.try_patch_change_atomic(&change).is_err());
+    /// assert_eq!(data, Foo { a: false, b: vec![] });
+    /// ```
+    fn try_patch_change(&mut self, change: &Change) -> PatchReport {
+        let mut report = PatchReport::default();
+        self.patch_change_with_report(change, &mut report);
+        report
+    }
+
+    /// Patches change into data only if all of it can be applied, otherwise fails with first
+    /// rejected change. Change is applied to a copy of data that replaces data on success, so
+    /// data is never left half-patched - note that patching hooks of copy get called either way.
+    fn try_patch_change_atomic(&mut self, change: &Change) -> Result<PatchReport>
+    where
+        Self: Clone + Sized,
+    {
+        let mut patched = self.clone();
+        let report = patched.try_patch_change(change);
+        if let Some((path, error)) = report.rejected.first() {
+            return Err(match error {
+                Error::InvalidPatch(_, _) => error.to_owned(),
+                error => Error::InvalidPatch(path.to_owned(), error.to_string()),
+            });
+        }
+        *self = patched;
+        Ok(report)
+    }
+
+    /// Patches change into data through shared reference, for data that can be mutated by all
+    /// its owners (such as content of `Arc<RwLock<T>>`). Returns false if data can't be patched
+    /// that way, which is default.
+    fn patch_shared_change_with_report(&self, _change: &Change, _report: &mut PatchReport) -> bool {
+        false
+    }
+
+    /// Copy of data, used to patch `Rc` and `Arc` content shared with other owners
+    /// copy-on-write, like `Rc::make_mut` does. Returns `None` if data can't be copied, which is
+    /// default - derived implementations return copy when type is marked with
+    /// `#[reflect_intermediate(clone)]`.
+    fn clone_for_patch(&self) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
     fn before_patch_change(&mut self) {}
 
     fn after_patch_change(&mut self) {}
 }
 
+/// Trait used to calculate changes directly between typed data.
+/// Prefer to implement using `DiffIntermediate` derive macro.
+///
+/// Structures, enums, tuples, arrays and maps compare their parts with their own typed diffs.
+/// Other sequences (`Vec`, `VecDeque`, `LinkedList`) only tell if they are same that way -
+/// otherwise their change is calculated from serialized data, as it takes all items to find
+/// shortest sequence of edits.
+///
+/// # Example
+/// ```rust
+/// use serde::{Serialize, Deserialize};
+/// use serde_intermediate::{Change, DiffIntermediate};
+///
+/// #[derive(Debug, PartialEq, Serialize, Deserialize, DiffIntermediate)]
+/// struct Foo {
+///     a: bool,
+///     b: Vec<i32>,
+/// }
+///
+/// let a = Foo { a: false, b: vec![1, 2, 3] };
+/// let b = Foo { a: true, b: vec![1, 2, 3] };
+/// assert_eq!(
+///     a.diff_with(&b, &Default::default()).unwrap(),
+///     Change::data_difference(&a, &b, &Default::default()).unwrap(),
+/// );
+/// ```
+pub trait DiffIntermediate: Serialize + Sized {
+    /// Calculates same change as `Change::data_difference`, serializing only parts of data
+    /// that differ. Default implementation serializes both values.
+    fn diff_with(&self, other: &Self, options: &DiffOptions) -> Result<Change> {
+        Change::data_difference(self, other, options)
+    }
+}
+
+/// Locations of changes applied to and rejected by data in `ReflectIntermediate::try_patch_change`.
+#[derive(Debug, Default, Clone)]
+pub struct PatchReport {
+    /// Locations of applied changes, which are paths of changed parts of data.
+    pub applied: Vec<IntermediatePath>,
+    /// Locations of rejected changes, with reasons.
+    pub rejected: Vec<(IntermediatePath, Error)>,
+    location: IntermediatePath,
+}
+
+impl PatchReport {
+    pub fn is_ok(&self) -> bool {
+        self.rejected.is_empty()
+    }
+
+    /// Location of currently patched data.
+    pub fn location(&self) -> &IntermediatePath {
+        &self.location
+    }
+
+    /// Runs `f` with report located at child of currently patched data.
+    pub fn nested<T>(
+        &mut self,
+        segment: IntermediatePathSegment,
+        f: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        self.location.push(segment);
+        let result = f(self);
+        self.location.pop();
+        result
+    }
+
+    /// Tells if data under `path` got changed, either by change applied to part of it or by
+    /// replacement of data containing it.
+    ///
+    /// # Example
+    /// ```rust
+    /// use serde::{Serialize, Deserialize};
+    /// use serde_intermediate::{Change, ReflectIntermediate};
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate)]
+    /// struct Mesh {
+    ///     name: String,
+    ///     vertices: Vec<f32>,
+    /// }
+    ///
+    /// let mut data = Mesh { name: "a".to_owned(), vertices: vec![0.0, 0.0] };
+    /// let change = Change::partial_struct().partial_struct_item(
+    ///     "vertices",
+    ///     Change::partial_seq().partial_seq_item(1, Change::changed(1.0f32)),
+    /// );
+    /// let report = data.try_patch_change(&change);
+    /// assert!(report.is_changed(&"vertices".parse().unwrap()));
+    /// assert!(report.is_changed(&"vertices[1]".parse().unwrap()));
+    /// assert!(!report.is_changed(&"vertices[0]".parse().unwrap()));
+    /// assert!(!report.is_changed(&"name".parse().unwrap()));
+    /// ```
+    pub fn is_changed(&self, path: &IntermediatePath) -> bool {
+        self.is_changed_since(0, path)
+    }
+
+    /// Same as `is_changed`, but considers only changes applied after first `start` ones.
+    pub fn is_changed_since(&self, start: usize, path: &IntermediatePath) -> bool {
+        self.applied
+            .iter()
+            .skip(start)
+            .any(|applied| applied.starts_with(path) || path.starts_with(applied))
+    }
+
+    pub fn apply(&mut self) {
+        self.applied.push(self.location.to_owned());
+    }
+
+    pub fn reject(&mut self, error: Error) {
+        self.rejected.push((self.location.to_owned(), error));
+    }
+
+    pub fn reject_invalid(&mut self, reason: impl ToString) {
+        self.reject(Error::InvalidPatch(
+            self.location.to_owned(),
+            reason.to_string(),
+        ));
+    }
+
+    /// Rejects changes with errors located relative to currently patched data.
+    pub(crate) fn reject_nested(&mut self, errors: Vec<(IntermediatePath, Error)>) {
+        for (path, error) in errors {
+            let path = self.location.join(&path);
+            let error = match error {
+                Error::InvalidPatch(_, reason) => Error::InvalidPatch(path.to_owned(), reason),
+                error => error,
+            };
+            self.rejected.push((path, error));
+        }
+    }
+
+    pub fn reject_missing(&mut self) {
+        self.reject(Error::PathNotFound(self.location.to_owned()));
+    }
+
+    pub fn reject_unsupported(&mut self, change: &Change) {
+        let kind = match change {
+            Change::Same => "same",
+            Change::Removed => "removed",
+            Change::Changed(_) => "changed",
+            Change::Added(_) => "added",
+            Change::Moved(_) => "moved",
+            Change::PartialChange(_) => "partial change",
+            Change::PartialSeq(_) => "partial sequence",
+            Change::PartialMap(_) => "partial map",
+            Change::PartialStruct(_) => "partial structure",
+        };
+        self.reject_invalid(format!("unsupported {} change", kind));
+    }
+
+    /// Replaces `target` with deserialized `value`.
+    pub fn replace<T>(&mut self, target: &mut T, value: &Intermediate)
+    where
+        T: DeserializeOwned,
+    {
+        match from_intermediate(value) {
+            Ok(value) => {
+                *target = value;
+                self.apply();
+            }
+            Err(error) => self.reject(error),
+        }
+    }
+
+    /// Patches `target` through its serialized form, as done for indirect fields.
+    pub fn patch_indirect<T>(&mut self, target: &mut T, change: &Change)
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        if let Some(patched) = self.patch_serialized(target, change) {
+            *target = patched;
+        }
+    }
+
+    /// Applies `change` to serialized copy of `target`, for parts of data that can be patched
+    /// only through their serialized form (such as `#[serde(flatten)]` fields).
+    /// Returns patched copy of `target` to take patched parts from, or rejects every operation
+    /// that doesn't match `target`.
+    pub fn patch_serialized<T>(&mut self, target: &T, change: &Change) -> Option<T>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let serialized = match to_intermediate(target) {
+            Ok(serialized) => serialized,
+            Err(error) => {
+                self.reject(error);
+                return None;
+            }
+        };
+        let (patched, errors) = change.patch_collect(&serialized);
+        if !errors.is_empty() {
+            self.reject_nested(errors);
+            return None;
+        }
+        let patched = match patched {
+            Some(patched) => from_intermediate(&patched),
+            None => Err(Error::CannotRemove(Intermediate::Unit)),
+        };
+        match patched {
+            Ok(patched) => {
+                self.apply();
+                Some(patched)
+            }
+            Err(error) => {
+                self.reject(error);
+                None
+            }
+        }
+    }
+}
+
 macro_rules! impl_reflect {
     (@atom $type:ty => $( $variant:ident ),+ ) => {
         impl ReflectIntermediate for $type {
             fn patch_change(&mut self, change: &Change) {
-                #[allow(clippy::collapsible_match)]
-                if let Change::Changed(v) = change {
-                    match v {
+                self.patch_change_with_report(change, &mut Default::default());
+            }
+
+            fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+                match change {
+                    Change::Same => {}
+                    Change::Changed(v) => match v {
                         $(
                             #[allow(irrefutable_let_patterns)]
-                            Intermediate::$variant(v) => if let Ok(v) = Self::try_from(*v) {
-                                *self = v;
+                            Intermediate::$variant(v) => match Self::try_from(*v) {
+                                Ok(v) => {
+                                    *self = v;
+                                    report.apply();
+                                }
+                                Err(_) => report.reject_invalid("value out of range"),
                             }
                         )+
-                        _ => {}
-                    }
+                        _ => report.reject_invalid("incompatible value"),
+                    },
+                    change => report.reject_unsupported(change),
                 }
             }
+
+            fn clone_for_patch(&self) -> Option<Self> {
+                Some(*self)
+            }
         }
     };
     (@cast $type:ty => $cast:ty => $( $variant:ident ),+ ) => {
         impl ReflectIntermediate for $type {
             fn patch_change(&mut self, change: &Change) {
-                #[allow(clippy::collapsible_match)]
-                if let Change::Changed(v) = change {
-                    match v {
+                self.patch_change_with_report(change, &mut Default::default());
+            }
+
+            fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+                match change {
+                    Change::Same => {}
+                    Change::Changed(v) => match v {
                         $(
                             #[allow(irrefutable_let_patterns)]
-                            Intermediate::$variant(v) => if let Ok(v) = <$cast>::try_from(*v) {
-                                if let Ok(v) = Self::try_from(v) {
+                            Intermediate::$variant(v) => match <$cast>::try_from(*v)
+                                .ok()
+                                .and_then(|v| Self::try_from(v).ok())
+                            {
+                                Some(v) => {
                                     *self = v.into();
+                                    report.apply();
                                 }
+                                None => report.reject_invalid("value out of range"),
                             }
                         )+
-                        _ => {}
-                    }
+                        _ => report.reject_invalid("incompatible value"),
+                    },
+                    change => report.reject_unsupported(change),
                 }
             }
         }
     };
 }
 
-impl ReflectIntermediate for () {}
-impl<T> ReflectIntermediate for PhantomData<T> {}
+impl ReflectIntermediate for () {
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+        patch_unit_with_report(change, report);
+    }
+
+    fn clone_for_patch(&self) -> Option<Self> {
+        Some(())
+    }
+}
+
+impl<T> ReflectIntermediate for PhantomData<T> {
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+        patch_unit_with_report(change, report);
+    }
+
+    fn clone_for_patch(&self) -> Option<Self> {
+        Some(PhantomData)
+    }
+}
 
 impl_reflect!(@atom bool => Bool);
 impl_reflect!(@atom i8 => Bool, I8, I16, I32, I64, I128, U8, U16, U32, U64, U128);
@@ -131,26 +465,54 @@ impl_reflect!(@cast NonZeroUsize => usize => Bool, I8, I16, I32, I64, I128, U8,
 
 impl ReflectIntermediate for String {
     fn patch_change(&mut self, change: &Change) {
-        if let Change::Changed(v) = change {
-            match v {
-                Intermediate::Char(v) => *self = v.to_string(),
-                Intermediate::String(v) => *self = v.to_owned(),
-                _ => {}
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+        match change {
+            Change::Same => {}
+            Change::Changed(Intermediate::Char(v)) => {
+                *self = v.to_string();
+                report.apply();
+            }
+            Change::Changed(Intermediate::String(v)) => {
+                *self = v.to_owned();
+                report.apply();
             }
+            Change::Changed(_) => report.reject_invalid("incompatible value"),
+            change => report.reject_unsupported(change),
         }
     }
+
+    fn clone_for_patch(&self) -> Option<Self> {
+        Some(self.clone())
+    }
 }
 
 impl ReflectIntermediate for PathBuf {
     fn patch_change(&mut self, change: &Change) {
-        if let Change::Changed(v) = change {
-            match v {
-                Intermediate::Char(v) => *self = v.to_string().into(),
-                Intermediate::String(v) => *self = v.into(),
-                _ => {}
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+        match change {
+            Change::Same => {}
+            Change::Changed(Intermediate::Char(v)) => {
+                *self = v.to_string().into();
+                report.apply();
+            }
+            Change::Changed(Intermediate::String(v)) => {
+                *self = v.into();
+                report.apply();
             }
+            Change::Changed(_) => report.reject_invalid("incompatible value"),
+            change => report.reject_unsupported(change),
         }
     }
+
+    fn clone_for_patch(&self) -> Option<Self> {
+        Some(self.clone())
+    }
 }
 
 impl<T, const N: usize> ReflectIntermediate for [T; N]
@@ -158,24 +520,45 @@ where
     T: ReflectIntermediate + DeserializeOwned,
 {
     fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
         match change {
-            Change::Changed(Intermediate::Seq(v)) => {
-                for (item, v) in self.iter_mut().zip(v.iter()) {
-                    if let Ok(v) = from_intermediate(v) {
-                        *item = v;
+            Change::Same => {}
+            Change::Changed(Intermediate::Seq(v) | Intermediate::Tuple(v)) if v.len() == N => {
+                match v.iter().map(from_intermediate).collect::<Result<Vec<T>>>() {
+                    Ok(items) => {
+                        for (item, v) in self.iter_mut().zip(items) {
+                            *item = v;
+                        }
+                        report.apply();
                     }
+                    Err(error) => report.reject(error),
                 }
             }
+            Change::Changed(_) => report.reject_invalid("incompatible value"),
             Change::PartialSeq(v) => {
                 for (index, change) in v {
-                    if *index < N {
-                        self[*index].patch_change(change);
-                    }
+                    report.nested(IntermediatePathSegment::Index(*index), |report| match self
+                        .get_mut(*index)
+                    {
+                        Some(item) => item.patch_change_with_report(change, report),
+                        None => report.reject_missing(),
+                    });
                 }
             }
-            _ => {}
+            change => report.reject_unsupported(change),
         }
     }
+
+    fn clone_for_patch(&self) -> Option<Self> {
+        let items = self
+            .iter()
+            .map(|item| item.clone_for_patch())
+            .collect::<Option<Vec<_>>>()?;
+        items.try_into().ok()
+    }
 }
 
 impl<T> ReflectIntermediate for (T,)
@@ -183,24 +566,31 @@ where
     T: ReflectIntermediate + DeserializeOwned,
 {
     fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
         match change {
-            Change::Changed(Intermediate::Seq(v)) => {
-                if let Some(v) = v.first() {
-                    if let Ok(v) = from_intermediate(v) {
-                        self.0 = v;
-                    }
-                }
-            }
+            Change::Same => {}
+            Change::Changed(v) => report.replace(self, v),
             Change::PartialSeq(v) => {
                 for (index, change) in v {
-                    if *index == 0 {
-                        self.0.patch_change(change);
-                    }
+                    report.nested(IntermediatePathSegment::Index(*index), |report| {
+                        if *index == 0 {
+                            self.0.patch_change_with_report(change, report);
+                        } else {
+                            report.reject_missing();
+                        }
+                    });
                 }
             }
-            _ => {}
+            change => report.reject_unsupported(change),
         }
     }
+
+    fn clone_for_patch(&self) -> Option<Self> {
+        Some((self.0.clone_for_patch()?,))
+    }
 }
 
 macro_rules! impl_tuple {
@@ -210,26 +600,44 @@ macro_rules! impl_tuple {
             $( $id: ReflectIntermediate + DeserializeOwned ),+
         {
             fn patch_change(&mut self, change: &Change) {
+                self.patch_change_with_report(change, &mut Default::default());
+            }
+
+            fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
                 match change {
-                    Change::Changed(Intermediate::Seq(v)) => {
-                        $(
-                            if let Some(v) = v.get($index) {
-                                if let Ok(v) = from_intermediate(v) {
-                                    self.$index = v;
-                                }
+                    Change::Same => {}
+                    Change::Changed(Intermediate::Seq(v) | Intermediate::Tuple(v))
+                        if v.len() == [ $( $index ),+ ].len() =>
+                    {
+                        let items = || -> Result<Self> {
+                            Ok(( $( from_intermediate::<$id>(&v[$index])? ),+ ))
+                        };
+                        match items() {
+                            Ok(items) => {
+                                *self = items;
+                                report.apply();
                             }
-                        )+
+                            Err(error) => report.reject(error),
+                        }
                     }
+                    Change::Changed(_) => report.reject_invalid("incompatible value"),
                     Change::PartialSeq(v) => {
-                        $(
-                            if let Some((_,change)) = v.iter().find(|(i,_)| *i == $index) {
-                                self.$index.patch_change(change);
-                            }
-                        )+
+                        for (index, change) in v {
+                            report.nested(IntermediatePathSegment::Index(*index), |report| {
+                                match *index {
+                                    $( $index => self.$index.patch_change_with_report(change, report), )+
+                                    _ => report.reject_missing(),
+                                }
+                            });
+                        }
                     }
-                    _ => {}
+                    change => report.reject_unsupported(change),
                 }
             }
+
+            fn clone_for_patch(&self) -> Option<Self> {
+                Some(( $( self.$index.clone_for_patch()? ),+ ))
+            }
         }
     };
 }
@@ -264,34 +672,21 @@ where
     T: ReflectIntermediate + DeserializeOwned,
 {
     fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
         match change {
-            Change::Changed(v) => {
-                if let Ok(v) = from_intermediate(v) {
-                    *self = v;
-                }
-            }
-            Change::PartialSeq(v) => {
-                for (index, change) in v {
-                    match change {
-                        Change::Removed => {
-                            self.remove(*index);
-                        }
-                        Change::Added(v) => {
-                            if let Ok(v) = from_intermediate(v) {
-                                self.insert(*index, v);
-                            }
-                        }
-                        change => {
-                            if let Some(item) = self.get_mut(*index) {
-                                item.patch_change(change);
-                            }
-                        }
-                    }
-                }
-            }
-            _ => {}
+            Change::Same => {}
+            Change::Changed(v) => report.replace(self, v),
+            Change::PartialSeq(v) => patch_items_with_report(self, v, report),
+            change => report.reject_unsupported(change),
         }
     }
+
+    fn clone_for_patch(&self) -> Option<Self> {
+        self.iter().map(|item| item.clone_for_patch()).collect()
+    }
 }
 
 impl<T> ReflectIntermediate for VecDeque<T>
@@ -299,34 +694,25 @@ where
     T: ReflectIntermediate + DeserializeOwned,
 {
     fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
         match change {
-            Change::Changed(v) => {
-                if let Ok(v) = from_intermediate(v) {
-                    *self = v;
-                }
-            }
+            Change::Same => {}
+            Change::Changed(v) => report.replace(self, v),
             Change::PartialSeq(v) => {
-                for (index, change) in v {
-                    match change {
-                        Change::Removed => {
-                            self.remove(*index);
-                        }
-                        Change::Added(v) => {
-                            if let Ok(v) = from_intermediate(v) {
-                                self.insert(*index, v);
-                            }
-                        }
-                        change => {
-                            if let Some(item) = self.get_mut(*index) {
-                                item.patch_change(change);
-                            }
-                        }
-                    }
-                }
+                let mut data = self.drain(..).collect::<Vec<_>>();
+                patch_items_with_report(&mut data, v, report);
+                *self = data.into_iter().collect();
             }
-            _ => {}
+            change => report.reject_unsupported(change),
         }
     }
+
+    fn clone_for_patch(&self) -> Option<Self> {
+        self.iter().map(|item| item.clone_for_patch()).collect()
+    }
 }
 
 impl<T> ReflectIntermediate for HashSet<T>
@@ -334,36 +720,25 @@ where
     T: ReflectIntermediate + DeserializeOwned + Hash + Eq + Clone,
 {
     fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
         match change {
-            Change::Changed(v) => {
-                if let Ok(v) = from_intermediate(v) {
-                    *self = v;
-                }
-            }
+            Change::Same => {}
+            Change::Changed(v) => report.replace(self, v),
             Change::PartialSeq(v) => {
                 let mut data = self.iter().cloned().collect::<Vec<_>>();
-                for (index, change) in v {
-                    match change {
-                        Change::Removed => {
-                            data.remove(*index);
-                        }
-                        Change::Added(v) => {
-                            if let Ok(v) = from_intermediate(v) {
-                                data.insert(*index, v);
-                            }
-                        }
-                        change => {
-                            if let Some(item) = data.get_mut(*index) {
-                                item.patch_change(change);
-                            }
-                        }
-                    }
-                }
+                patch_items_with_report(&mut data, v, report);
                 *self = data.into_iter().collect();
             }
-            _ => {}
+            change => report.reject_unsupported(change),
         }
     }
+
+    fn clone_for_patch(&self) -> Option<Self> {
+        Some(self.clone())
+    }
 }
 
 impl<T> ReflectIntermediate for BTreeSet<T>
@@ -371,36 +746,25 @@ where
     T: ReflectIntermediate + DeserializeOwned + Ord + Clone,
 {
     fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
         match change {
-            Change::Changed(v) => {
-                if let Ok(v) = from_intermediate(v) {
-                    *self = v;
-                }
-            }
+            Change::Same => {}
+            Change::Changed(v) => report.replace(self, v),
             Change::PartialSeq(v) => {
                 let mut data = self.iter().cloned().collect::<Vec<_>>();
-                for (index, change) in v {
-                    match change {
-                        Change::Removed => {
-                            data.remove(*index);
-                        }
-                        Change::Added(v) => {
-                            if let Ok(v) = from_intermediate(v) {
-                                data.insert(*index, v);
-                            }
-                        }
-                        change => {
-                            if let Some(item) = data.get_mut(*index) {
-                                item.patch_change(change);
-                            }
-                        }
-                    }
-                }
+                patch_items_with_report(&mut data, v, report);
                 *self = data.into_iter().collect();
             }
-            _ => {}
+            change => report.reject_unsupported(change),
         }
     }
+
+    fn clone_for_patch(&self) -> Option<Self> {
+        Some(self.clone())
+    }
 }
 
 impl<T> ReflectIntermediate for LinkedList<T>
@@ -408,36 +772,25 @@ where
     T: ReflectIntermediate + DeserializeOwned + Clone,
 {
     fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
         match change {
-            Change::Changed(v) => {
-                if let Ok(v) = from_intermediate(v) {
-                    *self = v;
-                }
-            }
+            Change::Same => {}
+            Change::Changed(v) => report.replace(self, v),
             Change::PartialSeq(v) => {
                 let mut data = self.iter().cloned().collect::<Vec<_>>();
-                for (index, change) in v {
-                    match change {
-                        Change::Removed => {
-                            data.remove(*index);
-                        }
-                        Change::Added(v) => {
-                            if let Ok(v) = from_intermediate(v) {
-                                data.insert(*index, v);
-                            }
-                        }
-                        change => {
-                            if let Some(item) = data.get_mut(*index) {
-                                item.patch_change(change);
-                            }
-                        }
-                    }
-                }
+                patch_items_with_report(&mut data, v, report);
                 *self = data.into_iter().collect();
             }
-            _ => {}
+            change => report.reject_unsupported(change),
         }
     }
+
+    fn clone_for_patch(&self) -> Option<Self> {
+        Some(self.clone())
+    }
 }
 
 impl<T> ReflectIntermediate for BinaryHeap<T>
@@ -445,110 +798,115 @@ where
     T: ReflectIntermediate + DeserializeOwned + Ord + Clone,
 {
     fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
         match change {
-            Change::Changed(v) => {
-                if let Ok(v) = from_intermediate(v) {
-                    *self = v;
-                }
-            }
+            Change::Same => {}
+            Change::Changed(v) => report.replace(self, v),
             Change::PartialSeq(v) => {
                 let mut data = self.iter().cloned().collect::<Vec<_>>();
-                for (index, change) in v {
-                    match change {
-                        Change::Removed => {
-                            data.remove(*index);
-                        }
-                        Change::Added(v) => {
-                            if let Ok(v) = from_intermediate(v) {
-                                data.insert(*index, v);
-                            }
-                        }
-                        change => {
-                            if let Some(item) = data.get_mut(*index) {
-                                item.patch_change(change);
-                            }
-                        }
-                    }
-                }
+                patch_items_with_report(&mut data, v, report);
                 *self = data.into_iter().collect();
             }
-            _ => {}
+            change => report.reject_unsupported(change),
         }
     }
+
+    fn clone_for_patch(&self) -> Option<Self> {
+        Some(self.clone())
+    }
 }
 
 impl<K, V> ReflectIntermediate for HashMap<K, V>
 where
-    K: ReflectIntermediate + DeserializeOwned + Hash + Eq,
+    K: DeserializeOwned + Hash + Eq,
     V: ReflectIntermediate + DeserializeOwned,
 {
     fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
         match change {
-            Change::Changed(v) => {
-                if let Ok(v) = from_intermediate(v) {
-                    *self = v;
-                }
-            }
+            Change::Same => {}
+            Change::Changed(v) => report.replace(self, v),
             Change::PartialMap(v) => {
                 for (key, change) in v {
-                    if let Ok(key) = from_intermediate(key) {
+                    report.nested(IntermediatePathSegment::Key(key.to_owned()), |report| {
+                        let key = match from_intermediate(key) {
+                            Ok(key) => key,
+                            Err(error) => return report.reject(error),
+                        };
                         match change {
-                            Change::Removed => {
-                                self.remove(&key);
-                            }
-                            Change::Added(v) => {
-                                if let Ok(v) = from_intermediate(v) {
+                            Change::Same => {}
+                            Change::Removed => match self.remove(&key) {
+                                Some(_) => report.apply(),
+                                None => report.reject_missing(),
+                            },
+                            Change::Added(v) => match from_intermediate(v) {
+                                Ok(v) => {
                                     self.insert(key, v);
+                                    report.apply();
                                 }
-                            }
-                            change => {
-                                if let Some(item) = self.get_mut(&key) {
-                                    item.patch_change(change);
-                                }
-                            }
+                                Err(error) => report.reject(error),
+                            },
+                            change => match self.get_mut(&key) {
+                                Some(item) => item.patch_change_with_report(change, report),
+                                None => report.reject_missing(),
+                            },
                         }
-                    }
+                    });
                 }
             }
-            _ => {}
+            change => report.reject_unsupported(change),
         }
     }
 }
 
 impl<K, V> ReflectIntermediate for BTreeMap<K, V>
 where
-    K: ReflectIntermediate + DeserializeOwned + Ord,
+    K: DeserializeOwned + Ord,
     V: ReflectIntermediate + DeserializeOwned,
 {
     fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
         match change {
-            Change::Changed(v) => {
-                if let Ok(v) = from_intermediate(v) {
-                    *self = v;
-                }
-            }
+            Change::Same => {}
+            Change::Changed(v) => report.replace(self, v),
             Change::PartialMap(v) => {
                 for (key, change) in v {
-                    if let Ok(key) = from_intermediate(key) {
+                    report.nested(IntermediatePathSegment::Key(key.to_owned()), |report| {
+                        let key = match from_intermediate(key) {
+                            Ok(key) => key,
+                            Err(error) => return report.reject(error),
+                        };
                         match change {
-                            Change::Removed => {
-                                self.remove(&key);
-                            }
-                            Change::Added(v) => {
-                                if let Ok(v) = from_intermediate(v) {
+                            Change::Same => {}
+                            Change::Removed => match self.remove(&key) {
+                                Some(_) => report.apply(),
+                                None => report.reject_missing(),
+                            },
+                            Change::Added(v) => match from_intermediate(v) {
+                                Ok(v) => {
                                     self.insert(key, v);
+                                    report.apply();
                                 }
-                            }
-                            change => {
-                                if let Some(item) = self.get_mut(&key) {
-                                    item.patch_change(change);
-                                }
-                            }
+                                Err(error) => report.reject(error),
+                            },
+                            change => match self.get_mut(&key) {
+                                Some(item) => item.patch_change_with_report(change, report),
+                                None => report.reject_missing(),
+                            },
                         }
-                    }
+                    });
                 }
             }
-            _ => {}
+            change => report.reject_unsupported(change),
         }
     }
 }
@@ -558,17 +916,15 @@ where
     T: ReflectIntermediate + DeserializeOwned,
 {
     fn patch_change(&mut self, change: &Change) {
-        match change {
-            Change::Changed(v) => {
-                if let Ok(v) = from_intermediate(v) {
-                    *self = v;
-                }
-            }
-            Change::PartialChange(change) => {
-                self.patch_change(change);
-            }
-            _ => {}
-        }
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+        (**self).patch_change_with_report(change, report);
+    }
+
+    fn clone_for_patch(&self) -> Option<Self> {
+        Some(Box::new((**self).clone_for_patch()?))
     }
 }
 
@@ -577,39 +933,58 @@ where
     T: ReflectIntermediate + DeserializeOwned,
 {
     fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
         match change {
-            Change::Changed(v) => {
-                if let Ok(v) = from_intermediate(v) {
-                    *self = v;
-                }
-            }
+            Change::Same => {}
+            Change::Changed(v) => report.replace(self, v),
             Change::PartialChange(change) => {
-                if let Some(content) = self {
-                    content.patch_change(change);
-                }
+                report.nested(IntermediatePathSegment::Inner, |report| match self {
+                    Some(content) => content.patch_change_with_report(change, report),
+                    None => report.reject_missing(),
+                });
             }
-            _ => {}
+            change => report.reject_unsupported(change),
+        }
+    }
+
+    fn clone_for_patch(&self) -> Option<Self> {
+        match self {
+            Some(content) => Some(Some(content.clone_for_patch()?)),
+            None => Some(None),
         }
     }
 }
 
-impl<T, E> ReflectIntermediate for Result<T, E>
+impl<T, E> ReflectIntermediate for std::result::Result<T, E>
 where
     T: ReflectIntermediate + DeserializeOwned,
     E: ReflectIntermediate + DeserializeOwned,
 {
     fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
         match change {
-            Change::Changed(v) => {
-                if let Ok(v) = from_intermediate(v) {
-                    *self = v;
-                }
+            Change::Same => {}
+            Change::Changed(v) => report.replace(self, v),
+            Change::PartialChange(change) => {
+                report.nested(IntermediatePathSegment::Inner, |report| match self {
+                    Ok(content) => content.patch_change_with_report(change, report),
+                    Err(content) => content.patch_change_with_report(change, report),
+                });
             }
-            Change::PartialChange(change) => match self {
-                Ok(content) => content.patch_change(change),
-                Err(content) => content.patch_change(change),
-            },
-            _ => {}
+            change => report.reject_unsupported(change),
+        }
+    }
+
+    fn clone_for_patch(&self) -> Option<Self> {
+        match self {
+            Ok(content) => Some(Ok(content.clone_for_patch()?)),
+            Err(error) => Some(Err(error.clone_for_patch()?)),
         }
     }
 }
@@ -619,17 +994,11 @@ where
     T: ReflectIntermediate + DeserializeOwned,
 {
     fn patch_change(&mut self, change: &Change) {
-        match change {
-            Change::Changed(v) => {
-                if let Ok(v) = from_intermediate(v) {
-                    self.set(v);
-                }
-            }
-            Change::PartialChange(change) => {
-                self.patch_change(change);
-            }
-            _ => {}
-        }
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+        self.get_mut().patch_change_with_report(change, report);
     }
 }
 
@@ -638,20 +1007,213 @@ where
     T: ReflectIntermediate + DeserializeOwned,
 {
     fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+        match self.get_mut() {
+            Ok(content) => content.patch_change_with_report(change, report),
+            Err(_) => report.reject_invalid("mutex is poisoned"),
+        }
+    }
+
+    fn patch_shared_change_with_report(&self, change: &Change, report: &mut PatchReport) -> bool {
+        patch_mutex(self, change, report);
+        true
+    }
+}
+
+impl<T> ReflectIntermediate for RwLock<T>
+where
+    T: ReflectIntermediate,
+{
+    fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+        match self.get_mut() {
+            Ok(content) => content.patch_change_with_report(change, report),
+            Err(_) => report.reject_invalid("lock is poisoned"),
+        }
+    }
+
+    fn patch_shared_change_with_report(&self, change: &Change, report: &mut PatchReport) -> bool {
+        patch_rw_lock(self, change, report);
+        true
+    }
+}
+
+impl<T> ReflectIntermediate for RefCell<T>
+where
+    T: ReflectIntermediate,
+{
+    fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+        self.get_mut().patch_change_with_report(change, report);
+    }
+
+    fn patch_shared_change_with_report(&self, change: &Change, report: &mut PatchReport) -> bool {
+        patch_ref_cell(self, change, report);
+        true
+    }
+}
+
+/// Empty cell can only get initialized by replacing its whole content.
+impl<T> ReflectIntermediate for OnceCell<T>
+where
+    T: ReflectIntermediate + DeserializeOwned,
+{
+    fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+        if let Some(content) = self.get_mut() {
+            return content.patch_change_with_report(change, report);
+        }
         match change {
-            Change::Changed(v) => {
-                if let Ok(v) = from_intermediate(v) {
-                    if let Ok(ref mut mutex) = self.try_lock() {
-                        **mutex = v;
-                    }
+            Change::Same => {}
+            Change::Changed(v) => match from_intermediate(v) {
+                Ok(v) => {
+                    let _ = self.set(v);
+                    report.apply();
+                }
+                Err(error) => report.reject(error),
+            },
+            _ => report.reject_missing(),
+        }
+    }
+}
+
+/// Patches borrowed value by turning it into owned one first.
+impl<T> ReflectIntermediate for Cow<'_, T>
+where
+    T: ToOwned + ?Sized,
+    T::Owned: ReflectIntermediate,
+{
+    fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+        if !change.is_same() {
+            self.to_mut().patch_change_with_report(change, report);
+        }
+    }
+
+    fn clone_for_patch(&self) -> Option<Self> {
+        Some(self.clone())
+    }
+}
+
+/// Patches value of its only owner. Value shared with other owners gets patched in place for
+/// all of them if it is shared for mutation (such as `Rc<RefCell<T>>`), otherwise it gets
+/// patched copy-on-write, like with `Rc::make_mut`, so other owners keep unchanged value.
+/// Shared values that can't be copied (see `ReflectIntermediate::clone_for_patch`) are left
+/// unchanged.
+impl<T> ReflectIntermediate for Rc<T>
+where
+    T: ReflectIntermediate,
+{
+    fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+        if change.is_same() {
+            return;
+        }
+        match Rc::get_mut(self) {
+            Some(content) => content.patch_change_with_report(change, report),
+            None => {
+                if let Some(content) = patch_shared(&**self, change, report) {
+                    *self = Rc::new(content);
                 }
             }
-            Change::PartialChange(change) => {
-                self.patch_change(change);
+        }
+    }
+
+    fn clone_for_patch(&self) -> Option<Self> {
+        Some(self.clone())
+    }
+}
+
+/// Patches value of its only owner. Value shared with other owners gets patched in place for
+/// all of them if it is shared for mutation (such as `Arc<RwLock<T>>`), otherwise it gets
+/// patched copy-on-write, like with `Arc::make_mut`, so other owners keep unchanged value.
+/// Shared values that can't be copied (see `ReflectIntermediate::clone_for_patch`) are left
+/// unchanged.
+impl<T> ReflectIntermediate for Arc<T>
+where
+    T: ReflectIntermediate,
+{
+    fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+        if change.is_same() {
+            return;
+        }
+        match Arc::get_mut(self) {
+            Some(content) => content.patch_change_with_report(change, report),
+            None => {
+                if let Some(content) = patch_shared(&**self, change, report) {
+                    *self = Arc::new(content);
+                }
             }
-            _ => {}
         }
     }
+
+    fn clone_for_patch(&self) -> Option<Self> {
+        Some(self.clone())
+    }
+}
+
+/// Patches value in place for all its owners, blocking until lock is acquired.
+impl<T> ReflectIntermediate for &Mutex<T>
+where
+    T: ReflectIntermediate,
+{
+    fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+        patch_mutex(self, change, report);
+    }
+}
+
+/// Patches value in place for all its owners, blocking until lock is acquired.
+impl<T> ReflectIntermediate for &RwLock<T>
+where
+    T: ReflectIntermediate,
+{
+    fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+        patch_rw_lock(self, change, report);
+    }
+}
+
+/// Patches value in place for all its owners, unless it is already borrowed.
+impl<T> ReflectIntermediate for &RefCell<T>
+where
+    T: ReflectIntermediate,
+{
+    fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+        patch_ref_cell(self, change, report);
+    }
 }
 
 impl<T> ReflectIntermediate for Range<T>
@@ -659,22 +1221,26 @@ where
     T: ReflectIntermediate + DeserializeOwned,
 {
     fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
         match change {
-            Change::Changed(v) => {
-                if let Ok(v) = from_intermediate(v) {
-                    *self = v;
-                }
-            }
+            Change::Same => {}
+            Change::Changed(v) => report.replace(self, v),
             Change::PartialStruct(v) => {
                 for (key, change) in v {
-                    match key.as_str() {
-                        "start" => self.start.patch_change(change),
-                        "end" => self.end.patch_change(change),
-                        _ => {}
-                    }
+                    report.nested(
+                        IntermediatePathSegment::Field(key.to_owned()),
+                        |report| match key.as_str() {
+                            "start" => self.start.patch_change_with_report(change, report),
+                            "end" => self.end.patch_change_with_report(change, report),
+                            _ => report.reject_missing(),
+                        },
+                    );
                 }
             }
-            _ => {}
+            change => report.reject_unsupported(change),
         }
     }
 }
@@ -684,24 +1250,672 @@ where
     T: ReflectIntermediate + DeserializeOwned + Clone,
 {
     fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
         match change {
-            Change::Changed(v) => {
-                if let Ok(v) = from_intermediate(v) {
-                    *self = v;
-                }
-            }
+            Change::Same => {}
+            Change::Changed(v) => report.replace(self, v),
             Change::PartialStruct(v) => {
                 let (mut start, mut end) = self.clone().into_inner();
                 for (key, change) in v {
-                    match key.as_str() {
-                        "start" => start.patch_change(change),
-                        "end" => end.patch_change(change),
-                        _ => {}
-                    }
+                    report.nested(
+                        IntermediatePathSegment::Field(key.to_owned()),
+                        |report| match key.as_str() {
+                            "start" => start.patch_change_with_report(change, report),
+                            "end" => end.patch_change_with_report(change, report),
+                            _ => report.reject_missing(),
+                        },
+                    );
                 }
                 *self = Self::new(start, end);
             }
-            _ => {}
+            change => report.reject_unsupported(change),
         }
     }
 }
+
+fn patch_unit_with_report(change: &Change, report: &mut PatchReport) {
+    match change {
+        Change::Same => {}
+        Change::Changed(Intermediate::Unit | Intermediate::UnitStruct) => report.apply(),
+        Change::Changed(_) => report.reject_invalid("incompatible value"),
+        change => report.reject_unsupported(change),
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<K, V> ReflectIntermediate for indexmap::IndexMap<K, V>
+where
+    K: DeserializeOwned + Hash + Eq,
+    V: ReflectIntermediate + DeserializeOwned,
+{
+    fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+        match change {
+            Change::Same => {}
+            Change::Changed(v) => report.replace(self, v),
+            Change::PartialMap(v) => {
+                for (key, change) in v {
+                    report.nested(IntermediatePathSegment::Key(key.to_owned()), |report| {
+                        let key = match from_intermediate(key) {
+                            Ok(key) => key,
+                            Err(error) => return report.reject(error),
+                        };
+                        match change {
+                            Change::Same => {}
+                            Change::Removed => match self.shift_remove(&key) {
+                                Some(_) => report.apply(),
+                                None => report.reject_missing(),
+                            },
+                            Change::Added(v) => match from_intermediate(v) {
+                                Ok(v) => {
+                                    self.insert(key, v);
+                                    report.apply();
+                                }
+                                Err(error) => report.reject(error),
+                            },
+                            change => match self.get_mut(&key) {
+                                Some(item) => item.patch_change_with_report(change, report),
+                                None => report.reject_missing(),
+                            },
+                        }
+                    });
+                }
+            }
+            change => report.reject_unsupported(change),
+        }
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<T> ReflectIntermediate for indexmap::IndexSet<T>
+where
+    T: ReflectIntermediate + DeserializeOwned + Hash + Eq,
+{
+    fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+        match change {
+            Change::Same => {}
+            Change::Changed(v) => report.replace(self, v),
+            Change::PartialSeq(v) => {
+                let mut data = self.drain(..).collect::<Vec<_>>();
+                patch_items_with_report(&mut data, v, report);
+                *self = data.into_iter().collect();
+            }
+            change => report.reject_unsupported(change),
+        }
+    }
+}
+
+#[cfg(feature = "smallvec")]
+impl<A> ReflectIntermediate for smallvec::SmallVec<A>
+where
+    A: smallvec::Array,
+    A::Item: ReflectIntermediate + DeserializeOwned,
+{
+    fn patch_change(&mut self, change: &Change) {
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+        match change {
+            Change::Same => {}
+            Change::Changed(v) => report.replace(self, v),
+            Change::PartialSeq(v) => {
+                let mut data = std::mem::take(self).into_vec();
+                patch_items_with_report(&mut data, v, report);
+                *self = Self::from_vec(data);
+            }
+            change => report.reject_unsupported(change),
+        }
+    }
+}
+
+fn patch_items_with_report<T>(
+    data: &mut Vec<T>,
+    changes: &[(usize, Change)],
+    report: &mut PatchReport,
+) where
+    T: ReflectIntermediate + DeserializeOwned,
+{
+    for (index, change) in changes {
+        let index = *index;
+        report.nested(
+            IntermediatePathSegment::Index(index),
+            |report| match change {
+                Change::Same => {}
+                Change::Removed => {
+                    if index < data.len() {
+                        data.remove(index);
+                        report.apply();
+                    } else {
+                        report.reject_missing();
+                    }
+                }
+                Change::Added(v) => {
+                    if index <= data.len() {
+                        match from_intermediate(v) {
+                            Ok(v) => {
+                                data.insert(index, v);
+                                report.apply();
+                            }
+                            Err(error) => report.reject(error),
+                        }
+                    } else {
+                        report.reject_missing();
+                    }
+                }
+                Change::Moved(to) => {
+                    if index < data.len() && *to < data.len() {
+                        let item = data.remove(index);
+                        data.insert(*to, item);
+                        report.apply();
+                    } else {
+                        report.reject_missing();
+                    }
+                }
+                change => match data.get_mut(index) {
+                    Some(item) => item.patch_change_with_report(change, report),
+                    None => report.reject_missing(),
+                },
+            },
+        );
+    }
+}
+
+macro_rules! impl_diff {
+    (@eq $( $type:ty ),+ ) => {
+        $(
+            impl DiffIntermediate for $type {
+                fn diff_with(&self, other: &Self, options: &DiffOptions) -> Result<Change> {
+                    if !options.is_default() {
+                        return Change::data_difference(self, other, options);
+                    }
+                    if self == other {
+                        Ok(Change::Same)
+                    } else {
+                        Ok(Change::Changed(to_intermediate(other)?))
+                    }
+                }
+            }
+        )+
+    };
+    (@serialize $( $type:ty ),+ ) => {
+        $(
+            impl DiffIntermediate for $type {}
+        )+
+    };
+}
+
+impl_diff!(@eq (), bool, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+impl_diff!(@eq f32, f64, char, String);
+impl_diff!(@eq NonZeroI8, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI128, NonZeroIsize);
+impl_diff!(@eq NonZeroU8, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU128, NonZeroUsize);
+impl_diff!(@serialize AtomicBool, AtomicI8, AtomicI16, AtomicI32, AtomicI64, AtomicIsize);
+impl_diff!(@serialize AtomicU8, AtomicU16, AtomicU32, AtomicU64, AtomicUsize, PathBuf);
+
+impl<T> DiffIntermediate for PhantomData<T> {
+    fn diff_with(&self, _: &Self, _: &DiffOptions) -> Result<Change> {
+        Ok(Change::Same)
+    }
+}
+
+impl DiffIntermediate for Intermediate {
+    fn diff_with(&self, other: &Self, options: &DiffOptions) -> Result<Change> {
+        Ok(Change::difference(self, other, options))
+    }
+}
+
+impl<T, const N: usize> DiffIntermediate for [T; N]
+where
+    T: DiffIntermediate,
+    Self: Serialize,
+{
+    fn diff_with(&self, other: &Self, options: &DiffOptions) -> Result<Change> {
+        if !options.is_default() {
+            return Change::data_difference(self, other, options);
+        }
+        let mut result = vec![];
+        for (index, (prev, next)) in self.iter().zip(other.iter()).enumerate() {
+            let change = prev.diff_with(next, options)?;
+            if !change.is_same() {
+                result.push((index, change));
+            }
+        }
+        Ok(partial_seq(result))
+    }
+}
+
+impl<T> DiffIntermediate for Vec<T>
+where
+    T: DiffIntermediate,
+{
+    fn diff_with(&self, other: &Self, options: &DiffOptions) -> Result<Change> {
+        if self.len() == other.len() && diff_items(self.iter(), other.iter(), options)? {
+            return Ok(Change::Same);
+        }
+        Change::data_difference(self, other, options)
+    }
+}
+
+impl<T> DiffIntermediate for VecDeque<T>
+where
+    T: DiffIntermediate,
+{
+    fn diff_with(&self, other: &Self, options: &DiffOptions) -> Result<Change> {
+        if self.len() == other.len() && diff_items(self.iter(), other.iter(), options)? {
+            return Ok(Change::Same);
+        }
+        Change::data_difference(self, other, options)
+    }
+}
+
+impl<T> DiffIntermediate for LinkedList<T>
+where
+    T: DiffIntermediate,
+{
+    fn diff_with(&self, other: &Self, options: &DiffOptions) -> Result<Change> {
+        if self.len() == other.len() && diff_items(self.iter(), other.iter(), options)? {
+            return Ok(Change::Same);
+        }
+        Change::data_difference(self, other, options)
+    }
+}
+
+impl<T> DiffIntermediate for HashSet<T> where T: Serialize {}
+impl<T> DiffIntermediate for BTreeSet<T> where T: Serialize {}
+impl<T> DiffIntermediate for BinaryHeap<T> where T: Serialize + Ord {}
+impl<T> DiffIntermediate for Cell<T> where T: Serialize + Copy {}
+impl<T> DiffIntermediate for Mutex<T> where T: Serialize {}
+impl<T> DiffIntermediate for RwLock<T> where T: Serialize {}
+impl<T> DiffIntermediate for RefCell<T> where T: Serialize {}
+impl<T> DiffIntermediate for Cow<'_, T> where T: ToOwned + Serialize + ?Sized {}
+
+impl<T> DiffIntermediate for Rc<T>
+where
+    T: DiffIntermediate,
+    Self: Serialize,
+{
+    fn diff_with(&self, other: &Self, options: &DiffOptions) -> Result<Change> {
+        if options.is_default() && Rc::ptr_eq(self, other) {
+            return Ok(Change::Same);
+        }
+        (**self).diff_with(other, options)
+    }
+}
+
+impl<T> DiffIntermediate for Arc<T>
+where
+    T: DiffIntermediate,
+    Self: Serialize,
+{
+    fn diff_with(&self, other: &Self, options: &DiffOptions) -> Result<Change> {
+        if options.is_default() && Arc::ptr_eq(self, other) {
+            return Ok(Change::Same);
+        }
+        (**self).diff_with(other, options)
+    }
+}
+
+macro_rules! impl_diff_tuple {
+    ( $( $id:ident : $index:tt ),+ ) => {
+        impl< $( $id ),+ > DiffIntermediate for ( $( $id ),+ , )
+        where
+            $( $id: DiffIntermediate ),+
+        {
+            fn diff_with(&self, other: &Self, options: &DiffOptions) -> Result<Change> {
+                if !options.is_default() {
+                    return Change::data_difference(self, other, options);
+                }
+                let mut result = vec![];
+                $(
+                    let change = self.$index.diff_with(&other.$index, options)?;
+                    if !change.is_same() {
+                        result.push(($index, change));
+                    }
+                )+
+                Ok(partial_seq(result))
+            }
+        }
+    };
+}
+
+impl_diff_tuple! { A:0 }
+impl_diff_tuple! { A:0, B:1 }
+impl_diff_tuple! { A:0, B:1, C:2 }
+impl_diff_tuple! { A:0, B:1, C:2, D:3 }
+impl_diff_tuple! { A:0, B:1, C:2, D:3, E:4 }
+impl_diff_tuple! { A:0, B:1, C:2, D:3, E:4, F:5 }
+impl_diff_tuple! { A:0, B:1, C:2, D:3, E:4, F:5, G:6 }
+impl_diff_tuple! { A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7 }
+impl_diff_tuple! { A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7, I:8 }
+impl_diff_tuple! { A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7, I:8, J:9 }
+impl_diff_tuple! { A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7, I:8, J:9, K:10 }
+impl_diff_tuple! { A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7, I:8, J:9, K:10, L:11 }
+impl_diff_tuple! { A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7, I:8, J:9, K:10, L:11, M:12 }
+impl_diff_tuple! { A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7, I:8, J:9, K:10, L:11, M:12, N:13 }
+impl_diff_tuple! { A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7, I:8, J:9, K:10, L:11, M:12, N:13, O:14 }
+impl_diff_tuple! { A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7, I:8, J:9, K:10, L:11, M:12, N:13, O:14, P:15 }
+
+impl<K, V> DiffIntermediate for HashMap<K, V>
+where
+    K: Serialize + Hash + Eq,
+    V: DiffIntermediate,
+{
+    fn diff_with(&self, other: &Self, options: &DiffOptions) -> Result<Change> {
+        if !options.is_default() {
+            return Change::data_difference(self, other, options);
+        }
+        let mut result = vec![];
+        for (key, value) in other {
+            if !self.contains_key(key) {
+                result.push((
+                    to_intermediate(key)?,
+                    Change::Added(to_intermediate(value)?),
+                ));
+            }
+        }
+        for key in self.keys() {
+            if !other.contains_key(key) {
+                result.push((to_intermediate(key)?, Change::Removed));
+            }
+        }
+        for (key, value) in self {
+            if let Some(other) = other.get(key) {
+                let change = value.diff_with(other, options)?;
+                if !change.is_same() {
+                    result.push((to_intermediate(key)?, change));
+                }
+            }
+        }
+        if result.is_empty() {
+            Ok(Change::Same)
+        } else {
+            Ok(Change::PartialMap(result))
+        }
+    }
+}
+
+impl<K, V> DiffIntermediate for BTreeMap<K, V>
+where
+    K: Serialize + Ord,
+    V: DiffIntermediate,
+{
+    fn diff_with(&self, other: &Self, options: &DiffOptions) -> Result<Change> {
+        if !options.is_default() {
+            return Change::data_difference(self, other, options);
+        }
+        let mut result = vec![];
+        for (key, value) in other {
+            if !self.contains_key(key) {
+                result.push((
+                    to_intermediate(key)?,
+                    Change::Added(to_intermediate(value)?),
+                ));
+            }
+        }
+        for key in self.keys() {
+            if !other.contains_key(key) {
+                result.push((to_intermediate(key)?, Change::Removed));
+            }
+        }
+        for (key, value) in self {
+            if let Some(other) = other.get(key) {
+                let change = value.diff_with(other, options)?;
+                if !change.is_same() {
+                    result.push((to_intermediate(key)?, change));
+                }
+            }
+        }
+        if result.is_empty() {
+            Ok(Change::Same)
+        } else {
+            Ok(Change::PartialMap(result))
+        }
+    }
+}
+
+impl<T> DiffIntermediate for Box<T>
+where
+    T: DiffIntermediate,
+{
+    fn diff_with(&self, other: &Self, options: &DiffOptions) -> Result<Change> {
+        (**self).diff_with(other, options)
+    }
+}
+
+impl<T> DiffIntermediate for Option<T>
+where
+    T: DiffIntermediate,
+{
+    fn diff_with(&self, other: &Self, options: &DiffOptions) -> Result<Change> {
+        if !options.is_default() {
+            return Change::data_difference(self, other, options);
+        }
+        match (self, other) {
+            (None, None) => Ok(Change::Same),
+            (Some(prev), Some(next)) => Ok(partial_change(prev.diff_with(next, options)?)),
+            _ => Ok(Change::Changed(to_intermediate(other)?)),
+        }
+    }
+}
+
+impl<T, E> DiffIntermediate for std::result::Result<T, E>
+where
+    T: DiffIntermediate,
+    E: DiffIntermediate,
+{
+    fn diff_with(&self, other: &Self, options: &DiffOptions) -> Result<Change> {
+        if !options.is_default() {
+            return Change::data_difference(self, other, options);
+        }
+        match (self, other) {
+            (Ok(prev), Ok(next)) => Ok(partial_change(prev.diff_with(next, options)?)),
+            (Err(prev), Err(next)) => Ok(partial_change(prev.diff_with(next, options)?)),
+            _ => Ok(Change::Changed(to_intermediate(other)?)),
+        }
+    }
+}
+
+impl<T> DiffIntermediate for Range<T>
+where
+    T: DiffIntermediate,
+{
+    fn diff_with(&self, other: &Self, options: &DiffOptions) -> Result<Change> {
+        if !options.is_default() {
+            return Change::data_difference(self, other, options);
+        }
+        partial_struct(vec![
+            ("start", self.start.diff_with(&other.start, options)?),
+            ("end", self.end.diff_with(&other.end, options)?),
+        ])
+    }
+}
+
+impl<T> DiffIntermediate for RangeInclusive<T>
+where
+    T: DiffIntermediate,
+{
+    fn diff_with(&self, other: &Self, options: &DiffOptions) -> Result<Change> {
+        if !options.is_default() {
+            return Change::data_difference(self, other, options);
+        }
+        partial_struct(vec![
+            ("start", self.start().diff_with(other.start(), options)?),
+            ("end", self.end().diff_with(other.end(), options)?),
+        ])
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<K, V> DiffIntermediate for indexmap::IndexMap<K, V>
+where
+    K: Serialize + Hash + Eq,
+    V: DiffIntermediate,
+{
+    fn diff_with(&self, other: &Self, options: &DiffOptions) -> Result<Change> {
+        if !options.is_default() {
+            return Change::data_difference(self, other, options);
+        }
+        let mut result = vec![];
+        for (key, value) in other {
+            if !self.contains_key(key) {
+                result.push((
+                    to_intermediate(key)?,
+                    Change::Added(to_intermediate(value)?),
+                ));
+            }
+        }
+        for key in self.keys() {
+            if !other.contains_key(key) {
+                result.push((to_intermediate(key)?, Change::Removed));
+            }
+        }
+        for (key, value) in self {
+            if let Some(other) = other.get(key) {
+                let change = value.diff_with(other, options)?;
+                if !change.is_same() {
+                    result.push((to_intermediate(key)?, change));
+                }
+            }
+        }
+        if result.is_empty() {
+            Ok(Change::Same)
+        } else {
+            Ok(Change::PartialMap(result))
+        }
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<T> DiffIntermediate for indexmap::IndexSet<T> where T: Serialize + Hash + Eq {}
+
+#[cfg(feature = "smallvec")]
+impl<A> DiffIntermediate for smallvec::SmallVec<A>
+where
+    A: smallvec::Array,
+    A::Item: DiffIntermediate,
+{
+    fn diff_with(&self, other: &Self, options: &DiffOptions) -> Result<Change> {
+        if self.len() == other.len() && diff_items(self.iter(), other.iter(), options)? {
+            return Ok(Change::Same);
+        }
+        Change::data_difference(self, other, options)
+    }
+}
+
+/// Tells if all items are same. Any difference is left for `Change::difference` to describe,
+/// as it may turn out to be items insertion or removal.
+fn diff_items<'a, T>(
+    prev: impl Iterator<Item = &'a T>,
+    mut next: impl Iterator<Item = &'a T>,
+    options: &DiffOptions,
+) -> Result<bool>
+where
+    T: DiffIntermediate + 'a,
+{
+    if !options.is_default() {
+        return Ok(false);
+    }
+    for prev in prev {
+        match next.next() {
+            Some(next) if prev.diff_with(next, options)?.is_same() => {}
+            _ => return Ok(false),
+        }
+    }
+    Ok(next.next().is_none())
+}
+
+/// Patches content shared with other owners in place if it is shared for mutation, otherwise
+/// returns patched copy of content that should replace it.
+fn patch_shared<T>(content: &T, change: &Change, report: &mut PatchReport) -> Option<T>
+where
+    T: ReflectIntermediate,
+{
+    if content.patch_shared_change_with_report(change, report) {
+        return None;
+    }
+    match content.clone_for_patch() {
+        Some(mut content) => {
+            content.patch_change_with_report(change, report);
+            Some(content)
+        }
+        None => {
+            report.reject_invalid("value is shared");
+            None
+        }
+    }
+}
+
+fn patch_mutex<T>(mutex: &Mutex<T>, change: &Change, report: &mut PatchReport)
+where
+    T: ReflectIntermediate + ?Sized,
+{
+    if !change.is_same() {
+        match mutex.lock() {
+            Ok(mut content) => content.patch_change_with_report(change, report),
+            Err(_) => report.reject_invalid("mutex is poisoned"),
+        }
+    }
+}
+
+fn patch_rw_lock<T>(lock: &RwLock<T>, change: &Change, report: &mut PatchReport)
+where
+    T: ReflectIntermediate + ?Sized,
+{
+    if !change.is_same() {
+        match lock.write() {
+            Ok(mut content) => content.patch_change_with_report(change, report),
+            Err(_) => report.reject_invalid("lock is poisoned"),
+        }
+    }
+}
+
+fn patch_ref_cell<T>(cell: &RefCell<T>, change: &Change, report: &mut PatchReport)
+where
+    T: ReflectIntermediate + ?Sized,
+{
+    if !change.is_same() {
+        match cell.try_borrow_mut() {
+            Ok(mut content) => content.patch_change_with_report(change, report),
+            Err(_) => report.reject_invalid("value is already borrowed"),
+        }
+    }
+}
+
+fn partial_seq(changes: Vec<(usize, Change)>) -> Change {
+    if changes.is_empty() {
+        Change::Same
+    } else {
+        Change::PartialSeq(changes)
+    }
+}
+
+fn partial_change(change: Change) -> Change {
+    if change.is_same() {
+        Change::Same
+    } else {
+        Change::PartialChange(Box::new(change))
+    }
+}
+
+fn partial_struct(fields: Vec<(&str, Change)>) -> Result<Change> {
+    let fields = fields
+        .into_iter()
+        .filter(|(_, change)| !change.is_same())
+        .map(|(name, change)| (name.to_owned(), change))
+        .collect::<Vec<_>>();
+    if fields.is_empty() {
+        Ok(Change::Same)
+    } else {
+        Ok(Change::PartialStruct(fields))
+    }
+}
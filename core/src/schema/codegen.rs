@@ -0,0 +1,856 @@
+use crate::schema::{
+    Schema, SchemaIdContainer, SchemaIdTree, SchemaPackage, SchemaType, SchemaTypeEnumVariant,
+    SchemaTypeInstance, SchemaTypePrimitive, SchemaTypeStruct,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+/// Keywords that can't be used as raw identifiers.
+const RUST_RESERVED: &[&str] = &["crate", "self", "Self", "super"];
+
+impl SchemaPackage {
+    /// Generates Rust source with declarations of structs and enums described in package,
+    /// deriving serde traits so they serialize the same way as original types.
+    ///
+    /// Type names come from last segment of type id path. Types sharing the same name get
+    /// prefixed with names of their parent modules, so `a::Item` and `b::Item` get declared as
+    /// `AItem` and `BItem`. Instances of generic type share single generic declaration, with
+    /// type parameters inferred from fields of instances found in package, so `Wrapper<u32>`
+    /// gets declared as `Wrapper<T>`. Types from `std` are referenced as they are.
+    ///
+    /// # Example
+    /// ```rust
+    /// use serde_intermediate::{SchemaIntermediate, SchemaPackage};
+    ///
+    /// /// Player.
+    /// #[derive(SchemaIntermediate)]
+    /// struct Player {
+    ///     name: String,
+    ///     scores: Vec<u32>,
+    /// }
+    ///
+    /// let mut package = SchemaPackage::default();
+    /// Player::schema(&mut package);
+    /// assert_eq!(
+    ///     package.to_rust_code(),
+    ///     r#"use serde::{Deserialize, Serialize};
+    ///
+    /// /// Player.
+    /// #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    /// pub struct Player {
+    ///     pub name: String,
+    ///     pub scores: Vec<u32>,
+    /// }
+    /// "#
+    /// );
+    /// ```
+    pub fn to_rust_code(&self) -> String {
+        let (declarations, names) = self.declarations();
+        let mut codegen = RustCodegen {
+            package: self,
+            names: &names,
+            params: Default::default(),
+            imports: Default::default(),
+        };
+        let declarations = declarations
+            .iter()
+            .map(|declaration| codegen.declaration(declaration))
+            .collect::<Vec<_>>();
+        let mut result = "use serde::{Deserialize, Serialize};\n".to_owned();
+        for import in &codegen.imports {
+            result.push_str(&format!("use {};\n", import));
+        }
+        for declaration in declarations {
+            result.push('\n');
+            result.push_str(&declaration);
+        }
+        result
+    }
+
+    /// Generates TypeScript declarations of types described in package, matching JSON layout
+    /// produced by serde (enums use default, externally tagged representation).
+    ///
+    /// Types are named the same way as in `to_rust_code`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use serde_intermediate::{SchemaIntermediate, SchemaPackage};
+    ///
+    /// #[derive(SchemaIntermediate)]
+    /// enum Shape {
+    ///     Empty,
+    ///     Circle(f32),
+    ///     Rect { width: f32, height: f32 },
+    /// }
+    ///
+    /// let mut package = SchemaPackage::default();
+    /// Shape::schema(&mut package);
+    /// assert_eq!(
+    ///     package.to_typescript_code(),
+    ///     r#"export type Shape =
+    ///   | { Circle: number }
+    ///   | "Empty"
+    ///   | { Rect: { height: number; width: number } };
+    /// "#
+    /// );
+    /// ```
+    pub fn to_typescript_code(&self) -> String {
+        let (declarations, names) = self.declarations();
+        let mut codegen = TypeScriptCodegen {
+            package: self,
+            names: &names,
+            params: Default::default(),
+        };
+        declarations
+            .iter()
+            .map(|declaration| codegen.declaration(declaration))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Declarations of structs and enums, sorted by their names, and names under which they
+    /// are referenced, by type path.
+    fn declarations(&self) -> (Vec<Declaration>, HashMap<Vec<String>, DeclaredName>) {
+        let mut groups = BTreeMap::<_, Vec<_>>::new();
+        for (id, schema) in &self.schemas {
+            if !is_declared(&schema.data_type) {
+                continue;
+            }
+            let (path, args) = match id.to_owned().try_into_tree() {
+                Some(SchemaIdTree::Path { path, args }) => (path, args),
+                _ => (vec![id.to_string()], vec![]),
+            };
+            groups
+                .entry(path)
+                .or_default()
+                .push((id.to_string(), args, schema));
+        }
+        let type_names = type_names(groups.keys());
+        let mut declarations = Vec::with_capacity(groups.len());
+        let mut names = HashMap::with_capacity(groups.len());
+        for (path, mut instances) in groups {
+            instances.sort_by(|(a, ..), (b, ..)| a.cmp(b));
+            let (_, args, schema) = &instances[0];
+            let params = match args.len() {
+                1 => vec!["T".to_owned()],
+                count => (1..=count).map(|index| format!("T{}", index)).collect(),
+            };
+            // Only instances with the same layout can tell which fields use type parameters.
+            let fields = instance_trees(&schema.data_type);
+            let instances = instances
+                .iter()
+                .map(|(_, args, schema)| (args, instance_trees(&schema.data_type)))
+                .filter(|(other, trees)| other.len() == args.len() && trees.len() == fields.len())
+                .collect::<Vec<_>>();
+            let mut used = BTreeSet::new();
+            let mut schema = Schema::clone(schema);
+            let mut index = 0;
+            visit_instances(&mut schema.data_type, &mut |instance| {
+                let trees = instances
+                    .iter()
+                    .map(|(_, trees)| trees[index].to_owned())
+                    .collect::<Option<Vec<_>>>();
+                if let Some(trees) = trees {
+                    let args = instances
+                        .iter()
+                        .map(|(args, _)| args.as_slice())
+                        .collect::<Vec<_>>();
+                    instance.id = generalize(&trees, &args, &params, &mut used).into();
+                }
+                index += 1;
+            });
+            let name = type_names[&path].to_owned();
+            declarations.push(Declaration {
+                name: name.to_owned(),
+                params: used.iter().map(|index| params[*index].to_owned()).collect(),
+                schema,
+            });
+            names.insert(
+                path,
+                DeclaredName {
+                    name,
+                    args: used.into_iter().collect(),
+                },
+            );
+        }
+        declarations.sort_by(|a, b| a.name.cmp(&b.name));
+        (declarations, names)
+    }
+}
+
+/// Struct or enum declared in generated code, shared by all instances of generic type.
+struct Declaration {
+    name: String,
+    params: Vec<String>,
+    schema: Schema,
+}
+
+impl Declaration {
+    fn header(&self) -> String {
+        if self.params.is_empty() {
+            self.name.to_owned()
+        } else {
+            format!("{}<{}>", self.name, self.params.join(", "))
+        }
+    }
+}
+
+/// Name of declared type, with indices of generic arguments that its references keep.
+struct DeclaredName {
+    name: String,
+    args: Vec<usize>,
+}
+
+struct RustCodegen<'a> {
+    package: &'a SchemaPackage,
+    names: &'a HashMap<Vec<String>, DeclaredName>,
+    /// Type parameters of currently generated declaration.
+    params: Vec<String>,
+    imports: BTreeSet<&'static str>,
+}
+
+impl RustCodegen<'_> {
+    fn declaration(&mut self, declaration: &Declaration) -> String {
+        let name = declaration.header();
+        let schema = &declaration.schema;
+        self.params = declaration.params.to_owned();
+        let mut result = rust_docs(&schema.description, "");
+        result.push_str("#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n");
+        match &schema.data_type {
+            SchemaType::Struct(content) if content.0.is_empty() => {
+                result.push_str(&format!("pub struct {};\n", name));
+            }
+            SchemaType::Struct(content) => {
+                result.push_str(&format!("pub struct {} {{\n", name));
+                result.push_str(&self.fields(content, "    ", "pub "));
+                result.push_str("}\n");
+            }
+            SchemaType::TupleStruct(content) => {
+                let items = content
+                    .0
+                    .iter()
+                    .map(|item| format!("pub {}", self.instance(item)))
+                    .collect::<Vec<_>>();
+                result.push_str(&format!("pub struct {}({});\n", name, items.join(", ")));
+            }
+            SchemaType::Enum(content) => {
+                result.push_str(&format!("pub enum {} {{\n", name));
+                let mut names = content.0.keys().collect::<Vec<_>>();
+                names.sort();
+                for name in names {
+                    let (attribute, identifier) = rust_identifier(name);
+                    if let Some(attribute) = attribute {
+                        result.push_str(&format!("    {}\n", attribute));
+                    }
+                    match &content.0[name] {
+                        SchemaTypeEnumVariant::Empty => {
+                            result.push_str(&format!("    {},\n", identifier));
+                        }
+                        SchemaTypeEnumVariant::Tuple(content) => {
+                            let items = content
+                                .0
+                                .iter()
+                                .map(|item| self.instance(item))
+                                .collect::<Vec<_>>();
+                            result.push_str(&format!(
+                                "    {}({}),\n",
+                                identifier,
+                                items.join(", ")
+                            ));
+                        }
+                        SchemaTypeEnumVariant::Struct(content) => {
+                            result.push_str(&format!("    {} {{\n", identifier));
+                            result.push_str(&self.fields(content, "        ", ""));
+                            result.push_str("    },\n");
+                        }
+                    }
+                }
+                result.push_str("}\n");
+            }
+            _ => unreachable!(),
+        }
+        result
+    }
+
+    fn fields(&mut self, content: &SchemaTypeStruct, indent: &str, visibility: &str) -> String {
+        let mut names = content.0.keys().collect::<Vec<_>>();
+        names.sort();
+        let mut result = String::new();
+        for name in names {
+            let field = &content.0[name];
+            result.push_str(&rust_docs(&field.description, indent));
+            let (attribute, identifier) = rust_identifier(name);
+            if let Some(attribute) = attribute {
+                result.push_str(&format!("{}{}\n", indent, attribute));
+            }
+            result.push_str(&format!(
+                "{}{}{}: {},\n",
+                indent,
+                visibility,
+                identifier,
+                self.instance(field)
+            ));
+        }
+        result
+    }
+
+    fn instance(&mut self, instance: &SchemaTypeInstance) -> String {
+        self.id(&instance.id)
+    }
+
+    fn id(&mut self, id: &SchemaIdContainer) -> String {
+        match id.to_owned().try_into_tree() {
+            Some(tree) => self.tree(&tree),
+            None => match self.names.get(&vec![id.to_string()]) {
+                Some(declared) => declared.name.to_owned(),
+                None => match self.package.find(id) {
+                    Some(schema) => self.data_type(&schema.data_type),
+                    None => id.to_string(),
+                },
+            },
+        }
+    }
+
+    fn tree(&mut self, tree: &SchemaIdTree) -> String {
+        let (path, args) = match tree {
+            SchemaIdTree::Tuple(list) => {
+                let items = list.iter().map(|item| self.tree(item)).collect::<Vec<_>>();
+                return match items.len() {
+                    1 => format!("({},)", items[0]),
+                    _ => format!("({})", items.join(", ")),
+                };
+            }
+            SchemaIdTree::Path { path, args } => (path, args),
+        };
+        if let Some(param) = param(path, args, &self.params) {
+            return param.to_owned();
+        }
+        if let Some(declared) = self.names.get(path) {
+            let args = declared
+                .args
+                .iter()
+                .filter_map(|index| args.get(*index))
+                .map(|arg| self.tree(arg))
+                .collect::<Vec<_>>();
+            return generic(&declared.name, &args);
+        }
+        let id = SchemaIdContainer::Tree(tree.to_owned());
+        let name = path.last().map(|name| name.as_str()).unwrap_or_default();
+        let args = args.iter().map(|arg| self.tree(arg)).collect::<Vec<_>>();
+        match (name, args.as_slice()) {
+            ("str", []) => "String".to_owned(),
+            ("Box" | "Rc" | "Arc", [content]) => format!("Box<{}>", content),
+            ("Cell" | "RefCell" | "Mutex" | "RwLock" | "Cow", [content]) => content.to_owned(),
+            ("Option" | "Vec", _) => format!("{}<{}>", name, args.join(", ")),
+            (
+                "VecDeque" | "LinkedList" | "HashSet" | "BTreeSet" | "BinaryHeap" | "HashMap"
+                | "BTreeMap",
+                _,
+            ) => {
+                self.imports.insert(match name {
+                    "VecDeque" => "std::collections::VecDeque",
+                    "LinkedList" => "std::collections::LinkedList",
+                    "HashSet" => "std::collections::HashSet",
+                    "BTreeSet" => "std::collections::BTreeSet",
+                    "BinaryHeap" => "std::collections::BinaryHeap",
+                    "HashMap" => "std::collections::HashMap",
+                    _ => "std::collections::BTreeMap",
+                });
+                format!("{}<{}>", name, args.join(", "))
+            }
+            ("PhantomData", _) => {
+                self.imports.insert("std::marker::PhantomData");
+                format!("PhantomData<{}>", args.join(", "))
+            }
+            _ => match self.package.find(&id) {
+                Some(schema) => self.data_type(&schema.data_type),
+                None => generic(name, &args),
+            },
+        }
+    }
+
+    fn data_type(&mut self, data_type: &SchemaType) -> String {
+        match data_type {
+            SchemaType::Primitive(primitive) => primitive.to_string(),
+            SchemaType::Option(content) => format!("Option<{}>", self.instance(content)),
+            SchemaType::Seq(content) => format!("Vec<{}>", self.instance(content)),
+            SchemaType::Map(content) => {
+                self.imports.insert("std::collections::HashMap");
+                format!(
+                    "HashMap<{}, {}>",
+                    self.instance(&content.key),
+                    self.instance(&content.value)
+                )
+            }
+            SchemaType::Tuple(content) => {
+                let items = content
+                    .0
+                    .iter()
+                    .map(|item| self.instance(item))
+                    .collect::<Vec<_>>();
+                match items.len() {
+                    1 => format!("({},)", items[0]),
+                    _ => format!("({})", items.join(", ")),
+                }
+            }
+            SchemaType::Array(content) => {
+                format!(
+                    "[{}; {}]",
+                    self.instance(&content.type_instance),
+                    content.count
+                )
+            }
+            SchemaType::Slice(content) => format!("Vec<{}>", self.instance(&content.type_instance)),
+            SchemaType::TupleStruct(_) | SchemaType::Struct(_) | SchemaType::Enum(_) => {
+                unreachable!()
+            }
+        }
+    }
+}
+
+struct TypeScriptCodegen<'a> {
+    package: &'a SchemaPackage,
+    names: &'a HashMap<Vec<String>, DeclaredName>,
+    /// Type parameters of currently generated declaration.
+    params: Vec<String>,
+}
+
+impl TypeScriptCodegen<'_> {
+    fn declaration(&mut self, declaration: &Declaration) -> String {
+        let name = declaration.header();
+        let schema = &declaration.schema;
+        self.params = declaration.params.to_owned();
+        let mut result = typescript_docs(&schema.description, "");
+        match &schema.data_type {
+            SchemaType::Struct(content) if content.0.is_empty() => {
+                result.push_str(&format!("export type {} = null;\n", name));
+            }
+            SchemaType::Struct(content) => {
+                result.push_str(&format!("export interface {} {{\n", name));
+                let mut names = content.0.keys().collect::<Vec<_>>();
+                names.sort();
+                for name in names {
+                    let field = &content.0[name];
+                    result.push_str(&typescript_docs(&field.description, "  "));
+                    result.push_str(&format!("  {};\n", self.field(name, field)));
+                }
+                result.push_str("}\n");
+            }
+            SchemaType::TupleStruct(content) => {
+                result.push_str(&format!(
+                    "export type {} = {};\n",
+                    name,
+                    self.items(&content.0)
+                ));
+            }
+            SchemaType::Enum(content) if content.0.is_empty() => {
+                result.push_str(&format!("export type {} = never;\n", name));
+            }
+            SchemaType::Enum(content) => {
+                result.push_str(&format!("export type {} =", name));
+                let mut names = content.0.keys().collect::<Vec<_>>();
+                names.sort();
+                for name in names {
+                    let variant = match &content.0[name] {
+                        SchemaTypeEnumVariant::Empty => format!("{:?}", name),
+                        SchemaTypeEnumVariant::Tuple(content) => format!(
+                            "{{ {}: {} }}",
+                            typescript_property(name),
+                            self.items(&content.0)
+                        ),
+                        SchemaTypeEnumVariant::Struct(content) => format!(
+                            "{{ {}: {} }}",
+                            typescript_property(name),
+                            self.object(content)
+                        ),
+                    };
+                    result.push_str(&format!("\n  | {}", variant));
+                }
+                result.push_str(";\n");
+            }
+            _ => unreachable!(),
+        }
+        result
+    }
+
+    /// Field declaration. Fields of `Option` type are optional, as serde reads missing ones
+    /// as `None`.
+    fn field(&self, name: &str, field: &SchemaTypeInstance) -> String {
+        let optional = if self.package.is_optional(&field.id) {
+            "?"
+        } else {
+            ""
+        };
+        format!(
+            "{}{}: {}",
+            typescript_property(name),
+            optional,
+            self.instance(field)
+        )
+    }
+
+    fn object(&self, content: &SchemaTypeStruct) -> String {
+        if content.0.is_empty() {
+            return "{}".to_owned();
+        }
+        let mut names = content.0.keys().collect::<Vec<_>>();
+        names.sort();
+        let fields = names
+            .into_iter()
+            .map(|name| self.field(name, &content.0[name]))
+            .collect::<Vec<_>>();
+        format!("{{ {} }}", fields.join("; "))
+    }
+
+    /// Newtype content is serialized as it is, other tuples are serialized as arrays.
+    fn items(&self, items: &[SchemaTypeInstance]) -> String {
+        match items {
+            [item] => self.instance(item),
+            items => {
+                let items = items
+                    .iter()
+                    .map(|item| self.instance(item))
+                    .collect::<Vec<_>>();
+                format!("[{}]", items.join(", "))
+            }
+        }
+    }
+
+    fn instance(&self, instance: &SchemaTypeInstance) -> String {
+        self.id(&instance.id)
+    }
+
+    fn id(&self, id: &SchemaIdContainer) -> String {
+        match id.to_owned().try_into_tree() {
+            Some(tree) => self.tree(&tree),
+            None => match self.names.get(&vec![id.to_string()]) {
+                Some(declared) => declared.name.to_owned(),
+                None => match self.package.find(id) {
+                    Some(schema) => self.data_type(&schema.data_type),
+                    None => "unknown".to_owned(),
+                },
+            },
+        }
+    }
+
+    fn tree(&self, tree: &SchemaIdTree) -> String {
+        let (path, args) = match tree {
+            SchemaIdTree::Tuple(list) if list.is_empty() => return "null".to_owned(),
+            SchemaIdTree::Tuple(list) => {
+                let items = list.iter().map(|item| self.tree(item)).collect::<Vec<_>>();
+                return format!("[{}]", items.join(", "));
+            }
+            SchemaIdTree::Path { path, args } => (path, args),
+        };
+        if let Some(param) = param(path, args, &self.params) {
+            return param.to_owned();
+        }
+        if let Some(declared) = self.names.get(path) {
+            let args = declared
+                .args
+                .iter()
+                .filter_map(|index| args.get(*index))
+                .map(|arg| self.tree(arg))
+                .collect::<Vec<_>>();
+            return generic(&declared.name, &args);
+        }
+        let id = SchemaIdContainer::Tree(tree.to_owned());
+        let name = path.last().map(|name| name.as_str()).unwrap_or_default();
+        match (name, args.as_slice()) {
+            ("bool", []) => "boolean".to_owned(),
+            (
+                "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64"
+                | "u128" | "usize" | "f32" | "f64",
+                [],
+            ) => "number".to_owned(),
+            ("char" | "String" | "str", []) => "string".to_owned(),
+            ("Option", [content]) => format!("{} | null", self.tree(content)),
+            (
+                "Vec" | "VecDeque" | "LinkedList" | "HashSet" | "BTreeSet" | "BinaryHeap",
+                [item, ..],
+            ) => format!("Array<{}>", self.tree(item)),
+            ("HashMap" | "BTreeMap", [_, value, ..]) => {
+                format!("Record<string, {}>", self.tree(value))
+            }
+            ("Box" | "Rc" | "Arc" | "Cell" | "RefCell" | "Mutex" | "RwLock" | "Cow", [content]) => {
+                self.tree(content)
+            }
+            ("PhantomData", _) => "null".to_owned(),
+            _ => match self.package.find(&id) {
+                Some(schema) => self.data_type(&schema.data_type),
+                None => "unknown".to_owned(),
+            },
+        }
+    }
+
+    fn data_type(&self, data_type: &SchemaType) -> String {
+        match data_type {
+            SchemaType::Primitive(SchemaTypePrimitive::Unit) => "null".to_owned(),
+            SchemaType::Primitive(SchemaTypePrimitive::Bool) => "boolean".to_owned(),
+            SchemaType::Primitive(SchemaTypePrimitive::Char | SchemaTypePrimitive::String) => {
+                "string".to_owned()
+            }
+            SchemaType::Primitive(_) => "number".to_owned(),
+            SchemaType::Option(content) => format!("{} | null", self.instance(content)),
+            SchemaType::Seq(content) => format!("Array<{}>", self.instance(content)),
+            SchemaType::Map(content) => {
+                format!("Record<string, {}>", self.instance(&content.value))
+            }
+            SchemaType::Tuple(content) => {
+                let items = content
+                    .0
+                    .iter()
+                    .map(|item| self.instance(item))
+                    .collect::<Vec<_>>();
+                format!("[{}]", items.join(", "))
+            }
+            SchemaType::Array(content) | SchemaType::Slice(content) => {
+                format!("Array<{}>", self.instance(&content.type_instance))
+            }
+            SchemaType::TupleStruct(_) | SchemaType::Struct(_) | SchemaType::Enum(_) => {
+                unreachable!()
+            }
+        }
+    }
+}
+
+fn is_declared(data_type: &SchemaType) -> bool {
+    matches!(
+        data_type,
+        SchemaType::TupleStruct(_) | SchemaType::Struct(_) | SchemaType::Enum(_)
+    )
+}
+
+/// Calls `f` on type instances used by struct or enum, in order of their declaration in
+/// generated code.
+fn visit_instances(data_type: &mut SchemaType, f: &mut impl FnMut(&mut SchemaTypeInstance)) {
+    match data_type {
+        SchemaType::Struct(content) => visit_fields(content, f),
+        SchemaType::TupleStruct(content) => content.0.iter_mut().for_each(f),
+        SchemaType::Enum(content) => {
+            let mut variants = content.0.iter_mut().collect::<Vec<_>>();
+            variants.sort_by_key(|(name, _)| *name);
+            for (_, variant) in variants {
+                match variant {
+                    SchemaTypeEnumVariant::Empty => {}
+                    SchemaTypeEnumVariant::Tuple(content) => content.0.iter_mut().for_each(&mut *f),
+                    SchemaTypeEnumVariant::Struct(content) => visit_fields(content, f),
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn visit_fields(content: &mut SchemaTypeStruct, f: &mut impl FnMut(&mut SchemaTypeInstance)) {
+    let mut fields = content.0.iter_mut().collect::<Vec<_>>();
+    fields.sort_by_key(|(name, _)| *name);
+    for (_, field) in fields {
+        f(field);
+    }
+}
+
+/// Id trees of type instances used by struct or enum, in order of `visit_instances`.
+fn instance_trees(data_type: &SchemaType) -> Vec<Option<SchemaIdTree>> {
+    let mut result = vec![];
+    visit_instances(&mut data_type.to_owned(), &mut |instance| {
+        result.push(instance.id.to_owned().try_into_tree());
+    });
+    result
+}
+
+/// Replaces parts of type found at the same place in all instances of generic type with type
+/// parameter, if they are generic arguments of these instances at the same position.
+fn generalize(
+    trees: &[SchemaIdTree],
+    args: &[&[SchemaIdTree]],
+    params: &[String],
+    used: &mut BTreeSet<usize>,
+) -> SchemaIdTree {
+    let found = (0..params.len()).find(|index| {
+        trees
+            .iter()
+            .zip(args)
+            .all(|(tree, args)| args.get(*index) == Some(tree))
+    });
+    if let Some(index) = found {
+        used.insert(index);
+        return SchemaIdTree::Path {
+            path: vec![params[index].to_owned()],
+            args: vec![],
+        };
+    }
+    let mut parts = vec![];
+    for tree in trees {
+        let (kind, items) = match tree {
+            SchemaIdTree::Tuple(list) => (None, list),
+            SchemaIdTree::Path { path, args } => (Some(path), args),
+        };
+        parts.push((kind, items));
+    }
+    let (kind, items) = &parts[0];
+    if parts
+        .iter()
+        .any(|(other, list)| other != kind || list.len() != items.len())
+    {
+        return trees[0].to_owned();
+    }
+    let items = (0..items.len())
+        .map(|index| {
+            let trees = parts
+                .iter()
+                .map(|(_, list)| list[index].to_owned())
+                .collect::<Vec<_>>();
+            generalize(&trees, args, params, used)
+        })
+        .collect();
+    match kind {
+        Some(path) => SchemaIdTree::Path {
+            path: path.to_vec(),
+            args: items,
+        },
+        None => SchemaIdTree::Tuple(items),
+    }
+}
+
+/// Type parameter of currently generated declaration that path refers to.
+fn param<'a>(path: &[String], args: &[SchemaIdTree], params: &'a [String]) -> Option<&'a str> {
+    match path {
+        [name] if args.is_empty() => params
+            .iter()
+            .find(|param| *param == name)
+            .map(|param| param.as_str()),
+        _ => None,
+    }
+}
+
+fn generic(name: &str, args: &[String]) -> String {
+    if args.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}<{}>", name, args.join(", "))
+    }
+}
+
+/// Names of declared types: last segments of their paths, prefixed with as many names of
+/// parent modules as needed to tell apart types of the same name.
+fn type_names<'a>(paths: impl Iterator<Item = &'a Vec<String>>) -> HashMap<Vec<String>, String> {
+    let mut groups = HashMap::<_, Vec<_>>::new();
+    for path in paths {
+        groups.entry(type_name(path, 1)).or_default().push(path);
+    }
+    let mut result = HashMap::new();
+    for paths in groups.into_values() {
+        let limit = paths
+            .iter()
+            .map(|path| path.len())
+            .max()
+            .unwrap_or_default();
+        let mut depth = 1;
+        while depth < limit
+            && paths
+                .iter()
+                .map(|path| type_name(path, depth))
+                .collect::<HashSet<_>>()
+                .len()
+                < paths.len()
+        {
+            depth += 1;
+        }
+        for path in paths {
+            result.insert(path.to_owned(), type_name(path, depth));
+        }
+    }
+    result
+}
+
+/// Last segment of path, prefixed with PascalCase names of `depth - 1` parent modules.
+fn type_name(path: &[String], depth: usize) -> String {
+    let path = &path[path.len().saturating_sub(depth)..];
+    let (name, modules) = match path.split_last() {
+        Some(result) => result,
+        None => return Default::default(),
+    };
+    let mut result = modules
+        .iter()
+        .flat_map(|module| module.split('_'))
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|c| c.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect::<String>();
+    result.extend(name.chars().filter(|c| c.is_alphanumeric() || *c == '_'));
+    result
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Identifier of field or variant, with serde attribute that restores its name if it can't be
+/// used as it is.
+fn rust_identifier(name: &str) -> (Option<String>, String) {
+    if is_identifier(name) && !RUST_RESERVED.contains(&name) {
+        if RUST_KEYWORDS.contains(&name) {
+            (None, format!("r#{}", name))
+        } else {
+            (None, name.to_owned())
+        }
+    } else {
+        let mut identifier = name
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+        if !is_identifier(&identifier) || RUST_RESERVED.contains(&identifier.as_str()) {
+            identifier.insert(0, '_');
+        }
+        (Some(format!("#[serde(rename = {:?})]", name)), identifier)
+    }
+}
+
+fn typescript_property(name: &str) -> String {
+    if is_identifier(name) {
+        name.to_owned()
+    } else {
+        format!("{:?}", name)
+    }
+}
+
+fn rust_docs(description: &str, indent: &str) -> String {
+    description
+        .lines()
+        .map(|line| {
+            if line.is_empty() {
+                format!("{}///\n", indent)
+            } else {
+                format!("{}/// {}\n", indent, line)
+            }
+        })
+        .collect()
+}
+
+fn typescript_docs(description: &str, indent: &str) -> String {
+    match description.lines().collect::<Vec<_>>().as_slice() {
+        [] => String::new(),
+        [line] => format!("{}/** {} */\n", indent, line),
+        lines => {
+            let mut result = format!("{}/**\n", indent);
+            for line in lines {
+                result.push_str(&format!("{} * {}\n", indent, line).replace(" * \n", " *\n"));
+            }
+            result.push_str(&format!("{} */\n", indent));
+            result
+        }
+    }
+}
@@ -0,0 +1,470 @@
+use crate::{
+    schema::{
+        variant_kind, SchemaIdContainer, SchemaPackage, SchemaType, SchemaTypeEnumVariant,
+        SchemaTypePrimitive, SchemaTypeStruct, SchemaTypeTuple,
+    },
+    value::path::IntermediatePath,
+};
+use std::collections::HashSet;
+
+/// Kind of difference between old and new schema of type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaChangeKind {
+    /// Type got replaced with one of different layout.
+    TypeChanged { from: String, to: String },
+    /// Struct field got removed.
+    FieldRemoved(String),
+    /// Struct field got added. Only fields of `Option` type are not required.
+    FieldAdded { name: String, required: bool },
+    /// Enum variant got removed.
+    VariantRemoved(String),
+    /// Enum variant got added.
+    VariantAdded(String),
+    /// Tuple got different number of items.
+    ArityChanged { from: usize, to: usize },
+    /// Array got different number of items.
+    CountChanged { from: usize, to: usize },
+}
+
+impl std::fmt::Display for SchemaChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TypeChanged { from, to } => write!(f, "type changed from `{}` to `{}`", from, to),
+            Self::FieldRemoved(name) => write!(f, "field removed: `{}`", name),
+            Self::FieldAdded { name, required } => {
+                if *required {
+                    write!(f, "required field added: `{}`", name)
+                } else {
+                    write!(f, "optional field added: `{}`", name)
+                }
+            }
+            Self::VariantRemoved(name) => write!(f, "variant removed: `{}`", name),
+            Self::VariantAdded(name) => write!(f, "variant added: `{}`", name),
+            Self::ArityChanged { from, to } => {
+                write!(f, "tuple items count changed from {} to {}", from, to)
+            }
+            Self::CountChanged { from, to } => {
+                write!(f, "array items count changed from {} to {}", from, to)
+            }
+        }
+    }
+}
+
+/// Difference between old and new schema of type, with its impact on compatibility.
+///
+/// Compatibility assumes default serde behavior: unknown fields are ignored and missing fields
+/// of `Option` type are read as `None`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaChange {
+    /// Type which schema has changed.
+    pub id: SchemaIdContainer,
+    /// Location of change in serialized value of type. Enum variant content is located under
+    /// field named after variant, as in externally tagged representation.
+    pub path: IntermediatePath,
+    pub kind: SchemaChangeKind,
+    /// Data serialized with old schema can be read with new schema.
+    pub backward_compatible: bool,
+    /// Data serialized with new schema can be read with old schema.
+    pub forward_compatible: bool,
+}
+
+impl SchemaChange {
+    /// Tells if data serialized with old schema can't be read anymore.
+    pub fn is_breaking(&self) -> bool {
+        !self.backward_compatible
+    }
+}
+
+impl std::fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}`", self.id)?;
+        if !self.path.is_empty() {
+            write!(f, " at `{}`", self.path)?;
+        }
+        write!(f, ": {}", self.kind)?;
+        if self.is_breaking() {
+            write!(f, " (breaking)")
+        } else {
+            write!(f, " (non-breaking)")
+        }
+    }
+}
+
+impl SchemaPackage {
+    /// Compares schemas of this (old) package with schemas of same types in `new` package,
+    /// reporting every change that affects compatibility of serialized data.
+    ///
+    /// Types that exist in only one of packages are not compared, but changed types of
+    /// fields and items are reported where they are used.
+    ///
+    /// # Example
+    /// ```rust
+    /// use serde_intermediate::{
+    ///     schema::{compatibility::SchemaChangeKind, *},
+    ///     SchemaPackage,
+    /// };
+    ///
+    /// let id = SchemaIdTree::Path {
+    ///     path: vec!["game".to_owned(), "Save".to_owned()],
+    ///     args: vec![],
+    /// };
+    /// let mut old = SchemaPackage::default();
+    /// old.with(
+    ///     id.to_owned(),
+    ///     SchemaType::new_struct(
+    ///         SchemaTypeStruct::default().field("level", SchemaIdTree::new::<u32>()),
+    ///     ),
+    /// );
+    /// let mut new = SchemaPackage::default();
+    /// new.with(
+    ///     id,
+    ///     SchemaType::new_struct(
+    ///         SchemaTypeStruct::default()
+    ///             .field("level", SchemaIdTree::new::<u32>())
+    ///             .field("score", SchemaIdTree::new::<u64>()),
+    ///     ),
+    /// );
+    /// let changes = old.compatibility(&new);
+    /// assert_eq!(changes.len(), 1);
+    /// assert_eq!(
+    ///     changes[0].kind,
+    ///     SchemaChangeKind::FieldAdded {
+    ///         name: "score".to_owned(),
+    ///         required: true,
+    ///     },
+    /// );
+    /// assert!(changes[0].is_breaking());
+    /// ```
+    pub fn compatibility(&self, new: &SchemaPackage) -> Vec<SchemaChange> {
+        let mut ids = self.schemas.keys().collect::<Vec<_>>();
+        ids.sort_by_cached_key(|id| id.to_string());
+        let mut result = vec![];
+        for id in ids {
+            if let Some(schema) = new.find(id) {
+                let mut comparison = Comparison {
+                    old: self,
+                    new,
+                    id,
+                    visited: Default::default(),
+                    result: &mut result,
+                };
+                comparison.compare_type(
+                    &self.schemas[id].data_type,
+                    &schema.data_type,
+                    &IntermediatePath::default(),
+                );
+            }
+        }
+        result
+    }
+}
+
+/// State of comparing old and new schema of single type.
+struct Comparison<'a> {
+    old: &'a SchemaPackage,
+    new: &'a SchemaPackage,
+    id: &'a SchemaIdContainer,
+    /// Pairs of differently named types being compared, to not follow recursive types forever.
+    visited: HashSet<(SchemaIdContainer, SchemaIdContainer)>,
+    result: &'a mut Vec<SchemaChange>,
+}
+
+impl Comparison<'_> {
+    fn push(
+        &mut self,
+        path: &IntermediatePath,
+        kind: SchemaChangeKind,
+        backward_compatible: bool,
+        forward_compatible: bool,
+    ) {
+        self.result.push(SchemaChange {
+            id: self.id.to_owned(),
+            path: path.to_owned(),
+            kind,
+            backward_compatible,
+            forward_compatible,
+        });
+    }
+
+    fn compare_id(
+        &mut self,
+        old: &SchemaIdContainer,
+        new: &SchemaIdContainer,
+        path: &IntermediatePath,
+    ) {
+        if old == new {
+            return;
+        }
+        if let (Some(old), Some(new)) = (
+            old.to_owned().try_into_tree(),
+            new.to_owned().try_into_tree(),
+        ) {
+            if old == new {
+                return;
+            }
+        }
+        let pair = (old.to_owned(), new.to_owned());
+        if self.visited.contains(&pair) {
+            return;
+        }
+        match (self.old.resolve(old), self.new.resolve(new)) {
+            (Some(old_type), Some(new_type)) => {
+                self.visited.insert(pair.to_owned());
+                self.compare_type(&old_type, &new_type, path);
+                self.visited.remove(&pair);
+            }
+            _ => self.push(
+                path,
+                SchemaChangeKind::TypeChanged {
+                    from: old.to_string(),
+                    to: new.to_string(),
+                },
+                false,
+                false,
+            ),
+        }
+    }
+
+    fn compare_type(&mut self, old: &SchemaType, new: &SchemaType, path: &IntermediatePath) {
+        match (old, new) {
+            (SchemaType::Primitive(old), SchemaType::Primitive(new)) => {
+                if old != new {
+                    self.push(
+                        path,
+                        SchemaChangeKind::TypeChanged {
+                            from: old.to_string(),
+                            to: new.to_string(),
+                        },
+                        primitive_reads(*new, *old),
+                        primitive_reads(*old, *new),
+                    );
+                }
+            }
+            (SchemaType::Option(old), SchemaType::Option(new)) => {
+                self.compare_id(&old.id, &new.id, &path.to_owned().inner());
+            }
+            (SchemaType::Seq(old), SchemaType::Seq(new)) => {
+                self.compare_id(&old.id, &new.id, path);
+            }
+            (SchemaType::Map(old), SchemaType::Map(new)) => {
+                self.compare_id(&old.key.id, &new.key.id, path);
+                self.compare_id(&old.value.id, &new.value.id, path);
+            }
+            (SchemaType::Tuple(old), SchemaType::Tuple(new)) => {
+                self.compare_tuple(old, new, path);
+            }
+            (SchemaType::TupleStruct(old), SchemaType::TupleStruct(new))
+                if old.0.len() == 1 && new.0.len() == 1 =>
+            {
+                self.compare_id(&old.0[0].id, &new.0[0].id, &path.to_owned().inner());
+            }
+            (SchemaType::TupleStruct(old), SchemaType::TupleStruct(new)) => {
+                self.compare_tuple(old, new, path);
+            }
+            (SchemaType::Array(old), SchemaType::Array(new)) => {
+                if old.count != new.count {
+                    self.push(
+                        path,
+                        SchemaChangeKind::CountChanged {
+                            from: old.count,
+                            to: new.count,
+                        },
+                        false,
+                        false,
+                    );
+                }
+                self.compare_id(&old.type_instance.id, &new.type_instance.id, path);
+            }
+            (SchemaType::Slice(old), SchemaType::Slice(new)) => {
+                self.compare_id(&old.type_instance.id, &new.type_instance.id, path);
+            }
+            (SchemaType::Struct(old), SchemaType::Struct(new)) => {
+                self.compare_struct(old, new, path);
+            }
+            (SchemaType::Enum(old), SchemaType::Enum(new)) => {
+                let mut names = old.0.keys().chain(new.0.keys()).collect::<Vec<_>>();
+                names.sort();
+                names.dedup();
+                for name in names {
+                    match (old.0.get(name), new.0.get(name)) {
+                        (Some(old), Some(new)) => {
+                            self.compare_variant(name, old, new, &path.to_owned().field(name));
+                        }
+                        (Some(_), None) => self.push(
+                            path,
+                            SchemaChangeKind::VariantRemoved(name.to_owned()),
+                            false,
+                            true,
+                        ),
+                        (None, Some(_)) => self.push(
+                            path,
+                            SchemaChangeKind::VariantAdded(name.to_owned()),
+                            true,
+                            false,
+                        ),
+                        (None, None) => unreachable!(),
+                    }
+                }
+            }
+            (old, new) => self.push(
+                path,
+                SchemaChangeKind::TypeChanged {
+                    from: type_kind(old).to_owned(),
+                    to: type_kind(new).to_owned(),
+                },
+                false,
+                false,
+            ),
+        }
+    }
+
+    fn compare_variant(
+        &mut self,
+        name: &str,
+        old: &SchemaTypeEnumVariant,
+        new: &SchemaTypeEnumVariant,
+        path: &IntermediatePath,
+    ) {
+        match (old, new) {
+            (SchemaTypeEnumVariant::Empty, SchemaTypeEnumVariant::Empty) => {}
+            (SchemaTypeEnumVariant::Tuple(old), SchemaTypeEnumVariant::Tuple(new))
+                if old.0.len() == 1 && new.0.len() == 1 =>
+            {
+                self.compare_id(&old.0[0].id, &new.0[0].id, path);
+            }
+            (SchemaTypeEnumVariant::Tuple(old), SchemaTypeEnumVariant::Tuple(new))
+                if old.0.len() != 1 && new.0.len() != 1 =>
+            {
+                self.compare_tuple(old, new, path);
+            }
+            (SchemaTypeEnumVariant::Struct(old), SchemaTypeEnumVariant::Struct(new)) => {
+                self.compare_struct(old, new, path);
+            }
+            (old, new) => self.push(
+                path,
+                SchemaChangeKind::TypeChanged {
+                    from: format!("{} `{}`", variant_kind(old), name),
+                    to: format!("{} `{}`", variant_kind(new), name),
+                },
+                false,
+                false,
+            ),
+        }
+    }
+
+    fn compare_tuple(
+        &mut self,
+        old: &SchemaTypeTuple,
+        new: &SchemaTypeTuple,
+        path: &IntermediatePath,
+    ) {
+        if old.0.len() != new.0.len() {
+            self.push(
+                path,
+                SchemaChangeKind::ArityChanged {
+                    from: old.0.len(),
+                    to: new.0.len(),
+                },
+                false,
+                false,
+            );
+        }
+        for (index, (old, new)) in old.0.iter().zip(new.0.iter()).enumerate() {
+            self.compare_id(&old.id, &new.id, &path.to_owned().index(index));
+        }
+    }
+
+    fn compare_struct(
+        &mut self,
+        old: &SchemaTypeStruct,
+        new: &SchemaTypeStruct,
+        path: &IntermediatePath,
+    ) {
+        let mut names = old.0.keys().chain(new.0.keys()).collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        for name in names {
+            match (old.0.get(name), new.0.get(name)) {
+                (Some(old), Some(new)) => {
+                    self.compare_id(&old.id, &new.id, &path.to_owned().field(name));
+                }
+                (Some(old), None) => {
+                    let optional = self.old.is_optional(&old.id);
+                    self.push(
+                        path,
+                        SchemaChangeKind::FieldRemoved(name.to_owned()),
+                        true,
+                        optional,
+                    );
+                }
+                (None, Some(new)) => {
+                    let required = !self.new.is_optional(&new.id);
+                    self.push(
+                        path,
+                        SchemaChangeKind::FieldAdded {
+                            name: name.to_owned(),
+                            required,
+                        },
+                        !required,
+                        true,
+                    );
+                }
+                (None, None) => unreachable!(),
+            }
+        }
+    }
+}
+
+/// Tells if values of `value` type can be read as `reader` type.
+fn primitive_reads(reader: SchemaTypePrimitive, value: SchemaTypePrimitive) -> bool {
+    if reader == value {
+        return true;
+    }
+    match (reader, value) {
+        (SchemaTypePrimitive::F64, SchemaTypePrimitive::F32) => true,
+        (SchemaTypePrimitive::String, SchemaTypePrimitive::Char) => true,
+        (SchemaTypePrimitive::F32 | SchemaTypePrimitive::F64, value) => {
+            integer_range(value).is_some()
+        }
+        (reader, value) => match (integer_range(reader), integer_range(value)) {
+            (Some((reader_min, reader_max)), Some((value_min, value_max))) => {
+                reader_min <= value_min && reader_max >= value_max
+            }
+            _ => false,
+        },
+    }
+}
+
+/// Range of values of integer type, or `None` if it is not integer.
+fn integer_range(primitive: SchemaTypePrimitive) -> Option<(i128, u128)> {
+    match primitive {
+        SchemaTypePrimitive::I8 => Some((i8::MIN as _, i8::MAX as _)),
+        SchemaTypePrimitive::I16 => Some((i16::MIN as _, i16::MAX as _)),
+        SchemaTypePrimitive::I32 => Some((i32::MIN as _, i32::MAX as _)),
+        SchemaTypePrimitive::I64 => Some((i64::MIN as _, i64::MAX as _)),
+        SchemaTypePrimitive::I128 => Some((i128::MIN, i128::MAX as _)),
+        SchemaTypePrimitive::Isize => Some((isize::MIN as _, isize::MAX as _)),
+        SchemaTypePrimitive::U8 => Some((0, u8::MAX as _)),
+        SchemaTypePrimitive::U16 => Some((0, u16::MAX as _)),
+        SchemaTypePrimitive::U32 => Some((0, u32::MAX as _)),
+        SchemaTypePrimitive::U64 => Some((0, u64::MAX as _)),
+        SchemaTypePrimitive::U128 => Some((0, u128::MAX)),
+        SchemaTypePrimitive::Usize => Some((0, usize::MAX as _)),
+        _ => None,
+    }
+}
+
+fn type_kind(data_type: &SchemaType) -> &'static str {
+    match data_type {
+        SchemaType::Primitive(_) => "primitive",
+        SchemaType::Option(_) => "option",
+        SchemaType::Seq(_) => "sequence",
+        SchemaType::Map(_) => "map",
+        SchemaType::Tuple(_) => "tuple",
+        SchemaType::Array(_) => "array",
+        SchemaType::Slice(_) => "slice",
+        SchemaType::TupleStruct(_) => "tuple struct",
+        SchemaType::Struct(_) => "struct",
+        SchemaType::Enum(_) => "enum",
+    }
+}
@@ -0,0 +1,250 @@
+use crate::{
+    schema::{
+        builtin_type, wrapped_content, SchemaIdContainer, SchemaPackage, SchemaType,
+        SchemaTypeEnumVariant, SchemaTypeInstance, SchemaTypePrimitive, SchemaTypeStruct,
+        SchemaTypeTuple,
+    },
+    value::intermediate::Intermediate,
+};
+
+/// Identifier of JSON Schema draft produced by `SchemaPackage::to_json_schema`.
+pub const JSON_SCHEMA_DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+impl SchemaPackage {
+    /// Produces JSON Schema (2020-12 draft) document describing JSON serialized type of `root` id.
+    ///
+    /// Every schema in package gets its definition in `$defs`, named by its id. Enums follow
+    /// default (externally tagged) representation. Types without schema in package are
+    /// described in place when they are known std types, otherwise they accept any value.
+    ///
+    /// # Example
+    /// ```rust
+    /// use serde_intermediate::{SchemaIntermediate, SchemaPackage};
+    ///
+    /// #[derive(SchemaIntermediate)]
+    /// struct Config {
+    ///     /// Window title.
+    ///     title: String,
+    /// }
+    ///
+    /// let mut package = SchemaPackage::default();
+    /// let root = Config::schema(&mut package);
+    /// let document = package.to_json_schema(&root);
+    /// let content = serde_json::to_string(&document).unwrap();
+    /// assert!(content.contains(r#""title":{"type":"string","description":"Window title."}"#));
+    /// ```
+    pub fn to_json_schema(&self, root: &SchemaIdContainer) -> Intermediate {
+        let mut ids = self.schemas.keys().collect::<Vec<_>>();
+        ids.sort_by_cached_key(|id| id.to_string());
+        let definitions = ids.into_iter().fold(Intermediate::map(), |result, id| {
+            let schema = &self.schemas[id];
+            let content = with_description(
+                self.json_schema_type(&schema.data_type),
+                &schema.description,
+            );
+            result.property(id.to_string(), content)
+        });
+        let mut document = Intermediate::map().property("$schema", JSON_SCHEMA_DRAFT);
+        if let Intermediate::Map(entries) = self.json_schema_id(root) {
+            for (key, value) in entries {
+                document = document.property(key, value);
+            }
+        }
+        document.property("$defs", definitions)
+    }
+
+    fn json_schema_id(&self, id: &SchemaIdContainer) -> Intermediate {
+        if let Some((id, _)) = self.find_entry(id) {
+            return Intermediate::map().property("$ref", json_schema_reference(id));
+        }
+        let tree = match id.to_owned().try_into_tree() {
+            Some(tree) => tree,
+            None => return Intermediate::map(),
+        };
+        if let Some(content) = wrapped_content(&tree) {
+            return self.json_schema_id(&SchemaIdContainer::Tree(content.to_owned()));
+        }
+        match builtin_type(&tree) {
+            Some(data_type) => self.json_schema_type(&data_type),
+            None => Intermediate::map(),
+        }
+    }
+
+    fn json_schema_instance(&self, instance: &SchemaTypeInstance) -> Intermediate {
+        with_description(self.json_schema_id(&instance.id), &instance.description)
+    }
+
+    fn json_schema_type(&self, data_type: &SchemaType) -> Intermediate {
+        match data_type {
+            SchemaType::Primitive(primitive) => json_schema_primitive(*primitive),
+            SchemaType::Option(content) => Intermediate::map().property(
+                "anyOf",
+                Intermediate::seq()
+                    .item(Intermediate::map().property("type", "null"))
+                    .item(self.json_schema_instance(content)),
+            ),
+            SchemaType::Seq(content) => Intermediate::map()
+                .property("type", "array")
+                .property("items", self.json_schema_instance(content)),
+            SchemaType::Map(content) => Intermediate::map().property("type", "object").property(
+                "additionalProperties",
+                self.json_schema_instance(&content.value),
+            ),
+            SchemaType::Tuple(content) => self.json_schema_tuple(content),
+            SchemaType::TupleStruct(content) if content.0.len() == 1 => {
+                self.json_schema_instance(&content.0[0])
+            }
+            SchemaType::TupleStruct(content) => self.json_schema_tuple(content),
+            SchemaType::Array(content) => Intermediate::map()
+                .property("type", "array")
+                .property("items", self.json_schema_instance(&content.type_instance))
+                .property("minItems", content.count as u64)
+                .property("maxItems", content.count as u64),
+            SchemaType::Slice(content) => Intermediate::map()
+                .property("type", "array")
+                .property("items", self.json_schema_instance(&content.type_instance)),
+            SchemaType::Struct(content) if content.0.is_empty() => Intermediate::map()
+                .property("type", Intermediate::seq().item("null").item("object"))
+                .property("additionalProperties", false),
+            SchemaType::Struct(content) => self.json_schema_struct(content),
+            SchemaType::Enum(content) => {
+                let mut names = content.0.keys().collect::<Vec<_>>();
+                names.sort();
+                let variants = names.into_iter().fold(Intermediate::seq(), |result, name| {
+                    let variant = match &content.0[name] {
+                        SchemaTypeEnumVariant::Empty => {
+                            return result
+                                .item(Intermediate::map().property("const", name.as_str()));
+                        }
+                        SchemaTypeEnumVariant::Tuple(content) if content.0.len() == 1 => {
+                            self.json_schema_instance(&content.0[0])
+                        }
+                        SchemaTypeEnumVariant::Tuple(content) => self.json_schema_tuple(content),
+                        SchemaTypeEnumVariant::Struct(content) => self.json_schema_struct(content),
+                    };
+                    result.item(
+                        Intermediate::map()
+                            .property("type", "object")
+                            .property(
+                                "properties",
+                                Intermediate::map().property(name.as_str(), variant),
+                            )
+                            .property("required", Intermediate::seq().item(name.as_str()))
+                            .property("additionalProperties", false),
+                    )
+                });
+                Intermediate::map().property("oneOf", variants)
+            }
+        }
+    }
+
+    fn json_schema_tuple(&self, content: &SchemaTypeTuple) -> Intermediate {
+        let items = content.0.iter().fold(Intermediate::seq(), |result, item| {
+            result.item(self.json_schema_instance(item))
+        });
+        Intermediate::map()
+            .property("type", "array")
+            .property("prefixItems", items)
+            .property("items", false)
+            .property("minItems", content.0.len() as u64)
+    }
+
+    fn json_schema_struct(&self, content: &SchemaTypeStruct) -> Intermediate {
+        let mut names = content.0.keys().collect::<Vec<_>>();
+        names.sort();
+        let properties = names.iter().fold(Intermediate::map(), |result, name| {
+            result.property(name.as_str(), self.json_schema_instance(&content.0[*name]))
+        });
+        let required = names
+            .iter()
+            .filter(|name| !self.is_optional(&content.0[**name].id))
+            .fold(Intermediate::seq(), |result, name| {
+                result.item(name.as_str())
+            });
+        Intermediate::map()
+            .property("type", "object")
+            .property("properties", properties)
+            .property("required", required)
+            .property("additionalProperties", false)
+    }
+}
+
+fn json_schema_primitive(primitive: SchemaTypePrimitive) -> Intermediate {
+    let integer = |minimum: Intermediate, maximum: Intermediate| {
+        Intermediate::map()
+            .property("type", "integer")
+            .property("minimum", minimum)
+            .property("maximum", maximum)
+    };
+    match primitive {
+        SchemaTypePrimitive::Unit => Intermediate::map().property("type", "null"),
+        SchemaTypePrimitive::Bool => Intermediate::map().property("type", "boolean"),
+        SchemaTypePrimitive::I8 => integer(i8::MIN.into(), i8::MAX.into()),
+        SchemaTypePrimitive::I16 => integer(i16::MIN.into(), i16::MAX.into()),
+        SchemaTypePrimitive::I32 => integer(i32::MIN.into(), i32::MAX.into()),
+        SchemaTypePrimitive::I64 | SchemaTypePrimitive::Isize => {
+            integer(i64::MIN.into(), i64::MAX.into())
+        }
+        SchemaTypePrimitive::U8 => integer(u8::MIN.into(), u8::MAX.into()),
+        SchemaTypePrimitive::U16 => integer(u16::MIN.into(), u16::MAX.into()),
+        SchemaTypePrimitive::U32 => integer(u32::MIN.into(), u32::MAX.into()),
+        SchemaTypePrimitive::U64 | SchemaTypePrimitive::Usize => {
+            integer(u64::MIN.into(), u64::MAX.into())
+        }
+        SchemaTypePrimitive::I128 => Intermediate::map().property("type", "integer"),
+        SchemaTypePrimitive::U128 => Intermediate::map()
+            .property("type", "integer")
+            .property("minimum", 0_u64),
+        SchemaTypePrimitive::F32 | SchemaTypePrimitive::F64 => {
+            Intermediate::map().property("type", "number")
+        }
+        SchemaTypePrimitive::Char => Intermediate::map()
+            .property("type", "string")
+            .property("minLength", 1_u64)
+            .property("maxLength", 1_u64),
+        SchemaTypePrimitive::String => Intermediate::map().property("type", "string"),
+    }
+}
+
+/// Puts description in schema, replacing one it already has.
+fn with_description(content: Intermediate, description: &str) -> Intermediate {
+    match content {
+        Intermediate::Map(mut entries) if !description.is_empty() => {
+            entries.retain(|(key, _)| key.as_str() != Some("description"));
+            Intermediate::Map(entries).property("description", description)
+        }
+        content => content,
+    }
+}
+
+/// Reference to definition in `$defs`: JSON Pointer put in URI fragment.
+fn json_schema_reference(id: &SchemaIdContainer) -> String {
+    let mut result = "#/$defs/".to_owned();
+    for byte in id.to_string().bytes() {
+        match byte {
+            b'~' => result.push_str("~0"),
+            b'/' => result.push_str("~1"),
+            b'a'..=b'z'
+            | b'A'..=b'Z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b':'
+            | b'@'
+            | b'!'
+            | b'$'
+            | b'&'
+            | b'\''
+            | b'('
+            | b')'
+            | b'*'
+            | b'+'
+            | b','
+            | b';'
+            | b'=' => result.push(byte as char),
+            byte => result.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    result
+}
@@ -0,0 +1,852 @@
+pub mod codegen;
+pub mod compatibility;
+pub mod json_schema;
+pub mod validation;
+
+use pest::{iterators::Pairs, Parser};
+use pest_derive::Parser;
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, LinkedList, VecDeque},
+    hash::Hash,
+    marker::PhantomData,
+    rc::Rc,
+    sync::{Arc, Mutex, RwLock},
+};
+
+#[derive(Parser)]
+#[grammar = "schema.grammar.pest"]
+struct SchemaIdParser;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SchemaIdTree {
+    Tuple(Vec<Self>),
+    Path { path: Vec<String>, args: Vec<Self> },
+}
+
+impl SchemaIdTree {
+    pub fn new<T>() -> Self {
+        let id = SchemaId::new::<T>();
+        id.tree()
+            .unwrap_or_else(|| panic!("Cannot produce schema id tree from: {}", id.id()))
+    }
+
+    pub fn as_tuple(&self) -> Option<&[Self]> {
+        match self {
+            Self::Tuple(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    pub fn as_path(&self) -> Option<&[String]> {
+        match self {
+            Self::Path { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    pub fn as_path_name(&self) -> Option<&str> {
+        self.as_path()
+            .and_then(|list| list.last())
+            .map(|segment| segment.as_str())
+    }
+
+    pub fn as_path_args(&self) -> Option<&[Self]> {
+        match self {
+            Self::Path { args, .. } => Some(args),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for SchemaIdTree {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tuple(list) => {
+                write!(f, "(")?;
+                for (i, item) in list.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    item.fmt(f)?;
+                }
+                write!(f, ")")?;
+            }
+            Self::Path { path, args } => {
+                for (i, segment) in path.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "::")?;
+                    }
+                    segment.fmt(f)?;
+                }
+                if !args.is_empty() {
+                    write!(f, "<")?;
+                    for (i, arg) in args.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        arg.fmt(f)?;
+                    }
+                    write!(f, ">")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<SchemaId> for SchemaIdTree {
+    type Error = ();
+
+    fn try_from(id: SchemaId) -> Result<Self, Self::Error> {
+        id.tree().ok_or(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SchemaId(String);
+
+impl SchemaId {
+    pub fn new<T>() -> Self {
+        Self(std::any::type_name::<T>().to_string())
+    }
+
+    pub fn id(&self) -> &str {
+        &self.0
+    }
+
+    pub fn tree(&self) -> Option<SchemaIdTree> {
+        let pairs = SchemaIdParser::parse(Rule::main, &self.0)
+            .ok()?
+            .next()?
+            .into_inner();
+        Some(Self::parse_tree(pairs))
+    }
+
+    fn parse_tree(mut pairs: Pairs<Rule>) -> SchemaIdTree {
+        let pair = pairs.next().unwrap();
+        match pair.as_rule() {
+            Rule::tuple_element => SchemaIdTree::Tuple(Self::parse_list(pair.into_inner())),
+            Rule::path_element => {
+                let mut pairs = pair.into_inner();
+                let path = Self::parse_path(pairs.next().unwrap().into_inner());
+                let args = pairs
+                    .next()
+                    .map(|pair| Self::parse_list(pair.into_inner()))
+                    .unwrap_or_default();
+                SchemaIdTree::Path { path, args }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn parse_path(pairs: Pairs<Rule>) -> Vec<String> {
+        pairs.map(|pair| pair.as_str().to_owned()).collect()
+    }
+
+    fn parse_list(pairs: Pairs<Rule>) -> Vec<SchemaIdTree> {
+        pairs
+            .map(|pair| Self::parse_tree(pair.into_inner()))
+            .collect()
+    }
+}
+
+impl std::fmt::Display for SchemaId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<SchemaIdTree> for SchemaId {
+    fn from(tree: SchemaIdTree) -> Self {
+        Self(tree.to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SchemaIdContainer {
+    Id(SchemaId),
+    Tree(SchemaIdTree),
+}
+
+impl SchemaIdContainer {
+    pub fn new<T>(prefer_tree: bool) -> Self {
+        let id = SchemaId::new::<T>();
+        if prefer_tree {
+            id.tree()
+                .map(|tree| tree.into())
+                .unwrap_or_else(|| id.into())
+        } else {
+            id.into()
+        }
+    }
+
+    pub fn new_id(id: impl Into<SchemaId>) -> Self {
+        Self::Id(id.into())
+    }
+
+    pub fn new_tree(tree: impl Into<SchemaIdTree>) -> Self {
+        Self::Tree(tree.into())
+    }
+
+    pub fn as_id(&self) -> Option<&SchemaId> {
+        match self {
+            Self::Id(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    pub fn as_tree(&self) -> Option<&SchemaIdTree> {
+        match self {
+            Self::Tree(tree) => Some(tree),
+            _ => None,
+        }
+    }
+
+    pub fn into_id(self) -> SchemaId {
+        match self {
+            Self::Id(id) => id,
+            Self::Tree(tree) => tree.into(),
+        }
+    }
+
+    pub fn try_into_tree(self) -> Option<SchemaIdTree> {
+        match self {
+            Self::Id(id) => id.tree(),
+            Self::Tree(tree) => Some(tree),
+        }
+    }
+}
+
+impl std::fmt::Display for SchemaIdContainer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Id(id) => id.fmt(f),
+            Self::Tree(tree) => tree.fmt(f),
+        }
+    }
+}
+
+impl From<SchemaId> for SchemaIdContainer {
+    fn from(id: SchemaId) -> Self {
+        Self::Id(id)
+    }
+}
+
+impl From<SchemaIdTree> for SchemaIdContainer {
+    fn from(tree: SchemaIdTree) -> Self {
+        Self::Tree(tree)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaTypeInstance {
+    pub id: SchemaIdContainer,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl SchemaTypeInstance {
+    pub fn new(id: impl Into<SchemaIdContainer>) -> Self {
+        Self {
+            id: id.into(),
+            description: Default::default(),
+        }
+    }
+
+    pub fn description(mut self, content: impl ToString) -> Self {
+        self.description = content.to_string();
+        self
+    }
+}
+
+impl<ID> From<ID> for SchemaTypeInstance
+where
+    ID: Into<SchemaIdContainer>,
+{
+    fn from(id: ID) -> Self {
+        Self::new(id)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaTypeTuple(pub Vec<SchemaTypeInstance>);
+
+impl SchemaTypeTuple {
+    pub fn item(mut self, type_instance: impl Into<SchemaTypeInstance>) -> Self {
+        self.0.push(type_instance.into());
+        self
+    }
+}
+
+impl<TI> FromIterator<TI> for SchemaTypeTuple
+where
+    TI: Into<SchemaTypeInstance>,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = TI>,
+    {
+        Self(
+            iter.into_iter()
+                .map(|type_instance| type_instance.into())
+                .collect(),
+        )
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaTypeStruct(pub HashMap<String, SchemaTypeInstance>);
+
+impl SchemaTypeStruct {
+    pub fn field(
+        mut self,
+        name: impl ToString,
+        type_instance: impl Into<SchemaTypeInstance>,
+    ) -> Self {
+        self.0.insert(name.to_string(), type_instance.into());
+        self
+    }
+}
+
+impl<N, TI> FromIterator<(N, TI)> for SchemaTypeStruct
+where
+    N: ToString,
+    TI: Into<SchemaTypeInstance>,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (N, TI)>,
+    {
+        Self(
+            iter.into_iter()
+                .map(|(name, type_instance)| (name.to_string(), type_instance.into()))
+                .collect(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaTypeArrayOrSlice {
+    pub type_instance: SchemaTypeInstance,
+    pub count: usize,
+}
+
+impl SchemaTypeArrayOrSlice {
+    pub fn new(type_instance: impl Into<SchemaTypeInstance>, count: usize) -> Self {
+        Self {
+            type_instance: type_instance.into(),
+            count,
+        }
+    }
+}
+
+impl<TI> From<(TI, usize)> for SchemaTypeArrayOrSlice
+where
+    TI: Into<SchemaTypeInstance>,
+{
+    fn from((type_instance, count): (TI, usize)) -> Self {
+        Self::new(type_instance, count)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaTypeMap {
+    pub key: SchemaTypeInstance,
+    pub value: SchemaTypeInstance,
+}
+
+impl SchemaTypeMap {
+    pub fn new(key: impl Into<SchemaTypeInstance>, value: impl Into<SchemaTypeInstance>) -> Self {
+        Self {
+            key: key.into(),
+            value: value.into(),
+        }
+    }
+}
+
+impl<K, V> From<(K, V)> for SchemaTypeMap
+where
+    K: Into<SchemaTypeInstance>,
+    V: Into<SchemaTypeInstance>,
+{
+    fn from((key, value): (K, V)) -> Self {
+        Self::new(key, value)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SchemaTypeEnumVariant {
+    #[default]
+    Empty,
+    Tuple(SchemaTypeTuple),
+    Struct(SchemaTypeStruct),
+}
+
+impl SchemaTypeEnumVariant {
+    pub fn new_tuple(content: impl Into<SchemaTypeTuple>) -> Self {
+        Self::Tuple(content.into())
+    }
+
+    pub fn new_struct(content: impl Into<SchemaTypeStruct>) -> Self {
+        Self::Struct(content.into())
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaTypeEnum(pub HashMap<String, SchemaTypeEnumVariant>);
+
+impl SchemaTypeEnum {
+    pub fn variant(
+        mut self,
+        name: impl ToString,
+        content: impl Into<SchemaTypeEnumVariant>,
+    ) -> Self {
+        self.0.insert(name.to_string(), content.into());
+        self
+    }
+}
+
+impl<N, V> FromIterator<(N, V)> for SchemaTypeEnum
+where
+    N: ToString,
+    V: Into<SchemaTypeEnumVariant>,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (N, V)>,
+    {
+        Self(
+            iter.into_iter()
+                .map(|(name, variant)| (name.to_string(), variant.into()))
+                .collect(),
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SchemaTypePrimitive {
+    Unit,
+    Bool,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    Isize,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    Usize,
+    F32,
+    F64,
+    Char,
+    String,
+}
+
+impl std::fmt::Display for SchemaTypePrimitive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Unit => "()",
+            Self::Bool => "bool",
+            Self::I8 => "i8",
+            Self::I16 => "i16",
+            Self::I32 => "i32",
+            Self::I64 => "i64",
+            Self::I128 => "i128",
+            Self::Isize => "isize",
+            Self::U8 => "u8",
+            Self::U16 => "u16",
+            Self::U32 => "u32",
+            Self::U64 => "u64",
+            Self::U128 => "u128",
+            Self::Usize => "usize",
+            Self::F32 => "f32",
+            Self::F64 => "f64",
+            Self::Char => "char",
+            Self::String => "String",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SchemaType {
+    Primitive(SchemaTypePrimitive),
+    Option(SchemaTypeInstance),
+    Seq(SchemaTypeInstance),
+    Map(SchemaTypeMap),
+    Tuple(SchemaTypeTuple),
+    Array(SchemaTypeArrayOrSlice),
+    Slice(SchemaTypeArrayOrSlice),
+    TupleStruct(SchemaTypeTuple),
+    Struct(SchemaTypeStruct),
+    Enum(SchemaTypeEnum),
+}
+
+impl SchemaType {
+    pub fn new_primitive(content: SchemaTypePrimitive) -> Self {
+        Self::Primitive(content)
+    }
+
+    pub fn new_option(content: impl Into<SchemaTypeInstance>) -> Self {
+        Self::Option(content.into())
+    }
+
+    pub fn new_seq(content: impl Into<SchemaTypeInstance>) -> Self {
+        Self::Seq(content.into())
+    }
+
+    pub fn new_map(content: impl Into<SchemaTypeMap>) -> Self {
+        Self::Map(content.into())
+    }
+
+    pub fn new_tuple(content: impl Into<SchemaTypeTuple>) -> Self {
+        Self::Tuple(content.into())
+    }
+
+    pub fn new_array(content: impl Into<SchemaTypeArrayOrSlice>) -> Self {
+        Self::Array(content.into())
+    }
+
+    pub fn new_slice(content: impl Into<SchemaTypeArrayOrSlice>) -> Self {
+        Self::Slice(content.into())
+    }
+
+    pub fn new_tuple_struct(content: impl Into<SchemaTypeTuple>) -> Self {
+        Self::TupleStruct(content.into())
+    }
+
+    pub fn new_struct(content: impl Into<SchemaTypeStruct>) -> Self {
+        Self::Struct(content.into())
+    }
+
+    pub fn new_enum(content: impl Into<SchemaTypeEnum>) -> Self {
+        Self::Enum(content.into())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Schema {
+    pub data_type: SchemaType,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl Schema {
+    pub fn new(data_type: impl Into<SchemaType>) -> Self {
+        Self {
+            description: Default::default(),
+            data_type: data_type.into(),
+        }
+    }
+
+    pub fn description(mut self, content: impl ToString) -> Self {
+        self.description = content.to_string();
+        self
+    }
+}
+
+impl<T> From<T> for Schema
+where
+    T: Into<SchemaType>,
+{
+    fn from(data_type: T) -> Self {
+        Self::new(data_type)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaPackage {
+    #[serde(default)]
+    pub prefer_tree_id: bool,
+    pub schemas: HashMap<SchemaIdContainer, Schema>,
+}
+
+impl SchemaPackage {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            prefer_tree_id: false,
+            schemas: HashMap::with_capacity(capacity),
+        }
+    }
+
+    pub fn prefer_tree_id(mut self, value: bool) -> Self {
+        self.prefer_tree_id = value;
+        self
+    }
+
+    /// Adds schema under id, normalized so that it can be found by ids describing same type.
+    pub fn with(
+        &mut self,
+        id: impl Into<SchemaIdContainer>,
+        schema: impl Into<Schema>,
+    ) -> &mut Self {
+        let id = match id.into() {
+            SchemaIdContainer::Id(id) => match id.tree() {
+                Some(tree) => SchemaIdContainer::Id(tree.into()),
+                None => SchemaIdContainer::Id(id),
+            },
+            id => id,
+        };
+        self.schemas.insert(id, schema.into());
+        self
+    }
+
+    /// Finds schema by id, matching ids that describe same type in either id or tree form.
+    /// Schemas inserted directly into `schemas` are matched only if their ids were normalized
+    /// the way `with` does it.
+    pub fn find(&self, id: &SchemaIdContainer) -> Option<&Schema> {
+        self.find_entry(id).map(|(_, schema)| schema)
+    }
+
+    fn find_entry(&self, id: &SchemaIdContainer) -> Option<(&SchemaIdContainer, &Schema)> {
+        if let Some(entry) = self.schemas.get_key_value(id) {
+            return Some(entry);
+        }
+        let tree = id.to_owned().try_into_tree()?;
+        let id = SchemaIdContainer::Id(tree.to_owned().into());
+        self.schemas
+            .get_key_value(&id)
+            .or_else(|| self.schemas.get_key_value(&SchemaIdContainer::Tree(tree)))
+    }
+
+    /// Finds type described by id, either in package or among known std types.
+    fn resolve(&self, id: &SchemaIdContainer) -> Option<Cow<'_, SchemaType>> {
+        if let Some(schema) = self.find(id) {
+            return Some(Cow::Borrowed(&schema.data_type));
+        }
+        let tree = id.to_owned().try_into_tree()?;
+        match wrapped_content(&tree) {
+            Some(content) => self.resolve(&SchemaIdContainer::Tree(content.to_owned())),
+            None => builtin_type(&tree).map(Cow::Owned),
+        }
+    }
+
+    /// Fields of `Option` type can be omitted.
+    fn is_optional(&self, id: &SchemaIdContainer) -> bool {
+        self.resolve(id)
+            .is_some_and(|data_type| matches!(data_type.as_ref(), SchemaType::Option(_)))
+    }
+}
+
+pub trait SchemaIntermediate: Sized {
+    fn schema(package: &mut SchemaPackage) -> SchemaIdContainer;
+}
+
+/// Type of value serialized by known std type of given id.
+fn builtin_type(tree: &SchemaIdTree) -> Option<SchemaType> {
+    let (name, args) = match tree {
+        SchemaIdTree::Tuple(list) if list.is_empty() => {
+            return Some(SchemaType::new_primitive(SchemaTypePrimitive::Unit));
+        }
+        SchemaIdTree::Tuple(list) => {
+            return Some(SchemaType::new_tuple(
+                list.iter().cloned().collect::<SchemaTypeTuple>(),
+            ));
+        }
+        SchemaIdTree::Path { path, args } => (path.last()?.as_str(), args.as_slice()),
+    };
+    let primitive = match (name, args) {
+        ("bool", []) => SchemaTypePrimitive::Bool,
+        ("i8", []) => SchemaTypePrimitive::I8,
+        ("i16", []) => SchemaTypePrimitive::I16,
+        ("i32", []) => SchemaTypePrimitive::I32,
+        ("i64", []) => SchemaTypePrimitive::I64,
+        ("i128", []) => SchemaTypePrimitive::I128,
+        ("isize", []) => SchemaTypePrimitive::Isize,
+        ("u8", []) => SchemaTypePrimitive::U8,
+        ("u16", []) => SchemaTypePrimitive::U16,
+        ("u32", []) => SchemaTypePrimitive::U32,
+        ("u64", []) => SchemaTypePrimitive::U64,
+        ("u128", []) => SchemaTypePrimitive::U128,
+        ("usize", []) => SchemaTypePrimitive::Usize,
+        ("f32", []) => SchemaTypePrimitive::F32,
+        ("f64", []) => SchemaTypePrimitive::F64,
+        ("char", []) => SchemaTypePrimitive::Char,
+        ("String" | "str", []) => SchemaTypePrimitive::String,
+        ("Option", [content]) => return Some(SchemaType::new_option(content.to_owned())),
+        ("Vec" | "VecDeque" | "LinkedList" | "HashSet" | "BTreeSet" | "BinaryHeap", [item, ..]) => {
+            return Some(SchemaType::new_seq(item.to_owned()))
+        }
+        ("HashMap" | "BTreeMap", [key, value, ..]) => {
+            return Some(SchemaType::new_map((key.to_owned(), value.to_owned())));
+        }
+        ("PhantomData", [_]) => {
+            return Some(SchemaType::new_struct(SchemaTypeStruct::default()));
+        }
+        _ => return None,
+    };
+    Some(SchemaType::new_primitive(primitive))
+}
+
+/// Content of known std type serialized as its content.
+fn wrapped_content(tree: &SchemaIdTree) -> Option<&SchemaIdTree> {
+    match (tree.as_path_name()?, tree.as_path_args()?) {
+        ("Box" | "Rc" | "Arc" | "Cell" | "RefCell" | "Mutex" | "RwLock" | "Cow", [content]) => {
+            Some(content)
+        }
+        _ => None,
+    }
+}
+
+fn variant_kind(variant: &SchemaTypeEnumVariant) -> &'static str {
+    match variant {
+        SchemaTypeEnumVariant::Empty => "unit variant",
+        SchemaTypeEnumVariant::Tuple(content) if content.0.len() == 1 => "newtype variant",
+        SchemaTypeEnumVariant::Tuple(_) => "tuple variant",
+        SchemaTypeEnumVariant::Struct(_) => "struct variant",
+    }
+}
+
+macro_rules! impl_schema {
+    (@primitive $type:ty => $variant:ident) => {
+        impl SchemaIntermediate for $type {
+            fn schema(package: &mut SchemaPackage) -> SchemaIdContainer {
+                let id = SchemaIdContainer::new::<Self>(package.prefer_tree_id);
+                package.with(
+                    id.to_owned(),
+                    SchemaType::new_primitive(SchemaTypePrimitive::$variant),
+                );
+                id
+            }
+        }
+    };
+    (@seq $type:ident) => {
+        impl<T> SchemaIntermediate for $type<T>
+        where
+            T: SchemaIntermediate,
+        {
+            fn schema(package: &mut SchemaPackage) -> SchemaIdContainer {
+                let id = SchemaIdContainer::new::<Self>(package.prefer_tree_id);
+                let item = T::schema(package);
+                package.with(id.to_owned(), SchemaType::new_seq(item));
+                id
+            }
+        }
+    };
+    (@map $type:ident) => {
+        impl<K, V> SchemaIntermediate for $type<K, V>
+        where
+            K: SchemaIntermediate,
+            V: SchemaIntermediate,
+        {
+            fn schema(package: &mut SchemaPackage) -> SchemaIdContainer {
+                let id = SchemaIdContainer::new::<Self>(package.prefer_tree_id);
+                let key = K::schema(package);
+                let value = V::schema(package);
+                package.with(id.to_owned(), SchemaType::new_map((key, value)));
+                id
+            }
+        }
+    };
+    (@wrapper $type:ident) => {
+        /// Serialized as its content, so it shares schema of its content.
+        impl<T> SchemaIntermediate for $type<T>
+        where
+            T: SchemaIntermediate,
+        {
+            fn schema(package: &mut SchemaPackage) -> SchemaIdContainer {
+                T::schema(package)
+            }
+        }
+    };
+    (@tuple $( $id:ident ),+ ) => {
+        impl< $( $id ),+ > SchemaIntermediate for ( $( $id, )+ )
+        where
+            $( $id: SchemaIntermediate ),+
+        {
+            fn schema(package: &mut SchemaPackage) -> SchemaIdContainer {
+                let id = SchemaIdContainer::new::<Self>(package.prefer_tree_id);
+                let content = SchemaTypeTuple::default() $( .item($id::schema(package)) )+;
+                package.with(id.to_owned(), SchemaType::new_tuple(content));
+                id
+            }
+        }
+    };
+}
+
+impl_schema!(@primitive () => Unit);
+impl_schema!(@primitive bool => Bool);
+impl_schema!(@primitive i8 => I8);
+impl_schema!(@primitive i16 => I16);
+impl_schema!(@primitive i32 => I32);
+impl_schema!(@primitive i64 => I64);
+impl_schema!(@primitive i128 => I128);
+impl_schema!(@primitive isize => Isize);
+impl_schema!(@primitive u8 => U8);
+impl_schema!(@primitive u16 => U16);
+impl_schema!(@primitive u32 => U32);
+impl_schema!(@primitive u64 => U64);
+impl_schema!(@primitive u128 => U128);
+impl_schema!(@primitive usize => Usize);
+impl_schema!(@primitive f32 => F32);
+impl_schema!(@primitive f64 => F64);
+impl_schema!(@primitive char => Char);
+impl_schema!(@primitive String => String);
+impl_schema!(@seq Vec);
+impl_schema!(@seq VecDeque);
+impl_schema!(@seq LinkedList);
+impl_schema!(@seq HashSet);
+impl_schema!(@seq BTreeSet);
+impl_schema!(@seq BinaryHeap);
+impl_schema!(@map HashMap);
+impl_schema!(@map BTreeMap);
+impl_schema!(@wrapper Box);
+impl_schema!(@wrapper Rc);
+impl_schema!(@wrapper Arc);
+impl_schema!(@wrapper Cell);
+impl_schema!(@wrapper RefCell);
+impl_schema!(@wrapper Mutex);
+impl_schema!(@wrapper RwLock);
+impl_schema!(@tuple A);
+impl_schema!(@tuple A, B);
+impl_schema!(@tuple A, B, C);
+impl_schema!(@tuple A, B, C, D);
+impl_schema!(@tuple A, B, C, D, E);
+impl_schema!(@tuple A, B, C, D, E, F);
+impl_schema!(@tuple A, B, C, D, E, F, G);
+impl_schema!(@tuple A, B, C, D, E, F, G, H);
+impl_schema!(@tuple A, B, C, D, E, F, G, H, I);
+impl_schema!(@tuple A, B, C, D, E, F, G, H, I, J);
+impl_schema!(@tuple A, B, C, D, E, F, G, H, I, J, K);
+impl_schema!(@tuple A, B, C, D, E, F, G, H, I, J, K, L);
+
+impl<T> SchemaIntermediate for Option<T>
+where
+    T: SchemaIntermediate,
+{
+    fn schema(package: &mut SchemaPackage) -> SchemaIdContainer {
+        let id = SchemaIdContainer::new::<Self>(package.prefer_tree_id);
+        let content = T::schema(package);
+        package.with(id.to_owned(), SchemaType::new_option(content));
+        id
+    }
+}
+
+impl<T, const N: usize> SchemaIntermediate for [T; N]
+where
+    T: SchemaIntermediate,
+{
+    fn schema(package: &mut SchemaPackage) -> SchemaIdContainer {
+        let id = SchemaIdContainer::new::<Self>(package.prefer_tree_id);
+        let item = T::schema(package);
+        package.with(id.to_owned(), SchemaType::new_array((item, N)));
+        id
+    }
+}
+
+impl<T> SchemaIntermediate for PhantomData<T> {
+    fn schema(package: &mut SchemaPackage) -> SchemaIdContainer {
+        let id = SchemaIdContainer::new::<Self>(package.prefer_tree_id);
+        package.with(
+            id.to_owned(),
+            SchemaType::new_struct(SchemaTypeStruct::default()),
+        );
+        id
+    }
+}
@@ -0,0 +1,439 @@
+use crate::{
+    schema::{
+        variant_kind, SchemaIdContainer, SchemaPackage, SchemaType, SchemaTypeEnumVariant,
+        SchemaTypePrimitive, SchemaTypeStruct,
+    },
+    value::{intermediate::Intermediate, path::IntermediatePath},
+};
+
+/// Reason of value not matching its schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaMismatchReason {
+    /// Value kind doesn't match type.
+    WrongType { expected: String, found: String },
+    /// Struct field required by schema is not present.
+    MissingField(String),
+    /// Struct field is not declared in schema.
+    UnknownField(String),
+    /// Enum variant is not declared in schema.
+    UnknownVariant(String),
+    /// Enum variant has different kind of content than declared in schema.
+    WrongVariantKind {
+        variant: String,
+        expected: String,
+        found: String,
+    },
+    /// Tuple has different number of items than declared in schema.
+    WrongArity { expected: usize, found: usize },
+    /// Array has different number of items than declared in schema.
+    WrongCount { expected: usize, found: usize },
+}
+
+impl std::fmt::Display for SchemaMismatchReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WrongType { expected, found } => {
+                write!(f, "expected `{}`, found {}", expected, found)
+            }
+            Self::MissingField(name) => write!(f, "missing field: `{}`", name),
+            Self::UnknownField(name) => write!(f, "unknown field: `{}`", name),
+            Self::UnknownVariant(name) => write!(f, "unknown variant: `{}`", name),
+            Self::WrongVariantKind {
+                variant,
+                expected,
+                found,
+            } => write!(
+                f,
+                "variant `{}` expected to be {}, found {}",
+                variant, expected, found
+            ),
+            Self::WrongArity { expected, found } => {
+                write!(f, "expected {} tuple items, found {}", expected, found)
+            }
+            Self::WrongCount { expected, found } => {
+                write!(f, "expected {} array items, found {}", expected, found)
+            }
+        }
+    }
+}
+
+/// Mismatch between value and schema, found at given location in value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaMismatch {
+    pub path: IntermediatePath,
+    pub reason: SchemaMismatchReason,
+}
+
+impl std::fmt::Display for SchemaMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at `{}`: {}", self.path, self.reason)
+    }
+}
+
+impl SchemaPackage {
+    /// Checks if value matches type of `root` id, returning all found mismatches.
+    ///
+    /// Types without schema in package are checked by their id: primitives, `String`, `Option`,
+    /// tuples, std collections and smart pointers. Other types without schema accept any value.
+    /// Values read from self-describing formats are accepted in their layouts too: structs as
+    /// maps with string keys, unit enum variants as strings with their names.
+    ///
+    /// # Example
+    /// ```rust
+    /// use serde_intermediate::{
+    ///     schema::validation::SchemaMismatchReason, SchemaIntermediate, SchemaPackage,
+    /// };
+    ///
+    /// #[derive(SchemaIntermediate)]
+    /// struct Item {
+    ///     name: String,
+    ///     count: usize,
+    /// }
+    ///
+    /// let mut package = SchemaPackage::default();
+    /// let root = Item::schema(&mut package);
+    /// let content = r#"#{ name: "sword", weight: 3 }"#;
+    /// let value = serde_intermediate::intermediate_from_str(content).unwrap();
+    /// let mismatches = package.validate(&value, &root);
+    /// assert_eq!(mismatches.len(), 2);
+    /// assert_eq!(
+    ///     mismatches[0].reason,
+    ///     SchemaMismatchReason::UnknownField("weight".to_owned()),
+    /// );
+    /// assert_eq!(
+    ///     mismatches[1].reason,
+    ///     SchemaMismatchReason::MissingField("count".to_owned()),
+    /// );
+    /// ```
+    pub fn validate(&self, value: &Intermediate, root: &SchemaIdContainer) -> Vec<SchemaMismatch> {
+        let mut result = vec![];
+        self.validate_id(value, root, &IntermediatePath::default(), &mut result);
+        result
+    }
+
+    fn validate_id(
+        &self,
+        value: &Intermediate,
+        id: &SchemaIdContainer,
+        path: &IntermediatePath,
+        result: &mut Vec<SchemaMismatch>,
+    ) {
+        if let Some(data_type) = self.resolve(id) {
+            self.validate_type(value, id, &data_type, path, result);
+        }
+    }
+
+    fn validate_type(
+        &self,
+        value: &Intermediate,
+        id: &SchemaIdContainer,
+        data_type: &SchemaType,
+        path: &IntermediatePath,
+        result: &mut Vec<SchemaMismatch>,
+    ) {
+        match (data_type, value) {
+            (SchemaType::Primitive(primitive), value) if primitive_matches(*primitive, value) => {}
+            (SchemaType::Option(_), Intermediate::Option(None)) => {}
+            (SchemaType::Option(content), Intermediate::Option(Some(inner))) => {
+                self.validate_id(inner, &content.id, &path.to_owned().inner(), result);
+            }
+            (SchemaType::Seq(content), Intermediate::Seq(items)) => {
+                self.validate_items(items, &content.id, path, result);
+            }
+            (SchemaType::Seq(content), Intermediate::Bytes(_))
+                if self.is_primitive(&content.id, SchemaTypePrimitive::U8) => {}
+            (SchemaType::Map(content), Intermediate::Map(entries)) => {
+                for (key, item) in entries {
+                    let path = path.to_owned().key(key.to_owned());
+                    self.validate_id(key, &content.key.id, &path, result);
+                    self.validate_id(item, &content.value.id, &path, result);
+                }
+            }
+            (SchemaType::Struct(content), Intermediate::Struct(fields)) => {
+                self.validate_struct(fields, content, path, result);
+            }
+            (SchemaType::Struct(content), Intermediate::Map(entries))
+                if entries
+                    .iter()
+                    .all(|(key, _)| matches!(key, Intermediate::String(_))) =>
+            {
+                let fields = entries
+                    .iter()
+                    .filter_map(|(key, value)| Some((key.as_str()?.to_owned(), value.to_owned())))
+                    .collect::<Vec<_>>();
+                self.validate_struct(&fields, content, path, result);
+            }
+            (SchemaType::Struct(content), Intermediate::Unit | Intermediate::UnitStruct)
+                if content.0.is_empty() => {}
+            (SchemaType::TupleStruct(content), Intermediate::NewTypeStruct(inner))
+                if content.0.len() == 1 =>
+            {
+                self.validate_id(inner, &content.0[0].id, &path.to_owned().inner(), result);
+            }
+            (
+                SchemaType::Tuple(content) | SchemaType::TupleStruct(content),
+                Intermediate::Tuple(items)
+                | Intermediate::TupleStruct(items)
+                | Intermediate::Seq(items),
+            ) => {
+                let ids = content
+                    .0
+                    .iter()
+                    .map(|item| item.id.to_owned())
+                    .collect::<Vec<_>>();
+                self.validate_tuple(items, &ids, path, result);
+            }
+            (SchemaType::Array(content), Intermediate::Seq(items) | Intermediate::Tuple(items)) => {
+                if items.len() != content.count {
+                    result.push(SchemaMismatch {
+                        path: path.to_owned(),
+                        reason: SchemaMismatchReason::WrongCount {
+                            expected: content.count,
+                            found: items.len(),
+                        },
+                    });
+                }
+                self.validate_items(items, &content.type_instance.id, path, result);
+            }
+            (SchemaType::Slice(content), Intermediate::Seq(items) | Intermediate::Tuple(items)) => {
+                self.validate_items(items, &content.type_instance.id, path, result);
+            }
+            (
+                SchemaType::Enum(content),
+                Intermediate::UnitVariant(name)
+                | Intermediate::NewTypeVariant(name, _)
+                | Intermediate::TupleVariant(name, _)
+                | Intermediate::StructVariant(name, _),
+            ) => {
+                let variant = match content.0.get(name) {
+                    Some(variant) => variant,
+                    None => {
+                        result.push(SchemaMismatch {
+                            path: path.to_owned(),
+                            reason: SchemaMismatchReason::UnknownVariant(name.to_owned()),
+                        });
+                        return;
+                    }
+                };
+                match (variant, value) {
+                    (SchemaTypeEnumVariant::Empty, Intermediate::UnitVariant(_)) => {}
+                    (
+                        SchemaTypeEnumVariant::Tuple(content),
+                        Intermediate::NewTypeVariant(_, inner),
+                    ) if content.0.len() == 1 => {
+                        self.validate_id(inner, &content.0[0].id, &path.to_owned().inner(), result);
+                    }
+                    (
+                        SchemaTypeEnumVariant::Tuple(content),
+                        Intermediate::TupleVariant(_, items),
+                    ) => {
+                        let ids = content
+                            .0
+                            .iter()
+                            .map(|item| item.id.to_owned())
+                            .collect::<Vec<_>>();
+                        self.validate_tuple(items, &ids, path, result);
+                    }
+                    (
+                        SchemaTypeEnumVariant::Struct(content),
+                        Intermediate::StructVariant(_, fields),
+                    ) => {
+                        self.validate_struct(fields, content, path, result);
+                    }
+                    _ => result.push(SchemaMismatch {
+                        path: path.to_owned(),
+                        reason: SchemaMismatchReason::WrongVariantKind {
+                            variant: name.to_owned(),
+                            expected: variant_kind(variant).to_owned(),
+                            found: value_kind(value).to_owned(),
+                        },
+                    }),
+                }
+            }
+            (SchemaType::Enum(content), Intermediate::String(name)) => match content.0.get(name) {
+                Some(SchemaTypeEnumVariant::Empty) => {}
+                Some(variant) => result.push(SchemaMismatch {
+                    path: path.to_owned(),
+                    reason: SchemaMismatchReason::WrongVariantKind {
+                        variant: name.to_owned(),
+                        expected: variant_kind(variant).to_owned(),
+                        found: value_kind(value).to_owned(),
+                    },
+                }),
+                None => result.push(SchemaMismatch {
+                    path: path.to_owned(),
+                    reason: SchemaMismatchReason::UnknownVariant(name.to_owned()),
+                }),
+            },
+            _ => result.push(wrong_type(id, value, path)),
+        }
+    }
+
+    fn is_primitive(&self, id: &SchemaIdContainer, primitive: SchemaTypePrimitive) -> bool {
+        self.resolve(id).is_some_and(|data_type| {
+            matches!(data_type.as_ref(), SchemaType::Primitive(item) if *item == primitive)
+        })
+    }
+
+    fn validate_struct(
+        &self,
+        fields: &[(String, Intermediate)],
+        content: &SchemaTypeStruct,
+        path: &IntermediatePath,
+        result: &mut Vec<SchemaMismatch>,
+    ) {
+        for (name, value) in fields {
+            match content.0.get(name) {
+                Some(field) => {
+                    self.validate_id(value, &field.id, &path.to_owned().field(name), result);
+                }
+                None => result.push(SchemaMismatch {
+                    path: path.to_owned(),
+                    reason: SchemaMismatchReason::UnknownField(name.to_owned()),
+                }),
+            }
+        }
+        let mut missing = content
+            .0
+            .iter()
+            .filter(|(name, field)| {
+                !self.is_optional(&field.id) && !fields.iter().any(|(key, _)| key == *name)
+            })
+            .map(|(name, _)| name.to_owned())
+            .collect::<Vec<_>>();
+        missing.sort();
+        result.extend(missing.into_iter().map(|name| SchemaMismatch {
+            path: path.to_owned(),
+            reason: SchemaMismatchReason::MissingField(name),
+        }));
+    }
+
+    fn validate_tuple(
+        &self,
+        items: &[Intermediate],
+        ids: &[SchemaIdContainer],
+        path: &IntermediatePath,
+        result: &mut Vec<SchemaMismatch>,
+    ) {
+        if items.len() != ids.len() {
+            result.push(SchemaMismatch {
+                path: path.to_owned(),
+                reason: SchemaMismatchReason::WrongArity {
+                    expected: ids.len(),
+                    found: items.len(),
+                },
+            });
+        }
+        for (index, (item, id)) in items.iter().zip(ids.iter()).enumerate() {
+            self.validate_id(item, id, &path.to_owned().index(index), result);
+        }
+    }
+
+    fn validate_items(
+        &self,
+        items: &[Intermediate],
+        id: &SchemaIdContainer,
+        path: &IntermediatePath,
+        result: &mut Vec<SchemaMismatch>,
+    ) {
+        for (index, item) in items.iter().enumerate() {
+            self.validate_id(item, id, &path.to_owned().index(index), result);
+        }
+    }
+}
+
+fn wrong_type(
+    expected: &impl std::fmt::Display,
+    value: &Intermediate,
+    path: &IntermediatePath,
+) -> SchemaMismatch {
+    SchemaMismatch {
+        path: path.to_owned(),
+        reason: SchemaMismatchReason::WrongType {
+            expected: expected.to_string(),
+            found: value_kind(value).to_owned(),
+        },
+    }
+}
+
+fn primitive_matches(primitive: SchemaTypePrimitive, value: &Intermediate) -> bool {
+    match primitive {
+        SchemaTypePrimitive::Unit => matches!(value, Intermediate::Unit),
+        SchemaTypePrimitive::Bool => matches!(value, Intermediate::Bool(_)),
+        SchemaTypePrimitive::I8 => integer_in_range(value, i8::MIN as _, i8::MAX as _),
+        SchemaTypePrimitive::I16 => integer_in_range(value, i16::MIN as _, i16::MAX as _),
+        SchemaTypePrimitive::I32 => integer_in_range(value, i32::MIN as _, i32::MAX as _),
+        SchemaTypePrimitive::I64 => integer_in_range(value, i64::MIN as _, i64::MAX as _),
+        SchemaTypePrimitive::I128 => integer_in_range(value, i128::MIN, i128::MAX as _),
+        SchemaTypePrimitive::Isize => integer_in_range(value, isize::MIN as _, isize::MAX as _),
+        SchemaTypePrimitive::U8 => integer_in_range(value, 0, u8::MAX as _),
+        SchemaTypePrimitive::U16 => integer_in_range(value, 0, u16::MAX as _),
+        SchemaTypePrimitive::U32 => integer_in_range(value, 0, u32::MAX as _),
+        SchemaTypePrimitive::U64 => integer_in_range(value, 0, u64::MAX as _),
+        SchemaTypePrimitive::U128 => integer_in_range(value, 0, u128::MAX),
+        SchemaTypePrimitive::Usize => integer_in_range(value, 0, usize::MAX as _),
+        SchemaTypePrimitive::F32 | SchemaTypePrimitive::F64 => {
+            matches!(value, Intermediate::F32(_) | Intermediate::F64(_))
+                || integer_in_range(value, i128::MIN, u128::MAX)
+        }
+        SchemaTypePrimitive::Char => match value {
+            Intermediate::Char(_) => true,
+            Intermediate::String(text) => text.chars().count() == 1,
+            _ => false,
+        },
+        SchemaTypePrimitive::String => {
+            matches!(value, Intermediate::String(_) | Intermediate::Char(_))
+        }
+    }
+}
+
+fn integer_in_range(value: &Intermediate, min: i128, max: u128) -> bool {
+    let signed = |value: i128| value >= min && (value < 0 || value as u128 <= max);
+    match *value {
+        Intermediate::I8(v) => signed(v as _),
+        Intermediate::I16(v) => signed(v as _),
+        Intermediate::I32(v) => signed(v as _),
+        Intermediate::I64(v) => signed(v as _),
+        Intermediate::I128(v) => signed(v),
+        Intermediate::U8(v) => v as u128 <= max,
+        Intermediate::U16(v) => v as u128 <= max,
+        Intermediate::U32(v) => v as u128 <= max,
+        Intermediate::U64(v) => v as u128 <= max,
+        Intermediate::U128(v) => v <= max,
+        _ => false,
+    }
+}
+
+fn value_kind(value: &Intermediate) -> &'static str {
+    match value {
+        Intermediate::Unit => "unit",
+        Intermediate::Bool(_) => "bool",
+        Intermediate::I8(_) => "i8",
+        Intermediate::I16(_) => "i16",
+        Intermediate::I32(_) => "i32",
+        Intermediate::I64(_) => "i64",
+        Intermediate::I128(_) => "i128",
+        Intermediate::U8(_) => "u8",
+        Intermediate::U16(_) => "u16",
+        Intermediate::U32(_) => "u32",
+        Intermediate::U64(_) => "u64",
+        Intermediate::U128(_) => "u128",
+        Intermediate::F32(_) => "f32",
+        Intermediate::F64(_) => "f64",
+        Intermediate::Char(_) => "char",
+        Intermediate::String(_) => "string",
+        Intermediate::Bytes(_) => "bytes",
+        Intermediate::Option(_) => "option",
+        Intermediate::UnitStruct => "unit struct",
+        Intermediate::UnitVariant(_) => "unit variant",
+        Intermediate::NewTypeStruct(_) => "newtype struct",
+        Intermediate::NewTypeVariant(_, _) => "newtype variant",
+        Intermediate::Seq(_) => "sequence",
+        Intermediate::Tuple(_) => "tuple",
+        Intermediate::TupleStruct(_) => "tuple struct",
+        Intermediate::TupleVariant(_, _) => "tuple variant",
+        Intermediate::Map(_) => "map",
+        Intermediate::Struct(_) => "struct",
+        Intermediate::StructVariant(_, _) => "struct variant",
+    }
+}
@@ -2233,3 +2233,3771 @@ fn test_schema() {
         expected,
     );
 }
+
+#[test]
+fn test_path() {
+    use crate::IntermediatePath;
+
+    let path = "players[3].stats?.hp".parse::<IntermediatePath>().unwrap();
+    assert_eq!(
+        path,
+        IntermediatePath::default()
+            .field("players")
+            .index(3)
+            .field("stats")
+            .inner()
+            .field("hp")
+    );
+    assert_eq!(path.to_string(), "players[3].stats?.hp");
+    let path = r#"map{"key"}.items{42_u8}."hello world""#.parse::<IntermediatePath>().unwrap();
+    assert_eq!(
+        path,
+        IntermediatePath::default()
+            .field("map")
+            .key("key")
+            .field("items")
+            .key(42u8)
+            .field("hello world")
+    );
+    assert_eq!(
+        path.to_string().parse::<IntermediatePath>().unwrap(),
+        path.to_owned()
+    );
+    for content in ["players[", "players.", "players[a]", "map{1_u8"] {
+        assert!(matches!(
+            content.parse::<IntermediatePath>(),
+            Err(crate::Error::InvalidPath(_))
+        ));
+    }
+
+    let mut value = Intermediate::struct_type()
+        .field(
+            "players",
+            Intermediate::seq().item(Intermediate::struct_type().field("name", "John").field(
+                "stats",
+                Intermediate::Option(Some(Box::new(Intermediate::struct_type().field("hp", 100)))),
+            )),
+        )
+        .field("scores", Intermediate::map().property("John", 42));
+    let hp = "players[0].stats?.hp".parse().unwrap();
+    assert_eq!(value.get(&hp), Some(&Intermediate::I32(100)));
+    assert_eq!(
+        value.get(&"players[0].stats.hp".parse().unwrap()),
+        Some(&Intermediate::I32(100))
+    );
+    assert_eq!(value.get(&"players[1]".parse().unwrap()), None);
+    assert_eq!(value.set(&hp, 50).unwrap(), Intermediate::I32(100));
+    assert_eq!(value.get(&hp), Some(&Intermediate::I32(50)));
+    *value.get_mut(&hp).unwrap() = 60.into();
+    assert_eq!(value.get(&hp), Some(&Intermediate::I32(60)));
+    assert!(matches!(
+        value.set(&"players[0].mana".parse().unwrap(), 1),
+        Err(crate::Error::PathNotFound(_))
+    ));
+
+    let score = r#"scores{"John"}"#.parse().unwrap();
+    assert_eq!(value.get(&score), Some(&Intermediate::I32(42)));
+    assert_eq!(
+        value.get(&"scores.John".parse().unwrap()),
+        Some(&Intermediate::I32(42))
+    );
+    assert_eq!(
+        value
+            .insert(&r#"scores{"Anna"}"#.parse().unwrap(), 7)
+            .unwrap(),
+        None
+    );
+    assert_eq!(value.remove(&score).unwrap(), Intermediate::I32(42));
+    assert_eq!(value.get(&score), None);
+    assert!(value.remove(&score).is_err());
+
+    value
+        .insert(
+            &"players[0]".parse().unwrap(),
+            Intermediate::struct_type().field("name", "Anna"),
+        )
+        .unwrap();
+    assert_eq!(
+        value.get(&"players[1].name".parse().unwrap()),
+        Some(&Intermediate::String("John".to_owned()))
+    );
+    assert!(value
+        .insert(&"players[3]".parse().unwrap(), Intermediate::Unit)
+        .is_err());
+    value
+        .insert(
+            &"players[0].stats".parse().unwrap(),
+            Intermediate::Option(None),
+        )
+        .unwrap();
+    value
+        .insert(
+            &"players[0].stats?".parse().unwrap(),
+            Intermediate::struct_type().field("hp", 10),
+        )
+        .unwrap();
+    assert_eq!(
+        value.get(&"players[0].stats.hp".parse().unwrap()),
+        Some(&Intermediate::I32(10))
+    );
+    value.remove(&"players[1].stats?".parse().unwrap()).unwrap();
+    assert_eq!(
+        value.get(&"players[1].stats".parse().unwrap()),
+        Some(&Intermediate::Option(None))
+    );
+    assert_eq!(
+        value.remove(&"players[0]".parse().unwrap()).unwrap(),
+        Intermediate::struct_type().field("name", "Anna").field(
+            "stats",
+            Intermediate::Option(Some(Box::new(Intermediate::struct_type().field("hp", 10))))
+        )
+    );
+    assert!(value.remove(&IntermediatePath::default()).is_err());
+
+    let change = Change::partial_struct()
+        .partial_struct_item(
+            "players",
+            Change::partial_seq().partial_seq_item(
+                0,
+                Change::partial_struct().partial_struct_item(
+                    "stats",
+                    Change::partial_change(
+                        Change::partial_struct().partial_struct_item("hp", Change::changed(1)),
+                    ),
+                ),
+            ),
+        )
+        .partial_struct_item("scores", Change::Removed);
+    assert_eq!(
+        change.flatten(),
+        vec![
+            (hp, &Change::changed(1)),
+            ("scores".parse().unwrap(), &Change::Removed),
+        ]
+    );
+}
+
+#[test]
+fn test_merge3() {
+    use crate::{IntermediatePath, MergeConflict, MergeResolution};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Scene {
+        name: String,
+        entities: Vec<String>,
+        tags: HashMap<String, usize>,
+        position: (f32, f32),
+    }
+
+    let base = Scene {
+        name: "level".to_owned(),
+        entities: vec!["player".to_owned(), "enemy".to_owned(), "boss".to_owned()],
+        tags: map! {"a".to_owned() => 1, "b".to_owned() => 2},
+        position: (0.0, 0.0),
+    };
+    let ours = Scene {
+        name: "level 1".to_owned(),
+        entities: vec![
+            "player".to_owned(),
+            "item".to_owned(),
+            "enemy".to_owned(),
+            "boss".to_owned(),
+        ],
+        tags: map! {"a".to_owned() => 1},
+        position: (1.0, 0.0),
+    };
+    let theirs = Scene {
+        name: "level".to_owned(),
+        entities: vec!["player".to_owned(), "enemy".to_owned(), "chest".to_owned()],
+        tags: map! {"a".to_owned() => 1, "b".to_owned() => 2, "c".to_owned() => 3},
+        position: (0.0, 1.0),
+    };
+    let expected = Scene {
+        name: "level 1".to_owned(),
+        entities: vec![
+            "player".to_owned(),
+            "item".to_owned(),
+            "enemy".to_owned(),
+            "chest".to_owned(),
+        ],
+        tags: map! {"a".to_owned() => 1, "c".to_owned() => 3},
+        position: (1.0, 1.0),
+    };
+    let base_value = crate::to_intermediate(&base).unwrap();
+    let result = Change::merge3(
+        &base_value,
+        &crate::to_intermediate(&ours).unwrap(),
+        &crate::to_intermediate(&theirs).unwrap(),
+        &Default::default(),
+    );
+    assert!(result.is_clean());
+    assert_eq!(
+        crate::from_intermediate::<Scene>(&result.value).unwrap(),
+        expected
+    );
+    assert_eq!(
+        result.change.patch(&base_value).unwrap().unwrap(),
+        result.value
+    );
+
+    let base = Intermediate::struct_type()
+        .field("a", 1)
+        .field("b", Intermediate::seq().item(1).item(2).item(3));
+    let ours = Intermediate::struct_type()
+        .field("a", 2)
+        .field("b", Intermediate::seq().item(1).item(4).item(5).item(3));
+    let theirs = Intermediate::struct_type()
+        .field("a", 3)
+        .field("b", Intermediate::seq().item(1).item(6).item(3));
+    let result = Change::merge3(&base, &ours, &theirs, &Default::default());
+    assert_eq!(
+        result.conflicts,
+        vec![
+            MergeConflict {
+                path: IntermediatePath::default().field("a"),
+                base: Some(1.into()),
+                ours: Some(2.into()),
+                theirs: Some(3.into()),
+            },
+            MergeConflict {
+                path: IntermediatePath::default().field("b").index(1),
+                base: Some(Intermediate::seq().item(2)),
+                ours: Some(Intermediate::seq().item(4).item(5)),
+                theirs: Some(Intermediate::seq().item(6)),
+            },
+        ]
+    );
+    assert_eq!(result.value, base);
+    assert!(result.change.is_same());
+
+    let result = Change::merge3_with(&base, &ours, &theirs, &Default::default(), |_| {
+        MergeResolution::Ours
+    });
+    assert!(result.is_clean());
+    assert_eq!(result.value, ours);
+    let result = Change::merge3_with(&base, &ours, &theirs, &Default::default(), |_| {
+        MergeResolution::Theirs
+    });
+    assert_eq!(result.value, theirs);
+    let result = Change::merge3_with(
+        &base,
+        &ours,
+        &theirs,
+        &Default::default(),
+        |conflict| match (&conflict.ours, &conflict.theirs) {
+            (Some(Intermediate::I32(a)), Some(Intermediate::I32(b))) => {
+                MergeResolution::Value(Some((a + b).into()))
+            }
+            _ => MergeResolution::Unresolved,
+        },
+    );
+    assert_eq!(result.conflicts.len(), 1);
+    assert_eq!(
+        result.value,
+        Intermediate::struct_type()
+            .field("a", 5)
+            .field("b", Intermediate::seq().item(1).item(2).item(3))
+    );
+
+    let base = Intermediate::map().property("x", 1);
+    let ours = Intermediate::map();
+    let theirs = Intermediate::map().property("x", 2);
+    let result = Change::merge3(&base, &ours, &theirs, &Default::default());
+    assert_eq!(
+        result.conflicts,
+        vec![MergeConflict {
+            path: IntermediatePath::default().key("x"),
+            base: Some(1.into()),
+            ours: None,
+            theirs: Some(2.into()),
+        }]
+    );
+
+    let entity = |id: u32, hp: u32, name: &str| {
+        Intermediate::struct_type()
+            .field("id", id)
+            .field("hp", hp)
+            .field("name", name)
+    };
+    let base = Intermediate::seq()
+        .item(entity(1, 10, "a"))
+        .item(entity(2, 20, "b"));
+    let ours = Intermediate::seq()
+        .item(entity(1, 15, "a"))
+        .item(entity(2, 20, "b"));
+    let theirs = Intermediate::seq()
+        .item(entity(1, 10, "c"))
+        .item(entity(3, 30, "d"))
+        .item(entity(2, 20, "b"));
+    let result = Change::merge3(&base, &ours, &theirs, &Default::default());
+    assert!(!result.is_clean());
+    let options = DiffOptions::default().sequence_key(crate::SequenceKey::field("id"));
+    let result = Change::merge3(&base, &ours, &theirs, &options);
+    assert!(result.is_clean());
+    assert_eq!(
+        result.value,
+        Intermediate::seq()
+            .item(entity(1, 15, "c"))
+            .item(entity(3, 30, "d"))
+            .item(entity(2, 20, "b"))
+    );
+
+    let base = Intermediate::struct_type().field("x", 1.0f32).field("y", 1);
+    let ours = Intermediate::struct_type()
+        .field("x", 1.0001f32)
+        .field("y", 1);
+    let theirs = Intermediate::struct_type().field("x", 2.0f32).field("y", 1);
+    let result = Change::merge3(&base, &ours, &theirs, &Default::default());
+    assert!(!result.is_clean());
+    let options = DiffOptions::default().float_tolerance(crate::FloatTolerance::absolute(0.01));
+    let result = Change::merge3(&base, &ours, &theirs, &options);
+    assert!(result.is_clean());
+    assert_eq!(result.value, theirs);
+
+    let ours = Intermediate::struct_type().field("x", 3.0f32).field("y", 2);
+    let theirs = Intermediate::struct_type().field("x", 2.0f32).field("y", 1);
+    let options = DiffOptions::default().include("y".parse().unwrap());
+    let result = Change::merge3(&base, &ours, &theirs, &options);
+    assert!(result.is_clean());
+    assert_eq!(
+        result.value,
+        Intermediate::struct_type().field("x", 1.0f32).field("y", 2)
+    );
+    assert_eq!(result.change.patch(&base).unwrap().unwrap(), result.value);
+}
+
+#[test]
+fn test_invert() {
+    use crate::ReversibleChange;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate)]
+    struct Level {
+        name: String,
+        entities: Vec<String>,
+        tags: HashMap<String, usize>,
+        spawn: Option<(i32, i32)>,
+    }
+
+    let prev = Level {
+        name: "level".to_owned(),
+        entities: vec!["a".to_owned(), "b".to_owned(), "c".to_owned()],
+        tags: map! {"x".to_owned() => 1, "y".to_owned() => 2},
+        spawn: Some((0, 0)),
+    };
+    let next = Level {
+        name: "level 1".to_owned(),
+        entities: vec![
+            "b".to_owned(),
+            "d".to_owned(),
+            "c".to_owned(),
+            "e".to_owned(),
+        ],
+        tags: map! {"x".to_owned() => 10, "z".to_owned() => 3},
+        spawn: Some((5, 0)),
+    };
+    let prev_value = crate::to_intermediate(&prev).unwrap();
+    let next_value = crate::to_intermediate(&next).unwrap();
+    let change = Change::difference(&prev_value, &next_value, &Default::default());
+    let undo = change.invert(&prev_value).unwrap();
+    let patched = change.patch(&prev_value).unwrap().unwrap();
+    assert_eq!(
+        crate::from_intermediate::<Level>(&patched).unwrap(),
+        next.to_owned()
+    );
+    let restored = undo.patch(&patched).unwrap().unwrap();
+    assert_eq!(
+        crate::from_intermediate::<Level>(&restored).unwrap(),
+        prev.to_owned()
+    );
+
+    let change = ReversibleChange::data_difference(&prev, &next, &Default::default()).unwrap();
+    let mut data = prev.to_owned();
+    data.patch_change(&change.forward);
+    assert_eq!(data, next);
+    data.patch_change(&change.backward);
+    assert_eq!(data, prev);
+    let change = change.invert();
+    data.patch_change(&change.backward);
+    assert_eq!(data, next);
+
+    let source = Intermediate::seq().item(1).item(2).item(3);
+    let change = Change::partial_seq()
+        .partial_seq_item(1, Change::Removed)
+        .partial_seq_item(1, Change::Changed(4.into()))
+        .partial_seq_item(0, Change::Added(0.into()));
+    let change = ReversibleChange::new(change, &source).unwrap();
+    assert_eq!(
+        change.backward,
+        Change::partial_seq()
+            .partial_seq_item(0, Change::Removed)
+            .partial_seq_item(1, Change::Changed(3.into()))
+            .partial_seq_item(1, Change::Added(2.into()))
+    );
+    let patched = change.forward.patch(&source).unwrap().unwrap();
+    assert_eq!(patched, Intermediate::seq().item(0).item(1).item(4));
+    assert_eq!(change.backward.patch(&patched).unwrap().unwrap(), source);
+
+    let source = Intermediate::struct_type().field("a", 1);
+    let change = Change::partial_struct()
+        .partial_struct_item("a", Change::Added(2.into()))
+        .partial_struct_item("b", Change::Added(3.into()));
+    let undo = change.invert(&source).unwrap();
+    assert_eq!(
+        undo,
+        Change::partial_struct()
+            .partial_struct_item("b", Change::Removed)
+            .partial_struct_item("a", Change::Changed(1.into()))
+    );
+    assert!(Change::Added(1.into()).invert(&source).is_err());
+    assert!(Change::partial_seq()
+        .partial_seq_item(5, Change::Removed)
+        .invert(&Intermediate::seq())
+        .is_err());
+}
+
+#[test]
+fn test_compose() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let generate = |rng: &mut StdRng| {
+        let len = rng.random_range(0..8);
+        Intermediate::Seq(
+            (0..len)
+                .map(|_| {
+                    Intermediate::struct_type()
+                        .field("id", rng.random_range(0..4))
+                        .field("value", rng.random_range(0..3))
+                })
+                .collect(),
+        )
+    };
+    for _ in 0..200 {
+        let a = generate(&mut rng);
+        let b = generate(&mut rng);
+        let c = generate(&mut rng);
+        let first = Change::difference(&a, &b, &Default::default());
+        let next = Change::difference(&b, &c, &Default::default());
+        let composed = first.compose(&next).unwrap();
+        assert_eq!(composed.patch(&a).unwrap().unwrap(), c);
+    }
+
+    let source = Intermediate::seq().item(1).item(2).item(3);
+    let first = Change::partial_seq()
+        .partial_seq_item(1, Change::Added(10.into()))
+        .partial_seq_item(3, Change::Changed(30.into()));
+    let next = Change::partial_seq()
+        .partial_seq_item(1, Change::Removed)
+        .partial_seq_item(0, Change::Removed);
+    let composed = first.compose(&next).unwrap();
+    assert_eq!(
+        composed,
+        Change::partial_seq()
+            .partial_seq_item(0, Change::Removed)
+            .partial_seq_item(1, Change::Changed(30.into()))
+    );
+    assert_eq!(
+        composed.patch(&source).unwrap().unwrap(),
+        next.patch(&first.patch(&source).unwrap().unwrap())
+            .unwrap()
+            .unwrap()
+    );
+
+    let first = Change::partial_map()
+        .partial_map_item("a", Change::Added(1.into()))
+        .partial_map_item("b", Change::Removed)
+        .partial_map_item(
+            "c",
+            Change::partial_struct().partial_struct_item("x", Change::Changed(1.into())),
+        );
+    let next = Change::partial_map()
+        .partial_map_item("a", Change::Removed)
+        .partial_map_item("b", Change::Added(2.into()))
+        .partial_map_item(
+            "c",
+            Change::partial_struct().partial_struct_item("y", Change::Changed(2.into())),
+        );
+    assert_eq!(
+        first.compose(&next).unwrap(),
+        Change::partial_map()
+            .partial_map_item("b", Change::Changed(2.into()))
+            .partial_map_item(
+                "c",
+                Change::partial_struct()
+                    .partial_struct_item("x", Change::Changed(1.into()))
+                    .partial_struct_item("y", Change::Changed(2.into()))
+            )
+    );
+
+    let source = Intermediate::map()
+        .property("b", 1)
+        .property("c", Intermediate::struct_type().field("x", 0).field("y", 0));
+    assert_eq!(
+        first
+            .compose(&next)
+            .unwrap()
+            .patch_strict(&source)
+            .unwrap()
+            .unwrap(),
+        Intermediate::map()
+            .property("b", 2)
+            .property("c", Intermediate::struct_type().field("x", 1).field("y", 2))
+    );
+
+    assert_eq!(
+        Change::Removed.compose(&Change::Added(1.into())).unwrap(),
+        Change::Changed(1.into())
+    );
+    assert_eq!(
+        Change::Changed(1.into()).compose(&Change::Removed).unwrap(),
+        Change::Removed
+    );
+
+    let first = Change::partial_change(Change::Changed(Intermediate::struct_type().field("a", 1)));
+    let next = Change::partial_change(
+        Change::partial_struct().partial_struct_item("a", Change::Changed(2.into())),
+    );
+    assert_eq!(
+        first.compose(&next).unwrap(),
+        Change::partial_change(Change::Changed(Intermediate::struct_type().field("a", 2)))
+    );
+    assert_eq!(
+        Change::compose_all(&[first.to_owned(), Change::Same, next]).unwrap(),
+        Change::partial_change(Change::Changed(Intermediate::struct_type().field("a", 2)))
+    );
+    assert!(Change::partial_seq()
+        .compose(&Change::partial_map())
+        .is_err());
+
+    let source = Intermediate::map().property("x", 1);
+    let first = Change::partial_map().partial_map_item("y", Change::Added(1.into()));
+    let next = Change::partial_map().partial_map_item("y", Change::Changed(3.into()));
+    let composed = first.compose(&next).unwrap();
+    assert_eq!(
+        composed,
+        Change::partial_map().partial_map_item("y", Change::Added(3.into()))
+    );
+    assert_eq!(
+        composed.patch(&source).unwrap().unwrap(),
+        next.patch(&first.patch(&source).unwrap().unwrap())
+            .unwrap()
+            .unwrap()
+    );
+
+    let source = Intermediate::struct_type().field("x", 1);
+    let first = Change::partial_struct().partial_struct_item(
+        "y",
+        Change::Added(Intermediate::struct_type().field("a", 1).field("b", 2)),
+    );
+    let next = Change::partial_struct().partial_struct_item(
+        "y",
+        Change::partial_struct().partial_struct_item("b", Change::Changed(5.into())),
+    );
+    let composed = first.compose(&next).unwrap();
+    assert_eq!(
+        composed,
+        Change::partial_struct().partial_struct_item(
+            "y",
+            Change::Added(Intermediate::struct_type().field("a", 1).field("b", 5))
+        )
+    );
+    assert_eq!(
+        composed.patch(&source).unwrap().unwrap(),
+        next.patch(&first.patch(&source).unwrap().unwrap())
+            .unwrap()
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_seq_diff_large() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..50 {
+        let prev = (0..rng.random_range(0..200))
+            .map(|_| Intermediate::U8(rng.random_range(0..8)))
+            .collect::<Vec<_>>();
+        let next = (0..rng.random_range(0..200))
+            .map(|_| Intermediate::U8(rng.random_range(0..8)))
+            .collect::<Vec<_>>();
+        let change = Change::difference(
+            &Intermediate::Seq(prev.to_owned()),
+            &Intermediate::Seq(next.to_owned()),
+            &Default::default(),
+        );
+        assert_eq!(
+            change.patch(&Intermediate::Seq(prev)).unwrap().unwrap(),
+            Intermediate::Seq(next)
+        );
+    }
+
+    let prev = (0..10000u32).map(Intermediate::U32).collect::<Vec<_>>();
+    let mut next = prev.to_owned();
+    next.remove(5000);
+    next.insert(100, Intermediate::U32(42));
+    next[9000] = Intermediate::U32(0);
+    let provided = Change::sequence_difference(&prev, &next, &Default::default());
+    let expected = vec![
+        (100, Change::Added(Intermediate::U32(42))),
+        (5001, Change::Removed),
+        (9000, Change::Changed(Intermediate::U32(0))),
+    ];
+    assert_eq!(provided, expected);
+}
+
+#[test]
+fn test_keyed_seq_diff() {
+    use crate::versioning::SequenceKey;
+    use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+    use std::collections::VecDeque;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate)]
+    struct Entity {
+        id: usize,
+        name: String,
+        hp: usize,
+    }
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let options = DiffOptions::default().sequence_key(SequenceKey::field("id"));
+    for _ in 0..100 {
+        let mut prev = (0..rng.random_range(0..30))
+            .map(|id| Entity {
+                id,
+                name: format!("entity-{}", id),
+                hp: 100,
+            })
+            .collect::<Vec<_>>();
+        prev.shuffle(&mut rng);
+        let mut next = prev
+            .iter()
+            .filter(|_| rng.random_bool(0.8))
+            .cloned()
+            .collect::<Vec<_>>();
+        next.shuffle(&mut rng);
+        for item in &mut next {
+            if rng.random_bool(0.2) {
+                item.hp = rng.random_range(0..100);
+            }
+        }
+        for id in 30..rng.random_range(30..35) {
+            let index = rng.random_range(0..=next.len());
+            next.insert(
+                index,
+                Entity {
+                    id,
+                    name: format!("entity-{}", id),
+                    hp: 100,
+                },
+            );
+        }
+
+        let change = Change::data_difference(&prev, &next, &options).unwrap();
+        if let Change::PartialSeq(v) = &change {
+            assert!(v
+                .iter()
+                .all(|(_, change)| !matches!(change, Change::Changed(_))));
+        }
+        assert_eq!(change.data_patch(&prev).unwrap().unwrap(), next);
+        let mut patched = prev.to_owned();
+        patched.patch_change(&change);
+        assert_eq!(patched, next);
+        let mut patched = prev.iter().cloned().collect::<VecDeque<_>>();
+        patched.patch_change(&change);
+        assert_eq!(patched, next);
+        let source = crate::to_intermediate(&prev).unwrap();
+        let undo = change.invert(&source).unwrap();
+        assert_eq!(undo.data_patch(&next).unwrap().unwrap(), prev,);
+    }
+
+    let prev = Intermediate::seq().item(1).item(2).item(3);
+    let next = Intermediate::seq().item(3).item(1).item(2);
+    let options =
+        DiffOptions::default().sequence_key(SequenceKey::extractor(|value| Some(value.to_owned())));
+    let change = Change::difference(&prev, &next, &options);
+    assert_eq!(
+        change,
+        Change::partial_seq().partial_seq_item(2, Change::moved(0))
+    );
+    assert_eq!(change.patch(&prev).unwrap().unwrap(), next);
+    let options = DiffOptions::default().sequence_key(SequenceKey::field("id"));
+    assert_eq!(
+        Change::difference(&prev, &next, &options),
+        Change::difference(&prev, &next, &Default::default())
+    );
+}
+
+#[test]
+fn test_map_diff_large() {
+    let hash = |value: &Intermediate| {
+        use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher};
+        BuildHasherDefault::<DefaultHasher>::default().hash_one(value)
+    };
+    assert_eq!(
+        hash(&Intermediate::F32(0.0)),
+        hash(&Intermediate::F32(-0.0))
+    );
+    assert_eq!(
+        hash(&Intermediate::F64(0.0)),
+        hash(&Intermediate::F64(-0.0))
+    );
+    assert_ne!(hash(&Intermediate::F32(1.0)), hash(&Intermediate::F64(1.0)));
+    let set = [
+        Intermediate::F64(0.0),
+        Intermediate::F64(-0.0),
+        Intermediate::seq().item(1.5),
+        Intermediate::seq().item(1.5),
+    ]
+    .into_iter()
+    .collect::<HashSet<_>>();
+    assert_eq!(set.len(), 2);
+
+    let prev = (0..50000)
+        .map(|index| (format!("component-{}", index), index))
+        .collect::<HashMap<_, _>>();
+    let mut next = prev.to_owned();
+    next.remove("component-42");
+    next.insert("component-42000".to_owned(), 0);
+    next.insert("component-50000".to_owned(), 50000);
+    let change = Change::data_difference(&prev, &next, &Default::default()).unwrap();
+    assert_eq!(
+        change,
+        Change::partial_map()
+            .partial_map_item("component-50000", Change::added(50000))
+            .partial_map_item("component-42", Change::removed())
+            .partial_map_item("component-42000", Change::changed(0))
+    );
+    assert_eq!(change.data_patch(&prev).unwrap().unwrap(), next);
+}
+
+#[test]
+fn test_json_patch() {
+    use crate::versioning::{json_patch::JsonPatchOperation, SequenceKey};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Circle(f32),
+        Rect { w: f32, h: f32 },
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Entity {
+        id: usize,
+        shape: Shape,
+        tag: Option<String>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Scene {
+        entities: Vec<Entity>,
+        props: HashMap<String, usize>,
+    }
+
+    let prev = Scene {
+        entities: vec![
+            Entity {
+                id: 0,
+                shape: Shape::Circle(1.0),
+                tag: Some("a/b~c".to_owned()),
+            },
+            Entity {
+                id: 1,
+                shape: Shape::Rect { w: 1.0, h: 2.0 },
+                tag: None,
+            },
+            Entity {
+                id: 2,
+                shape: Shape::Circle(3.0),
+                tag: None,
+            },
+        ],
+        props: map! { "a/b~c".to_owned() => 1, "b".to_owned() => 2 },
+    };
+    let next = Scene {
+        entities: vec![
+            Entity {
+                id: 2,
+                shape: Shape::Circle(4.0),
+                tag: Some("new".to_owned()),
+            },
+            Entity {
+                id: 0,
+                shape: Shape::Circle(1.0),
+                tag: Some("a/b~d".to_owned()),
+            },
+            Entity {
+                id: 1,
+                shape: Shape::Rect { w: 1.0, h: 5.0 },
+                tag: None,
+            },
+        ],
+        props: map! { "a/b~c".to_owned() => 10, "c".to_owned() => 3 },
+    };
+    let base = crate::to_intermediate(&prev).unwrap();
+    let options = DiffOptions::default().sequence_key(SequenceKey::field("id"));
+    let change = Change::data_difference(&prev, &next, &options).unwrap();
+    let operations = change.to_json_patch(&base).unwrap();
+    assert!(operations.contains(&JsonPatchOperation::Move {
+        from: "/entities/2".to_owned(),
+        path: "/entities/0".to_owned(),
+    }));
+    assert!(operations.contains(&JsonPatchOperation::Replace {
+        path: "/entities/0/shape/Circle".to_owned(),
+        value: Intermediate::F32(4.0),
+    }));
+    assert!(operations.contains(&JsonPatchOperation::Replace {
+        path: "/entities/2/shape/Rect/h".to_owned(),
+        value: Intermediate::F32(5.0),
+    }));
+    assert!(operations.contains(&JsonPatchOperation::Replace {
+        path: "/props/a~1b~0c".to_owned(),
+        value: Intermediate::U64(10),
+    }));
+
+    let json = serde_json::to_string(&operations).unwrap();
+    let operations = serde_json::from_str::<Vec<JsonPatchOperation>>(&json).unwrap();
+    let change = Change::from_json_patch(&base, &operations).unwrap();
+    assert_eq!(change.data_patch(&prev).unwrap().unwrap(), next);
+
+    let json = r#"[
+        { "op": "move", "from": "/entities/0", "path": "/entities/-" },
+        { "op": "remove", "path": "/props/b" },
+        { "op": "add", "path": "/props/d", "value": 4 },
+        { "op": "replace", "path": "/entities/0/tag", "value": "first" },
+        { "op": "move", "from": "/props/d", "path": "/props/e" }
+    ]"#;
+    let operations = serde_json::from_str::<Vec<JsonPatchOperation>>(json).unwrap();
+    let change = Change::from_json_patch(&base, &operations).unwrap();
+    let patched = change.data_patch(&prev).unwrap().unwrap();
+    assert_eq!(
+        patched.entities.iter().map(|e| e.id).collect::<Vec<_>>(),
+        vec![1, 2, 0]
+    );
+    assert_eq!(patched.entities[0].tag.as_deref(), Some("first"));
+    assert_eq!(
+        patched.props,
+        map! { "a/b~c".to_owned() => 1, "e".to_owned() => 4 }
+    );
+
+    for json in [
+        r#"{ "op": "test", "path": "/props/b", "value": 2 }"#,
+        r#"{ "op": "copy", "from": "/props/b", "path": "/props/c" }"#,
+    ] {
+        let operation = serde_json::from_str::<JsonPatchOperation>(json).unwrap();
+        assert!(matches!(
+            Change::from_json_patch(&base, &[operation]),
+            Err(crate::Error::UnsupportedPatchOperation(_))
+        ));
+    }
+    let operation = JsonPatchOperation::Remove {
+        path: "/props/missing".to_owned(),
+    };
+    assert!(matches!(
+        Change::from_json_patch(&base, &[operation]),
+        Err(crate::Error::PathNotFound(_))
+    ));
+
+    let prev = Intermediate::struct_type()
+        .field("a", Intermediate::Option(Some(Box::new(1.into()))))
+        .field("b", Intermediate::Option(Some(Box::new(3.into()))))
+        .field("c", Intermediate::Option(None));
+    let next = Intermediate::struct_type()
+        .field("a", Intermediate::Option(Some(Box::new(2.into()))))
+        .field("b", Intermediate::Option(None))
+        .field("c", Intermediate::Option(Some(Box::new(5.into()))));
+    let change = Change::difference(&prev, &next, &Default::default());
+    let operations = change.to_json_patch(&prev).unwrap();
+    assert!(operations.contains(&JsonPatchOperation::Replace {
+        path: "/a".to_owned(),
+        value: 2.into(),
+    }));
+    let change = Change::from_json_patch(&prev, &operations).unwrap();
+    assert_eq!(change.patch(&prev).unwrap().unwrap(), next);
+    let json = r#"[
+        { "op": "replace", "path": "/a", "value": null },
+        { "op": "add", "path": "/c", "value": 5 }
+    ]"#;
+    let operations = serde_json::from_str::<Vec<JsonPatchOperation>>(json).unwrap();
+    let patched = Change::from_json_patch(&prev, &operations)
+        .unwrap()
+        .patch(&prev)
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        patched.get(&"a".parse().unwrap()),
+        Some(&Intermediate::Option(None))
+    );
+    assert!(matches!(
+        patched.get(&"c".parse().unwrap()),
+        Some(Intermediate::Option(Some(_)))
+    ));
+
+    let prev: HashMap<u32, String> = map! { 1 => "a".to_owned(), 2 => "b".to_owned() };
+    let next: HashMap<u32, String> = map! { 1 => "c".to_owned(), 3 => "d".to_owned() };
+    let base = crate::to_intermediate(&prev).unwrap();
+    let change = Change::data_difference(&prev, &next, &Default::default()).unwrap();
+    let operations = change.to_json_patch(&base).unwrap();
+    assert!(operations.contains(&JsonPatchOperation::Replace {
+        path: "/1".to_owned(),
+        value: "c".into(),
+    }));
+    assert!(operations.contains(&JsonPatchOperation::Remove {
+        path: "/2".to_owned(),
+    }));
+    let json = serde_json::to_string(&operations).unwrap();
+    let operations = serde_json::from_str::<Vec<JsonPatchOperation>>(&json).unwrap();
+    let change = Change::from_json_patch(&base, &operations).unwrap();
+    assert_eq!(change.data_patch(&prev).unwrap().unwrap(), next);
+}
+
+#[test]
+fn test_merge_patch() {
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Window {
+        title: Option<String>,
+        size: (usize, usize),
+        fullscreen: bool,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        window: Window,
+        plugins: HashMap<String, bool>,
+        tags: Vec<String>,
+    }
+
+    let json = |content: &str| {
+        serde_json::from_str::<serde_json::Value>(content)
+            .map(|value| crate::to_intermediate(&value).unwrap())
+            .unwrap()
+    };
+
+    let value =
+        |value: &Intermediate| crate::from_intermediate::<serde_json::Value>(value).unwrap();
+
+    // Example from RFC 7386 appendix.
+    let target = json(
+        r#"{"title":"Goodbye!","author":{"givenName":"John","familyName":"Doe"},
+        "tags":["example","sample"],"content":"This will be unchanged"}"#,
+    );
+    let patch = json(
+        r#"{"title":"Hello!","phoneNumber":"+01-234-567-8910",
+        "author":{"familyName":null},"tags":["example"]}"#,
+    );
+    let expected = json(
+        r#"{"title":"Hello!","author":{"givenName":"John"},"tags":["example"],
+        "content":"This will be unchanged","phoneNumber":"+01-234-567-8910"}"#,
+    );
+    let change = Change::from_merge_patch(&target, &patch).unwrap();
+    assert_eq!(
+        value(&change.patch(&target).unwrap().unwrap()),
+        value(&expected)
+    );
+    assert_eq!(
+        Change::from_merge_patch(&json(r#"{"a":"b"}"#), &json(r#"{"a":{"bb":{"ccc":null}}}"#))
+            .unwrap()
+            .patch(&json(r#"{"a":"b"}"#))
+            .unwrap()
+            .unwrap(),
+        json(r#"{"a":{"bb":{}}}"#)
+    );
+    assert_eq!(
+        Change::from_merge_patch(&json(r#"{"a":"foo"}"#), &json("null"))
+            .unwrap()
+            .patch(&json(r#"{"a":"foo"}"#))
+            .unwrap()
+            .unwrap(),
+        json("null")
+    );
+
+    let prev = Config {
+        window: Window {
+            title: Some("Game".to_owned()),
+            size: (800, 600),
+            fullscreen: false,
+        },
+        plugins: map! { "audio".to_owned() => true, "physics".to_owned() => true },
+        tags: vec!["a".to_owned()],
+    };
+    let base = crate::to_intermediate(&prev).unwrap();
+    let patch = json(
+        r#"{"window":{"title":null,"fullscreen":true},
+        "plugins":{"physics":null,"network":false},"tags":["b","c"]}"#,
+    );
+    let change = Change::from_merge_patch(&base, &patch).unwrap();
+    let next = change.data_patch(&prev).unwrap().unwrap();
+    assert_eq!(
+        next,
+        Config {
+            window: Window {
+                title: None,
+                size: (800, 600),
+                fullscreen: true,
+            },
+            plugins: map! { "audio".to_owned() => true, "network".to_owned() => false },
+            tags: vec!["b".to_owned(), "c".to_owned()],
+        }
+    );
+
+    let change = Change::data_difference(&prev, &next, &Default::default()).unwrap();
+    let patch = change.to_merge_patch(&base).unwrap();
+    let patch = serde_json::to_string(&patch).unwrap();
+    let change = Change::from_merge_patch(&base, &json(&patch)).unwrap();
+    assert_eq!(change.data_patch(&prev).unwrap().unwrap(), next);
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Address {
+        city: String,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Profile {
+        nick: Option<String>,
+        home: Option<Address>,
+        scores: HashMap<u32, Option<u32>>,
+    }
+
+    let prev = Profile {
+        nick: Some("a".to_owned()),
+        home: None,
+        scores: map! { 1 => Some(10), 2 => None },
+    };
+    let base = crate::to_intermediate(&prev).unwrap();
+    let patch = json(r#"{"nick":"b","home":{"city":"X"},"scores":{"2":null,"3":30}}"#);
+    let change = Change::from_merge_patch(&base, &patch).unwrap();
+    let patched = change.patch(&base).unwrap().unwrap();
+    assert_eq!(
+        patched.get(&"nick".parse().unwrap()),
+        Some(&Intermediate::Option(Some(Box::new("b".into()))))
+    );
+    assert_eq!(
+        change.data_patch(&prev).unwrap().unwrap(),
+        Profile {
+            nick: Some("b".to_owned()),
+            home: Some(Address {
+                city: "X".to_owned()
+            }),
+            scores: map! { 1 => Some(10), 3 => Some(30) },
+        }
+    );
+    let patch = json(r#"{"nick":null,"scores":{"1":null}}"#);
+    let change = Change::from_merge_patch(&base, &patch).unwrap();
+    assert_eq!(
+        change.data_patch(&prev).unwrap().unwrap(),
+        Profile {
+            nick: None,
+            home: None,
+            scores: map! { 2 => None },
+        }
+    );
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Rect { width: u32, height: u32 },
+        Circle(u32),
+        Point((u32, u32)),
+    }
+
+    let cases = [
+        (
+            Shape::Rect {
+                width: 1,
+                height: 2,
+            },
+            Shape::Rect {
+                width: 1,
+                height: 5,
+            },
+        ),
+        (Shape::Circle(1), Shape::Circle(4)),
+        (
+            Shape::Rect {
+                width: 1,
+                height: 2,
+            },
+            Shape::Circle(3),
+        ),
+        (
+            Shape::Circle(3),
+            Shape::Rect {
+                width: 1,
+                height: 2,
+            },
+        ),
+        (Shape::Point((1, 2)), Shape::Circle(3)),
+    ];
+    for (prev, next) in cases {
+        let base = crate::to_intermediate(&prev).unwrap();
+        let patched = crate::to_intermediate(&next).unwrap();
+        let patch = Change::difference(&base, &patched, &Default::default())
+            .to_merge_patch(&base)
+            .unwrap();
+        let change = Change::from_merge_patch(&base, &patch).unwrap();
+        assert_eq!(change.data_patch(&prev).unwrap().unwrap(), next);
+    }
+    let prev = Shape::Rect {
+        width: 1,
+        height: 2,
+    };
+    let base = crate::to_intermediate(&prev).unwrap();
+    let change = Change::from_merge_patch(&base, &json(r#"{"Rect":{"height":5}}"#)).unwrap();
+    assert_eq!(
+        change.data_patch(&prev).unwrap().unwrap(),
+        Shape::Rect {
+            width: 1,
+            height: 5,
+        }
+    );
+}
+
+#[test]
+fn test_enum_diff() {
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate)]
+    enum Shape {
+        Point,
+        Empty,
+        Circle(f32),
+        Line(f32, f32),
+        Segment(f32, f32),
+        Rect { w: f32, h: f32 },
+        Ellipse { w: f32, h: f32 },
+    }
+
+    fn check(prev: Shape, next: Shape, expected: Change) {
+        let change = Change::data_difference(&prev, &next, &Default::default()).unwrap();
+        assert_eq!(change, expected);
+        assert_eq!(change.data_patch(&prev).unwrap().unwrap(), next);
+        let mut patched = prev.to_owned();
+        patched.patch_change(&change);
+        assert_eq!(patched, next);
+    }
+
+    check(Shape::Point, Shape::Point, Change::Same);
+    check(
+        Shape::Point,
+        Shape::Empty,
+        Change::changed(Intermediate::UnitVariant("Empty".to_owned())),
+    );
+    check(
+        Shape::Circle(1.0),
+        Shape::Circle(2.0),
+        Change::partial_change(Change::changed(2.0f32)),
+    );
+    check(
+        Shape::Circle(1.0),
+        Shape::Point,
+        Change::changed(Intermediate::UnitVariant("Point".to_owned())),
+    );
+    check(
+        Shape::Line(1.0, 2.0),
+        Shape::Line(1.0, 3.0),
+        Change::partial_seq().partial_seq_item(1, Change::changed(3.0f32)),
+    );
+    check(
+        Shape::Line(1.0, 2.0),
+        Shape::Line(2.0, 1.0),
+        Change::partial_seq()
+            .partial_seq_item(0, Change::changed(2.0f32))
+            .partial_seq_item(1, Change::changed(1.0f32)),
+    );
+    check(
+        Shape::Line(1.0, 2.0),
+        Shape::Segment(1.0, 2.0),
+        Change::changed(
+            Intermediate::TupleVariant("Segment".to_owned(), vec![])
+                .item(1.0f32)
+                .item(2.0f32),
+        ),
+    );
+    check(
+        Shape::Rect { w: 1.0, h: 2.0 },
+        Shape::Rect { w: 1.0, h: 3.0 },
+        Change::partial_struct().partial_struct_item("h", Change::changed(3.0f32)),
+    );
+    check(
+        Shape::Rect { w: 1.0, h: 2.0 },
+        Shape::Ellipse { w: 1.0, h: 2.0 },
+        Change::changed(
+            Intermediate::struct_variant("Ellipse")
+                .field("w", 1.0f32)
+                .field("h", 2.0f32),
+        ),
+    );
+    check(
+        Shape::Ellipse { w: 1.0, h: 2.0 },
+        Shape::Line(1.0, 2.0),
+        Change::changed(
+            Intermediate::TupleVariant("Line".to_owned(), vec![])
+                .item(1.0f32)
+                .item(2.0f32),
+        ),
+    );
+}
+
+#[test]
+fn test_diff_tolerance() {
+    use crate::{FloatTolerance, IntermediatePath};
+
+    let prev = Intermediate::struct_type()
+        .field("x", 1.0f32)
+        .field("y", 100.0f64)
+        .field("name", "foo")
+        .field("timestamp", 10u64)
+        .field(
+            "points",
+            Intermediate::seq().item(1.0f64).item(2.0f64).item(3.0f64),
+        );
+    let next = Intermediate::struct_type()
+        .field("x", 1.000_001f32)
+        .field("y", 100.5f64)
+        .field("name", "foo")
+        .field("timestamp", 20u64)
+        .field(
+            "points",
+            Intermediate::seq()
+                .item(1.000_000_1f64)
+                .item(2.5f64)
+                .item(3.000_000_1f64),
+        );
+
+    let options = DiffOptions::default().float_tolerance(FloatTolerance::absolute(1.0e-3));
+    let change = Change::difference(&prev, &next, &options);
+    assert_eq!(
+        change,
+        Change::partial_struct()
+            .partial_struct_item("y", Change::changed(100.5f64))
+            .partial_struct_item("timestamp", Change::changed(20u64))
+            .partial_struct_item(
+                "points",
+                Change::partial_seq().partial_seq_item(1, Change::changed(2.5f64))
+            ),
+    );
+
+    let options = DiffOptions::default().float_tolerance(FloatTolerance::relative(0.01));
+    let change = Change::difference(&prev, &next, &options);
+    assert_eq!(
+        change,
+        Change::partial_struct()
+            .partial_struct_item("timestamp", Change::changed(20u64))
+            .partial_struct_item(
+                "points",
+                Change::partial_seq().partial_seq_item(1, Change::changed(2.5f64))
+            ),
+    );
+
+    let ignored = IntermediatePath::default().field("timestamp");
+    let options = DiffOptions::default()
+        .float_tolerance(FloatTolerance::relative(0.01))
+        .equality(move |path, _, _| (path == &ignored).then_some(true));
+    let change = Change::difference(&prev, &next, &options);
+    assert_eq!(
+        change,
+        Change::partial_struct().partial_struct_item(
+            "points",
+            Change::partial_seq().partial_seq_item(1, Change::changed(2.5f64))
+        ),
+    );
+    let patched = change.patch(&prev).unwrap().unwrap();
+    assert!(Change::difference(&patched, &next, &options).is_same());
+
+    let prev = (0..20).map(|index| index as f64).collect::<Vec<_>>();
+    let mut next = prev.iter().map(|value| value + 1.0e-9).collect::<Vec<_>>();
+    next.remove(5);
+    next.insert(10, 100.0);
+    let options = DiffOptions::default().float_tolerance(FloatTolerance::absolute(1.0e-6));
+    let change = Change::sequence_difference(
+        &prev
+            .iter()
+            .copied()
+            .map(Intermediate::F64)
+            .collect::<Vec<_>>(),
+        &next
+            .iter()
+            .copied()
+            .map(Intermediate::F64)
+            .collect::<Vec<_>>(),
+        &options,
+    );
+    assert_eq!(
+        change,
+        vec![(5, Change::Removed), (10, Change::added(100.0f64))]
+    );
+}
+
+#[test]
+fn test_diff_filter() {
+    use crate::{IntermediatePathFilter, IntermediatePathPattern};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Entity {
+        name: String,
+        cache: Vec<u8>,
+        last_modified: u64,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct World {
+        entities: Vec<Entity>,
+        settings: HashMap<String, f32>,
+        last_modified: u64,
+    }
+
+    let pattern = |content: &str| content.parse::<IntermediatePathPattern>().unwrap();
+    assert_eq!(pattern("**.last_modified").to_string(), "**.last_modified");
+    assert_eq!(
+        pattern("entities[*].cache").to_string(),
+        "entities[*].cache"
+    );
+    assert_eq!(pattern(".*{*}?[2]").to_string(), "*{*}?[2]");
+    assert_eq!(pattern("a.\"b c\"{\"d\"}").to_string(), "a.\"b c\"{\"d\"}");
+    assert!(pattern("a.b.**").matches(&"a.b".parse().unwrap()));
+    assert!(pattern("**").matches(&Default::default()));
+    assert!(pattern("a.**.c").matches(&"a.b[0]?.c".parse().unwrap()));
+    assert!(!pattern("a.**.c").matches(&"a.b[0]?.c.d".parse().unwrap()));
+    assert!("a.[*]".parse::<IntermediatePathPattern>().is_err());
+
+    let prev = World {
+        entities: vec![
+            Entity {
+                name: "a".to_owned(),
+                cache: vec![1, 2, 3],
+                last_modified: 1,
+            },
+            Entity {
+                name: "b".to_owned(),
+                cache: vec![],
+                last_modified: 2,
+            },
+        ],
+        settings: map! {"volume".to_owned() => 0.5},
+        last_modified: 3,
+    };
+    let mut next = prev.clone();
+    next.entities[0].cache = vec![4];
+    next.entities[0].last_modified = 10;
+    next.entities[1].name = "c".to_owned();
+    next.entities[1].cache = vec![5];
+    next.entities[1].last_modified = 20;
+    next.settings.insert("gamma".to_owned(), 2.2);
+    next.last_modified = 30;
+
+    let options = DiffOptions::default()
+        .exclude(pattern("**.last_modified"))
+        .exclude(pattern("entities[*].cache"));
+    let change = Change::data_difference(&prev, &next, &options).unwrap();
+    assert_eq!(
+        change,
+        Change::partial_struct()
+            .partial_struct_item(
+                "entities",
+                Change::partial_seq().partial_seq_item(
+                    1,
+                    Change::partial_struct().partial_struct_item("name", Change::changed("c"))
+                )
+            )
+            .partial_struct_item(
+                "settings",
+                Change::partial_map().partial_map_item("gamma", Change::added(2.2f32))
+            ),
+    );
+    assert!(Change::data_difference(&prev, &prev, &options)
+        .unwrap()
+        .is_same());
+
+    let options = DiffOptions::default()
+        .include(pattern("entities"))
+        .exclude(pattern("entities[*].cache"));
+    let change = Change::data_difference(&prev, &next, &options).unwrap();
+    let flattened = change
+        .flatten()
+        .into_iter()
+        .map(|(path, _)| path.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        flattened,
+        vec![
+            "entities[0].last_modified",
+            "entities[1].name",
+            "entities[1].last_modified"
+        ]
+    );
+
+    let change = Change::data_difference(&prev, &next, &Default::default()).unwrap();
+    let filter = IntermediatePathFilter::default()
+        .include(pattern("entities[*].name"))
+        .include(pattern("settings"));
+    let serialized = crate::to_intermediate(&prev).unwrap();
+    let patched = change
+        .patch_filtered(&serialized, &filter)
+        .unwrap()
+        .unwrap();
+    let patched = crate::from_intermediate::<World>(&patched).unwrap();
+    let mut expected = prev.clone();
+    expected.entities[1].name = "c".to_owned();
+    expected.settings = next.settings.clone();
+    assert_eq!(patched, expected);
+    assert!(change
+        .filter(&IntermediatePathFilter::default().include(pattern("foo")))
+        .is_same());
+}
+
+#[test]
+fn test_patch_strict() {
+    use crate::{Error, IntermediatePath};
+
+    fn invalid_path(result: crate::error::Result<Option<Intermediate>>) -> String {
+        match result {
+            Err(Error::InvalidPatch(path, _)) => path.to_string(),
+            result => panic!("Expected invalid patch, got: {:?}", result),
+        }
+    }
+
+    let value = Intermediate::struct_type()
+        .field("name", "foo")
+        .field("tags", Intermediate::seq().item("a").item("b"))
+        .field("meta", Intermediate::map().property("x", 1))
+        .field("parent", Intermediate::Option(None));
+
+    let changes = vec![
+        (
+            Change::partial_struct().partial_struct_item(
+                "tags",
+                Change::partial_seq().partial_seq_item(5, Change::Removed),
+            ),
+            "tags[5]",
+        ),
+        (
+            Change::partial_struct().partial_struct_item(
+                "tags",
+                Change::partial_seq().partial_seq_item(3, Change::added("c")),
+            ),
+            "tags[3]",
+        ),
+        (
+            Change::partial_struct().partial_struct_item(
+                "tags",
+                Change::partial_seq()
+                    .partial_seq_item(0, Change::Removed)
+                    .partial_seq_item(1, Change::changed("c")),
+            ),
+            "tags[1]",
+        ),
+        (
+            Change::partial_struct().partial_struct_item(
+                "tags",
+                Change::partial_seq().partial_seq_item(0, Change::moved(2)),
+            ),
+            "tags[0]",
+        ),
+        (
+            Change::partial_struct().partial_struct_item("age", Change::changed(42)),
+            "age",
+        ),
+        (
+            Change::partial_struct().partial_struct_item("name", Change::added("bar")),
+            "name",
+        ),
+        (
+            Change::partial_struct().partial_struct_item(
+                "meta",
+                Change::partial_map().partial_map_item("y", Change::Removed),
+            ),
+            "meta{\"y\"}",
+        ),
+        (
+            Change::partial_struct().partial_struct_item(
+                "meta",
+                Change::partial_map().partial_map_item("x", Change::partial_seq()),
+            ),
+            "meta{\"x\"}",
+        ),
+        (
+            Change::partial_struct()
+                .partial_struct_item("parent", Change::partial_change(Change::changed(1))),
+            "parent",
+        ),
+    ];
+    for (change, path) in changes {
+        assert_eq!(invalid_path(change.patch_strict(&value)), path);
+        assert!(change.validate(&value).is_err());
+        let _ = change.patch(&value);
+    }
+
+    let change = Change::partial_struct().partial_struct_item(
+        "tags",
+        Change::partial_seq()
+            .partial_seq_item(0, Change::moved(2))
+            .partial_seq_item(1, Change::changed("c")),
+    );
+    assert_eq!(
+        change.patch(&value).unwrap().unwrap(),
+        Intermediate::struct_type()
+            .field("name", "foo")
+            .field("tags", Intermediate::seq().item("a").item("c"))
+            .field("meta", Intermediate::map().property("x", 1))
+            .field("parent", Intermediate::Option(None))
+    );
+
+    let change = Change::partial_struct()
+        .partial_struct_item(
+            "tags",
+            Change::partial_seq()
+                .partial_seq_item(5, Change::Removed)
+                .partial_seq_item(0, Change::changed("c")),
+        )
+        .partial_struct_item("age", Change::changed(42))
+        .partial_struct_item(
+            "meta",
+            Change::partial_map().partial_map_item("x", Change::partial_seq()),
+        );
+    let invalid = change
+        .invalid_operations(&value)
+        .into_iter()
+        .map(|(path, error)| {
+            assert!(matches!(&error, Error::InvalidPatch(p, _) if *p == path));
+            path.to_string()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(invalid, vec!["tags[5]", "age", "meta{\"x\"}"]);
+    assert_eq!(invalid_path(change.patch_strict(&value)), "tags[5]");
+    let mut report = crate::PatchReport::default();
+    let data = crate::to_intermediate(&value).unwrap();
+    let patched = report.nested(
+        crate::IntermediatePathSegment::Field("a".to_owned()),
+        |report| report.patch_serialized(&data, &change),
+    );
+    assert!(patched.is_none());
+    assert!(report.applied.is_empty());
+    assert_eq!(
+        report
+            .rejected
+            .iter()
+            .map(|(path, _)| path.to_string())
+            .collect::<Vec<_>>(),
+        vec!["a.tags[5]", "a.age", "a.meta{\"x\"}"],
+    );
+
+    let change = Change::partial_struct().partial_struct_item(
+        "tags",
+        Change::partial_seq()
+            .partial_seq_item(7, Change::Removed)
+            .partial_seq_item(9, Change::added("x"))
+            .partial_seq_item(4, Change::changed("y"))
+            .partial_seq_item(1, Change::changed("c")),
+    );
+    assert_eq!(
+        change.patch(&value).unwrap().unwrap(),
+        Intermediate::struct_type()
+            .field("name", "foo")
+            .field("tags", Intermediate::seq().item("a").item("c"))
+            .field("meta", Intermediate::map().property("x", 1))
+            .field("parent", Intermediate::Option(None)),
+    );
+    let mut data = vec![1, 2];
+    data.patch_change(
+        &Change::partial_seq()
+            .partial_seq_item(5, Change::Removed)
+            .partial_seq_item(5, Change::added(3)),
+    );
+    assert_eq!(data, vec![1, 2]);
+
+    let next = Intermediate::struct_type()
+        .field("name", "bar")
+        .field("tags", Intermediate::seq().item("b").item("c").item("a"))
+        .field("meta", Intermediate::map().property("y", 2))
+        .field("parent", Intermediate::Option(Some(Box::new(42.into()))));
+    let change = Change::difference(&value, &next, &Default::default());
+    assert!(change.validate(&value).is_ok());
+    assert_eq!(change.patch_strict(&value).unwrap().unwrap(), next);
+    assert_eq!(
+        Error::InvalidPatch(
+            IntermediatePath::default().field("tags").index(5),
+            "cannot remove missing item".to_owned()
+        )
+        .to_string(),
+        "invalid patch at `tags[5]`: cannot remove missing item"
+    );
+}
+
+#[test]
+fn test_try_patch_change() {
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ReflectIntermediate)]
+    enum Shape {
+        Circle(f32),
+        Rect { w: f32, h: f32 },
+        Line(f32, f32),
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ReflectIntermediate)]
+    #[reflect_intermediate(after_patch_change = "on_patched")]
+    struct Entity {
+        name: String,
+        hp: u8,
+        shape: Shape,
+        tags: Vec<String>,
+        stats: HashMap<String, i32>,
+        parent: Option<usize>,
+        #[reflect_intermediate(indirect)]
+        extra: (bool, String),
+        #[serde(skip)]
+        #[reflect_intermediate(ignore)]
+        patched: usize,
+    }
+
+    impl Entity {
+        fn on_patched(&mut self) {
+            self.patched += 1;
+        }
+    }
+
+    let entity = Entity {
+        name: "foo".to_owned(),
+        hp: 100,
+        shape: Shape::Rect { w: 1.0, h: 2.0 },
+        tags: vec!["a".to_owned(), "b".to_owned()],
+        stats: map! {"str".to_owned() => 10},
+        parent: None,
+        extra: (false, "x".to_owned()),
+        patched: 0,
+    };
+    let paths = |paths: &[crate::IntermediatePath]| {
+        paths
+            .iter()
+            .map(|path| path.to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let change = Change::partial_struct()
+        .partial_struct_item("name", Change::changed("bar"))
+        .partial_struct_item("hp", Change::changed(300))
+        .partial_struct_item(
+            "shape",
+            Change::partial_struct()
+                .partial_struct_item("h", Change::changed(3.0f32))
+                .partial_struct_item("d", Change::changed(1.0f32)),
+        )
+        .partial_struct_item(
+            "tags",
+            Change::partial_seq()
+                .partial_seq_item(0, Change::Removed)
+                .partial_seq_item(5, Change::Removed)
+                .partial_seq_item(1, Change::added("c"))
+                .partial_seq_item(0, Change::added(42)),
+        )
+        .partial_struct_item(
+            "stats",
+            Change::partial_map()
+                .partial_map_item("str", Change::changed(12))
+                .partial_map_item("dex", Change::Removed),
+        )
+        .partial_struct_item("parent", Change::partial_change(Change::changed(1)))
+        .partial_struct_item(
+            "extra",
+            Change::partial_seq().partial_seq_item(1, Change::changed("y")),
+        )
+        .partial_struct_item("patched", Change::changed(42))
+        .partial_struct_item("age", Change::changed(42));
+
+    let mut data = entity.clone();
+    let report = data.try_patch_change(&change);
+    assert!(!report.is_ok());
+    assert_eq!(
+        paths(&report.applied),
+        vec![
+            "name",
+            "shape.h",
+            "tags[0]",
+            "tags[1]",
+            "stats{\"str\"}",
+            "extra"
+        ]
+    );
+    assert_eq!(
+        paths(
+            &report
+                .rejected
+                .iter()
+                .map(|(path, _)| path.to_owned())
+                .collect::<Vec<_>>()
+        ),
+        vec![
+            "hp",
+            "shape.d",
+            "tags[5]",
+            "tags[0]",
+            "stats{\"dex\"}",
+            "parent?",
+            "patched",
+            "age"
+        ]
+    );
+    assert_eq!(data.name, "bar");
+    assert_eq!(data.hp, 100);
+    assert_eq!(data.shape, Shape::Rect { w: 1.0, h: 3.0 });
+    assert_eq!(data.tags, vec!["b".to_owned(), "c".to_owned()]);
+    assert_eq!(data.stats, map! {"str".to_owned() => 12});
+    assert_eq!(data.extra, (false, "y".to_owned()));
+    assert_eq!(data.patched, 1);
+
+    let mut data = entity.clone();
+    let error = data.try_patch_change_atomic(&change).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invalid patch at `hp`: value out of range"
+    );
+    assert_eq!(data, entity);
+
+    let change = Change::partial_struct()
+        .partial_struct_item("name", Change::changed("bar"))
+        .partial_struct_item(
+            "shape",
+            Change::changed(Intermediate::NewTypeVariant(
+                "Circle".to_owned(),
+                Box::new(1.0f32.into()),
+            )),
+        )
+        .partial_struct_item(
+            "tags",
+            Change::partial_seq().partial_seq_item(1, Change::moved(0)),
+        );
+    let mut data = entity.clone();
+    let report = data.try_patch_change_atomic(&change).unwrap();
+    assert!(report.is_ok());
+    assert_eq!(paths(&report.applied), vec!["name", "shape", "tags[1]"]);
+    assert_eq!(data.shape, Shape::Circle(1.0));
+    assert_eq!(data.tags, vec!["b".to_owned(), "a".to_owned()]);
+
+    let change = Change::partial_struct().partial_struct_item(
+        "shape",
+        Change::partial_seq().partial_seq_item(0, Change::changed(2.0f32)),
+    );
+    let report = data.try_patch_change(&change);
+    assert_eq!(paths(&report.applied), Vec::<String>::new());
+    assert_eq!(
+        report.rejected[0].1.to_string(),
+        "invalid patch at `shape`: change doesn't match enum variant"
+    );
+
+    let mut data = (1u8, [1, 2, 3], vec![Some(1)]);
+    let report = data.try_patch_change(
+        &Change::partial_seq()
+            .partial_seq_item(
+                1,
+                Change::partial_seq()
+                    .partial_seq_item(2, Change::changed(4))
+                    .partial_seq_item(3, Change::changed(5)),
+            )
+            .partial_seq_item(
+                2,
+                Change::partial_seq()
+                    .partial_seq_item(0, Change::partial_change(Change::changed(2)))
+                    .partial_seq_item(1, Change::added(Intermediate::Option(None))),
+            )
+            .partial_seq_item(3, Change::changed(0)),
+    );
+    assert_eq!(paths(&report.applied), vec!["[1][2]", "[2][0]?", "[2][1]"]);
+    assert_eq!(
+        paths(
+            &report
+                .rejected
+                .iter()
+                .map(|(path, _)| path.to_owned())
+                .collect::<Vec<_>>()
+        ),
+        vec!["[1][3]", "[3]"]
+    );
+    assert_eq!(data, (1, [1, 2, 4], vec![Some(2), None]));
+}
+
+#[test]
+fn test_diff_with() {
+    use crate::DiffIntermediate;
+
+    #[derive(Debug, Clone, Serialize, Deserialize, ReflectIntermediate, DiffIntermediate)]
+    enum Shape {
+        None,
+        Circle(f32),
+        Rect(f32, f32),
+        Path {
+            points: Vec<(f32, f32)>,
+            closed: bool,
+        },
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, ReflectIntermediate, DiffIntermediate)]
+    struct Entity {
+        name: String,
+        shape: Shape,
+        tags: Vec<String>,
+        props: HashMap<String, Pair<i32>>,
+        parent: Option<Box<Entity>>,
+        #[reflect_intermediate(ignore)]
+        id: (usize, bool),
+        #[serde(skip)]
+        cache: usize,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, ReflectIntermediate, DiffIntermediate)]
+    #[serde(bound(deserialize = "T: DeserializeOwned"))]
+    struct Pair<T: ReflectIntermediate + DeserializeOwned>(T, T);
+
+    #[derive(Debug, Clone, Serialize, Deserialize, ReflectIntermediate, DiffIntermediate)]
+    #[serde(tag = "type")]
+    enum Tagged {
+        A { value: i32 },
+        B { value: i32 },
+    }
+
+    fn check<T: DiffIntermediate + std::fmt::Debug>(prev: &T, next: &T) {
+        let options = DiffOptions::default();
+        assert_eq!(
+            prev.diff_with(next, &options).unwrap(),
+            Change::data_difference(prev, next, &options).unwrap(),
+            "{:?} -> {:?}",
+            prev,
+            next,
+        );
+        let options = DiffOptions::default().float_tolerance(crate::FloatTolerance::absolute(0.5));
+        assert_eq!(
+            prev.diff_with(next, &options).unwrap(),
+            Change::data_difference(prev, next, &options).unwrap(),
+        );
+    }
+
+    let entity = Entity {
+        name: "a".to_owned(),
+        shape: Shape::Rect(1.0, 2.0),
+        tags: vec!["a".to_owned(), "b".to_owned()],
+        props: map! {"a".to_owned() => Pair(1, 2)},
+        parent: Some(Box::new(Entity {
+            name: "root".to_owned(),
+            shape: Shape::None,
+            tags: vec![],
+            props: Default::default(),
+            parent: None,
+            id: (0, false),
+            cache: 0,
+        })),
+        id: (1, false),
+        cache: 0,
+    };
+    check(&entity, &entity);
+    let mut other = entity.clone();
+    other.cache = 42;
+    check(&entity, &other);
+    assert_eq!(
+        entity.diff_with(&other, &Default::default()).unwrap(),
+        Change::Same
+    );
+    other.name = "b".to_owned();
+    other.id.1 = true;
+    check(&entity, &other);
+    other.shape = Shape::Rect(1.0, 3.0);
+    other.tags.push("c".to_owned());
+    other.props.insert("b".to_owned(), Pair(3, 4));
+    other.props.insert("a".to_owned(), Pair(1, 3));
+    check(&entity, &other);
+    other.shape = Shape::Circle(1.0);
+    other.parent.as_mut().unwrap().name = "parent".to_owned();
+    check(&entity, &other);
+    other.parent = None;
+    other.props.clear();
+    check(&entity, &other);
+    other.shape = Shape::Path {
+        points: vec![(0.0, 0.0)],
+        closed: false,
+    };
+    check(&entity, &other);
+    let mut next = other.clone();
+    next.shape = Shape::Path {
+        points: vec![(0.0, 0.0), (1.0, 0.0)],
+        closed: true,
+    };
+    check(&other, &next);
+    check(&Shape::None, &Shape::None);
+    check(&Shape::Circle(1.0), &Shape::Circle(1.0));
+    check(&Tagged::A { value: 1 }, &Tagged::A { value: 2 });
+    check(&Tagged::A { value: 1 }, &Tagged::B { value: 1 });
+    check(&(1, "a".to_owned(), [1, 2]), &(1, "b".to_owned(), [1, 3]));
+    check(&vec![Some(1), None], &vec![Some(1), None]);
+    check(&vec![Some(1), None], &vec![Some(2), Some(1)]);
+    check(&(1..2), &(1..3));
+    check(&Ok::<_, bool>(1), &Err(false));
+}
+
+#[test]
+fn test_tracked() {
+    use crate::{IntermediatePath, Tracked};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate)]
+    struct Stats {
+        hp: f32,
+        buffs: HashMap<String, usize>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate)]
+    struct Player {
+        name: String,
+        stats: Option<Stats>,
+        items: Vec<String>,
+    }
+
+    fn path(path: &str) -> IntermediatePath {
+        path.parse().unwrap()
+    }
+
+    let mut remote = Player {
+        name: "a".to_owned(),
+        stats: Some(Stats {
+            hp: 10.0,
+            buffs: map! {"speed".to_owned() => 1},
+        }),
+        items: vec!["sword".to_owned(), "shield".to_owned()],
+    };
+    let mut tracked = Tracked::new(remote.clone());
+    assert!(!tracked.is_changed());
+    assert_eq!(
+        tracked.write_at(path("name"), |v| &mut v.name).as_str(),
+        "a"
+    );
+    assert!(!tracked.is_changed());
+
+    *tracked.write_at(path("stats?.hp"), |v| &mut v.stats.as_mut().unwrap().hp) -= 3.0;
+    *tracked.write_at(path(r#"stats?.buffs{"speed"}"#), |v| {
+        v.stats.as_mut().unwrap().buffs.get_mut("speed").unwrap()
+    }) += 1;
+    tracked
+        .write_at(path("items[1]"), |v| &mut v.items[1])
+        .push('!');
+    *tracked.write_at(path("stats?.hp"), |v| &mut v.stats.as_mut().unwrap().hp) -= 2.0;
+    assert!(tracked.is_changed());
+    let change = tracked.take_change().unwrap();
+    assert_eq!(
+        change,
+        Change::partial_struct()
+            .partial_struct_item(
+                "stats",
+                Change::partial_change(
+                    Change::partial_struct()
+                        .partial_struct_item("hp", Change::changed(5.0f32))
+                        .partial_struct_item(
+                            "buffs",
+                            Change::partial_map()
+                                .partial_map_item("speed", Change::changed(2usize)),
+                        ),
+                ),
+            )
+            .partial_struct_item(
+                "items",
+                Change::partial_seq().partial_seq_item(1, Change::changed("shield!")),
+            ),
+    );
+    remote.patch_change(&change);
+    assert_eq!(&remote, tracked.value());
+    assert!(!tracked.is_changed());
+
+    tracked.write().stats = None;
+    tracked.write_at(path("name"), |v| &mut v.name).push('b');
+    let change = tracked.take_change().unwrap();
+    assert_eq!(
+        change,
+        Change::changed(crate::to_intermediate(tracked.value()).unwrap())
+    );
+    remote.patch_change(&change);
+    assert_eq!(&remote, tracked.value());
+
+    tracked
+        .write_at(path("stats"), |v| &mut v.stats)
+        .replace(Stats {
+            hp: 1.0,
+            buffs: Default::default(),
+        });
+    *tracked.write_at(path("stats?.hp.value"), |v| {
+        &mut v.stats.as_mut().unwrap().hp
+    }) = 2.0;
+    let change = tracked.take_change().unwrap();
+    assert_eq!(
+        change,
+        Change::changed(crate::to_intermediate(tracked.value()).unwrap())
+    );
+    remote.patch_change(&change);
+    assert_eq!(&remote, tracked.value());
+}
+
+#[test]
+fn test_serde_attributes() {
+    use crate::DiffIntermediate;
+
+    fn check<T>(prev: &T, next: &T)
+    where
+        T: DiffIntermediate
+            + ReflectIntermediate
+            + DeserializeOwned
+            + Clone
+            + PartialEq
+            + std::fmt::Debug,
+    {
+        let change = Change::data_difference(prev, next, &Default::default()).unwrap();
+        assert_eq!(
+            prev.diff_with(next, &Default::default()).unwrap(),
+            change,
+            "{:?} -> {:?}",
+            prev,
+            next
+        );
+        let mut patched = prev.clone();
+        patched.patch_change(&change);
+        assert_eq!(&patched, next, "{:#?}", change);
+        let mut patched = prev.clone();
+        let report = patched.try_patch_change(&change);
+        assert!(report.is_ok(), "{:#?}", report);
+        assert_eq!(&patched, next, "{:#?}", change);
+    }
+
+    #[derive(
+        Debug,
+        Default,
+        Clone,
+        PartialEq,
+        Serialize,
+        Deserialize,
+        ReflectIntermediate,
+        DiffIntermediate,
+    )]
+    #[serde(rename_all = "camelCase")]
+    struct Renamed {
+        first_name: String,
+        #[serde(rename = "years")]
+        age: u8,
+        #[serde(skip)]
+        cache: usize,
+        r#type: bool,
+    }
+
+    #[derive(
+        Debug,
+        Default,
+        Clone,
+        PartialEq,
+        Serialize,
+        Deserialize,
+        ReflectIntermediate,
+        DiffIntermediate,
+    )]
+    struct Skipped(#[serde(skip)] usize, String, bool);
+
+    #[derive(
+        Debug,
+        Default,
+        Clone,
+        PartialEq,
+        Serialize,
+        Deserialize,
+        ReflectIntermediate,
+        DiffIntermediate,
+    )]
+    struct Flattened {
+        id: usize,
+        #[serde(flatten)]
+        renamed: Renamed,
+        #[serde(flatten)]
+        extra: HashMap<String, i32>,
+    }
+
+    #[derive(
+        Debug, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate, DiffIntermediate,
+    )]
+    #[serde(rename_all = "snake_case", rename_all_fields = "UPPERCASE")]
+    enum External {
+        UnitValue,
+        NewType(Renamed),
+        Tuple(#[serde(skip)] usize, i32, i32),
+        StructValue {
+            some_value: i32,
+            other: Vec<i32>,
+        },
+        #[serde(rename_all = "kebab-case")]
+        Kebab {
+            some_value: i32,
+        },
+    }
+
+    #[derive(
+        Debug, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate, DiffIntermediate,
+    )]
+    #[serde(tag = "kind", rename_all = "lowercase")]
+    enum Internal {
+        Unit,
+        NewType(Renamed),
+        Map(HashMap<String, i32>),
+        Struct {
+            #[serde(rename = "v")]
+            value: i32,
+            items: Vec<i32>,
+        },
+    }
+
+    #[derive(
+        Debug, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate, DiffIntermediate,
+    )]
+    #[serde(tag = "t", content = "c")]
+    enum Adjacent {
+        Unit,
+        NewType(Renamed),
+        Tuple(i32, String),
+        Struct {
+            #[serde(rename = "v")]
+            value: i32,
+        },
+    }
+
+    #[derive(
+        Debug, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate, DiffIntermediate,
+    )]
+    #[serde(untagged)]
+    enum Untagged {
+        Number(i32),
+        Struct { value: i32, text: String },
+        Other { value: i32 },
+    }
+
+    #[derive(
+        Debug, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate, DiffIntermediate,
+    )]
+    #[serde(transparent)]
+    struct Transparent {
+        inner: Renamed,
+    }
+
+    let renamed = Renamed {
+        first_name: "a".to_owned(),
+        age: 1,
+        cache: 0,
+        r#type: false,
+    };
+    let serialized = crate::to_intermediate(&renamed).unwrap();
+    assert_eq!(
+        serialized,
+        Intermediate::struct_type()
+            .field("firstName", "a")
+            .field("years", 1u8)
+            .field("type", false)
+    );
+    let other = Renamed {
+        first_name: "b".to_owned(),
+        age: 2,
+        cache: 0,
+        r#type: true,
+    };
+    check(&renamed, &other);
+    check(
+        &Skipped(0, "a".to_owned(), false),
+        &Skipped(0, "b".to_owned(), true),
+    );
+    check(
+        &Flattened {
+            id: 1,
+            renamed: renamed.clone(),
+            extra: map! {"a".to_owned() => 1, "b".to_owned() => 2},
+        },
+        &Flattened {
+            id: 2,
+            renamed: other.clone(),
+            extra: map! {"b".to_owned() => 3, "c".to_owned() => 4},
+        },
+    );
+
+    check(&External::UnitValue, &External::UnitValue);
+    check(
+        &External::NewType(renamed.clone()),
+        &External::NewType(other.clone()),
+    );
+    check(&External::Tuple(0, 1, 2), &External::Tuple(0, 3, 2));
+    check(
+        &External::StructValue {
+            some_value: 1,
+            other: vec![1],
+        },
+        &External::StructValue {
+            some_value: 2,
+            other: vec![1, 2],
+        },
+    );
+    check(
+        &External::Kebab { some_value: 1 },
+        &External::Kebab { some_value: 2 },
+    );
+    check(&External::UnitValue, &External::Kebab { some_value: 2 });
+
+    check(
+        &Internal::NewType(renamed.clone()),
+        &Internal::NewType(other.clone()),
+    );
+    check(
+        &Internal::Map(map! {"a".to_owned() => 1}),
+        &Internal::Map(map! {"a".to_owned() => 2}),
+    );
+    check(
+        &Internal::Struct {
+            value: 1,
+            items: vec![1, 2],
+        },
+        &Internal::Struct {
+            value: 2,
+            items: vec![2],
+        },
+    );
+    check(&Internal::Unit, &Internal::NewType(renamed.clone()));
+    check(
+        &Internal::Struct {
+            value: 1,
+            items: vec![],
+        },
+        &Internal::Map(Default::default()),
+    );
+
+    check(&Adjacent::Unit, &Adjacent::Unit);
+    check(
+        &Adjacent::NewType(renamed.clone()),
+        &Adjacent::NewType(other.clone()),
+    );
+    check(
+        &Adjacent::Tuple(1, "a".to_owned()),
+        &Adjacent::Tuple(2, "a".to_owned()),
+    );
+    check(
+        &Adjacent::Struct { value: 1 },
+        &Adjacent::Struct { value: 2 },
+    );
+    check(&Adjacent::Tuple(1, "a".to_owned()), &Adjacent::Unit);
+    check(
+        &Adjacent::Struct { value: 1 },
+        &Adjacent::Tuple(1, "a".to_owned()),
+    );
+
+    check(&Untagged::Number(1), &Untagged::Number(2));
+    check(
+        &Untagged::Struct {
+            value: 1,
+            text: "a".to_owned(),
+        },
+        &Untagged::Other { value: 1 },
+    );
+    check(
+        &Untagged::Other { value: 1 },
+        &Untagged::Struct {
+            value: 2,
+            text: "a".to_owned(),
+        },
+    );
+
+    check(
+        &Transparent {
+            inner: renamed.clone(),
+        },
+        &Transparent { inner: other },
+    );
+}
+
+#[test]
+fn test_reflect_containers() {
+    use crate::DiffIntermediate;
+    use std::{
+        borrow::Cow,
+        cell::{OnceCell, RefCell},
+        collections::BTreeMap,
+        rc::Rc,
+        sync::{Arc, RwLock},
+    };
+
+    #[derive(
+        Debug, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate, DiffIntermediate,
+    )]
+    #[reflect_intermediate(clone)]
+    struct Position {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, ReflectIntermediate)]
+    struct Unique {
+        x: i32,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+    struct Cell {
+        row: usize,
+        column: usize,
+    }
+
+    let change = Change::partial_struct().partial_struct_item("x", Change::changed(10));
+    let patched = Position { x: 10, y: 2 };
+
+    let mut a = Rc::new(Position { x: 1, y: 2 });
+    a.patch_change(&change);
+    assert_eq!(*a, patched);
+    let mut a = Rc::new(Position { x: 1, y: 2 });
+    let b = a.clone();
+    a.patch_change(&change);
+    assert_eq!(*a, patched);
+    assert_eq!(*b, Position { x: 1, y: 2 });
+    let mut a = Arc::new(Position { x: 1, y: 2 });
+    assert!(a.try_patch_change(&change).is_ok());
+    assert_eq!(*a, patched);
+    let mut a = Arc::new(Position { x: 1, y: 2 });
+    let b = a.clone();
+    a.patch_change(&Change::Same);
+    assert!(Arc::ptr_eq(&a, &b));
+    assert!(a.try_patch_change(&change).is_ok());
+    assert!(!Arc::ptr_eq(&a, &b));
+    assert_eq!(*a, patched);
+    assert_eq!(*b, Position { x: 1, y: 2 });
+    let mut a = Arc::new(5);
+    let b = a.clone();
+    a.patch_change(&Change::changed(8));
+    assert_eq!((*a, *b), (8, 5));
+    let mut a = Rc::new(vec![1, 2]);
+    let b = a.clone();
+    a.patch_change(&Change::partial_seq().partial_seq_item(1, Change::changed(3)));
+    assert_eq!((&*a, &*b), (&vec![1, 3], &vec![1, 2]));
+    let mut a = Arc::new(Unique { x: 1 });
+    let b = a.clone();
+    let report = a.try_patch_change(&change);
+    assert_eq!(
+        report.rejected[0].1.to_string(),
+        "invalid patch at ``: value is shared"
+    );
+    assert_eq!(*b, Unique { x: 1 });
+
+    let mut a = Arc::new(RwLock::new(Position { x: 1, y: 2 }));
+    let b = a.clone();
+    a.patch_change(&change);
+    assert_eq!(*b.read().unwrap(), patched);
+    let mut a = Rc::new(RefCell::new(Position { x: 1, y: 2 }));
+    let b = a.clone();
+    a.patch_change(&change);
+    assert_eq!(*b.borrow(), patched);
+
+    let shared = Arc::new(RwLock::new(Position { x: 1, y: 2 }));
+    let other = shared.clone();
+    let report = (&*shared).try_patch_change(&change);
+    assert_eq!(report.applied, vec!["x".parse().unwrap()]);
+    assert_eq!(*other.read().unwrap(), patched);
+    let shared = Rc::new(RefCell::new(Position { x: 1, y: 2 }));
+    let borrowed = shared.borrow();
+    let report = (&*shared).try_patch_change(&change);
+    assert!(!report.is_ok());
+    drop(borrowed);
+    (&*shared).patch_change(&change);
+    assert_eq!(*shared.borrow(), patched);
+
+    let mut a = RwLock::new(Position { x: 1, y: 2 });
+    a.patch_change(&change);
+    assert_eq!(*a.read().unwrap(), patched);
+    let mut a = RefCell::new(Position { x: 1, y: 2 });
+    a.patch_change(&change);
+    assert_eq!(*a.borrow(), patched);
+
+    let mut a = Cow::Borrowed("a");
+    a.patch_change(&Change::Same);
+    assert!(matches!(a, Cow::Borrowed("a")));
+    a.patch_change(&Change::changed("b"));
+    assert_eq!(a, "b");
+    let items = vec![1, 2];
+    let mut a = Cow::Borrowed(items.as_slice());
+    a.patch_change(&Change::partial_seq().partial_seq_item(1, Change::changed(3)));
+    assert_eq!(a, [1, 3].as_slice());
+    assert_eq!(items, vec![1, 2]);
+
+    let mut a = OnceCell::<Position>::new();
+    let report = a.try_patch_change(&change);
+    assert!(matches!(
+        report.rejected.as_slice(),
+        [(_, crate::Error::PathNotFound(_))]
+    ));
+    a.patch_change(&Change::Changed(
+        crate::to_intermediate(&Position { x: 1, y: 2 }).unwrap(),
+    ));
+    a.patch_change(&change);
+    assert_eq!(a.get(), Some(&patched));
+
+    let mut a = BTreeMap::new();
+    a.insert(Cell { row: 0, column: 0 }, Position { x: 1, y: 2 });
+    a.insert(Cell { row: 0, column: 1 }, Position { x: 3, y: 4 });
+    let mut b = a.clone();
+    b.remove(&Cell { row: 0, column: 1 });
+    b.get_mut(&Cell { row: 0, column: 0 }).unwrap().x = 10;
+    b.insert(Cell { row: 1, column: 0 }, Position { x: 5, y: 6 });
+    let change = Change::data_difference(&a, &b, &Default::default()).unwrap();
+    assert!(a.try_patch_change(&change).is_ok());
+    assert_eq!(a, b);
+
+    #[cfg(feature = "indexmap")]
+    {
+        use indexmap::{IndexMap, IndexSet};
+
+        let mut a = IndexMap::new();
+        a.insert("a".to_owned(), Position { x: 1, y: 2 });
+        a.insert("b".to_owned(), Position { x: 3, y: 4 });
+        a.insert("c".to_owned(), Position { x: 5, y: 6 });
+        let mut b = a.clone();
+        b.shift_remove("a");
+        b["b"].x = 10;
+        b.insert("d".to_owned(), Position { x: 7, y: 8 });
+        let change = a.diff_with(&b, &Default::default()).unwrap();
+        assert_eq!(
+            change,
+            Change::data_difference(&a, &b, &Default::default()).unwrap()
+        );
+        assert!(a.try_patch_change(&change).is_ok());
+        assert_eq!(a, b);
+        assert!(a.keys().eq(b.keys()));
+
+        let mut a = IndexSet::from([1, 2, 3]);
+        let b = IndexSet::from([1, 3, 4]);
+        let change = a.diff_with(&b, &Default::default()).unwrap();
+        a.patch_change(&change);
+        assert!(a.iter().eq(b.iter()));
+    }
+
+    #[cfg(feature = "smallvec")]
+    {
+        use smallvec::{smallvec, SmallVec};
+
+        let mut a: SmallVec<[Position; 2]> = smallvec![Position { x: 1, y: 2 }];
+        let b: SmallVec<[Position; 2]> = smallvec![
+            Position { x: 10, y: 2 },
+            Position { x: 3, y: 4 },
+            Position { x: 5, y: 6 },
+        ];
+        let change = a.diff_with(&b, &Default::default()).unwrap();
+        assert!(a.try_patch_change(&change).is_ok());
+        assert_eq!(a, b);
+    }
+}
+
+#[test]
+fn test_on_change() {
+    #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate)]
+    struct Mesh {
+        name: String,
+        #[reflect_intermediate(on_change = "recompute_bounds")]
+        vertices: Vec<(i32, i32)>,
+        #[reflect_intermediate(on_change = "recompute_bounds")]
+        scale: i32,
+        #[reflect_intermediate(on_change = "rebuild_buffers")]
+        #[serde(rename = "idx")]
+        indices: Vec<usize>,
+        #[serde(skip)]
+        bounds_rebuilds: usize,
+        #[serde(skip)]
+        buffers_rebuilds: usize,
+    }
+
+    impl Mesh {
+        fn recompute_bounds(&mut self) {
+            self.bounds_rebuilds += 1;
+        }
+
+        fn rebuild_buffers(&mut self) {
+            self.buffers_rebuilds += 1;
+        }
+    }
+
+    #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate)]
+    struct Tuple(
+        usize,
+        #[reflect_intermediate(on_change = "on_changed")] usize,
+        #[serde(skip)] usize,
+    );
+
+    impl Tuple {
+        fn on_changed(&mut self) {
+            self.2 += 1;
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate)]
+    enum Shape {
+        Circle {
+            #[reflect_intermediate(on_change = "on_changed")]
+            radius: i32,
+            #[serde(skip)]
+            changes: usize,
+        },
+        Square(#[reflect_intermediate(on_change = "on_changed")] i32),
+    }
+
+    impl Shape {
+        fn on_changed(&mut self) {
+            if let Self::Circle { changes, .. } = self {
+                *changes += 1;
+            }
+        }
+    }
+
+    #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate)]
+    struct Flattened {
+        #[reflect_intermediate(on_change = "on_changed")]
+        a: usize,
+        #[serde(flatten)]
+        rest: HashMap<String, usize>,
+        #[serde(skip)]
+        changes: usize,
+    }
+
+    impl Flattened {
+        fn on_changed(&mut self) {
+            self.changes += 1;
+        }
+    }
+
+    let mut mesh = Mesh::default();
+    let change = Change::partial_struct().partial_struct_item("name", Change::changed("a"));
+    let report = mesh.try_patch_change(&change);
+    assert!(report.is_changed(&"name".parse().unwrap()));
+    assert!(!report.is_changed(&"vertices".parse().unwrap()));
+    assert_eq!((mesh.bounds_rebuilds, mesh.buffers_rebuilds), (0, 0));
+    let change = Change::partial_struct()
+        .partial_struct_item(
+            "vertices",
+            Change::partial_seq().partial_seq_item(0, Change::added((1, 2))),
+        )
+        .partial_struct_item("scale", Change::changed(2));
+    mesh.patch_change(&change);
+    assert_eq!((mesh.bounds_rebuilds, mesh.buffers_rebuilds), (1, 0));
+    assert_eq!(mesh.vertices, vec![(1, 2)]);
+    let change = Change::partial_struct().partial_struct_item(
+        "idx",
+        Change::partial_seq().partial_seq_item(3, Change::Removed),
+    );
+    let report = mesh.try_patch_change(&change);
+    assert!(!report.is_ok());
+    assert_eq!((mesh.bounds_rebuilds, mesh.buffers_rebuilds), (1, 0));
+    let change = Change::partial_struct().partial_struct_item(
+        "idx",
+        Change::partial_seq().partial_seq_item(0, Change::added(1usize)),
+    );
+    mesh.patch_change(&change);
+    assert_eq!((mesh.bounds_rebuilds, mesh.buffers_rebuilds), (1, 1));
+    let mut other = mesh.clone();
+    other.scale = 3;
+    mesh.patch_change(&Change::Changed(crate::to_intermediate(&other).unwrap()));
+    // Replacement resets skipped counters, then runs hooks of all fields.
+    assert_eq!((mesh.bounds_rebuilds, mesh.buffers_rebuilds), (1, 1));
+    assert_eq!(mesh.scale, 3);
+
+    let mut data = Tuple::default();
+    data.patch_change(&Change::partial_seq().partial_seq_item(0, Change::changed(1usize)));
+    assert_eq!(data, Tuple(1, 0, 0));
+    data.patch_change(&Change::partial_seq().partial_seq_item(1, Change::changed(1usize)));
+    assert_eq!(data, Tuple(1, 1, 1));
+
+    let mut data = Shape::Circle {
+        radius: 1,
+        changes: 0,
+    };
+    data.patch_change(&Change::partial_struct().partial_struct_item("radius", Change::changed(2)));
+    assert_eq!(
+        data,
+        Shape::Circle {
+            radius: 2,
+            changes: 1
+        }
+    );
+    let mut data = Shape::Square(1);
+    let report = data.try_patch_change(&Change::partial_change(Change::changed(2)));
+    assert!(report.is_changed(&"?".parse().unwrap()));
+    assert_eq!(data, Shape::Square(2));
+
+    let mut data = Flattened::default();
+    data.patch_change(&Change::partial_map().partial_map_item("b", Change::added(1usize)));
+    assert_eq!(data.changes, 0);
+    data.patch_change(&Change::partial_map().partial_map_item("a", Change::changed(1usize)));
+    assert_eq!(data.changes, 1);
+    assert_eq!(data.a, 1);
+    assert_eq!(data.rest, map! {"b".to_owned() => 1});
+}
+
+#[test]
+fn test_change_history() {
+    use crate::ChangeHistory;
+
+    #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate)]
+    struct Scene {
+        name: String,
+        objects: Vec<String>,
+    }
+
+    fn rename(name: &str) -> Change {
+        Change::partial_struct().partial_struct_item("name", Change::changed(name))
+    }
+
+    fn add(index: usize, name: &str) -> Change {
+        Change::partial_struct().partial_struct_item(
+            "objects",
+            Change::partial_seq().partial_seq_item(index, Change::added(name)),
+        )
+    }
+
+    let mut history = ChangeHistory::new(Scene::default());
+    assert!(!history.can_undo());
+    assert!(!history.undo().unwrap());
+    history.apply(&rename("a")).unwrap();
+    history.apply(&add(0, "cube")).unwrap();
+    history.checkpoint("cube");
+    history.apply(&add(1, "sphere")).unwrap();
+    assert!(history.apply(&add(5, "cone")).is_err());
+    assert_eq!(history.value().objects, vec!["cube", "sphere"]);
+
+    assert!(history.undo().unwrap());
+    assert!(history.undo().unwrap());
+    assert_eq!(
+        history.value(),
+        &Scene {
+            name: "a".to_owned(),
+            objects: vec![]
+        }
+    );
+    assert!(history.can_redo());
+    history.restore_checkpoint("cube").unwrap();
+    assert_eq!(history.value().objects, vec!["cube"]);
+    assert!(history.redo().unwrap());
+    assert!(!history.redo().unwrap());
+    assert_eq!(history.value().objects, vec!["cube", "sphere"]);
+    history.undo().unwrap();
+    history.undo().unwrap();
+    history.apply(&rename("b")).unwrap();
+    assert!(!history.can_redo());
+    assert!(!history.has_checkpoint("cube"));
+    assert!(matches!(
+        history.restore_checkpoint("cube"),
+        Err(crate::Error::UnknownCheckpoint(_))
+    ));
+
+    history.begin_transaction();
+    history.apply(&add(0, "light")).unwrap();
+    history.apply(&add(1, "camera")).unwrap();
+    history.commit_transaction();
+    history.begin_transaction();
+    history.apply(&rename("c")).unwrap();
+    history.cancel_transaction().unwrap();
+    assert_eq!(
+        history.value(),
+        &Scene {
+            name: "b".to_owned(),
+            objects: vec!["light".to_owned(), "camera".to_owned()]
+        }
+    );
+    history.undo().unwrap();
+    assert!(history.value().objects.is_empty());
+    history.redo().unwrap();
+    assert_eq!(history.value().objects.len(), 2);
+    history.undo().unwrap();
+    history.undo().unwrap();
+    history.undo().unwrap();
+    assert_eq!(history.value(), &Scene::default());
+    assert!(!history.can_undo());
+
+    let mut history = ChangeHistory::new(Scene {
+        name: "x".to_owned(),
+        objects: vec![],
+    });
+    history.checkpoint("first");
+    history.apply(&rename("a")).unwrap();
+    let bytesize = history.total_bytesize();
+    let mut history = history.max_bytesize(bytesize * 2);
+    history.apply(&rename("b")).unwrap();
+    history.apply(&rename("c")).unwrap();
+    assert!(history.total_bytesize() <= bytesize * 2);
+    assert!(!history.has_checkpoint("first"));
+    history.undo().unwrap();
+    history.undo().unwrap();
+    assert!(!history.can_undo());
+    assert_eq!(history.value().name, "a");
+
+    let mut history = ChangeHistory::new(Scene::default());
+    history.apply(&rename("a")).unwrap();
+    let bytesize = history.total_bytesize();
+    for name in ["b", "c", "d"] {
+        history.apply(&rename(name)).unwrap();
+    }
+    history.checkpoint("d");
+    history.undo().unwrap();
+    history.undo().unwrap();
+    let history = history.max_bytesize(bytesize * 2);
+    assert!(history.total_bytesize() <= bytesize * 2);
+    assert!(history.can_undo());
+    assert!(!history.can_redo());
+    assert!(!history.has_checkpoint("d"));
+
+    let mut history = ChangeHistory::new(Scene::default()).max_bytesize(bytesize * 2);
+    history.checkpoint("empty");
+    history.apply(&rename("a")).unwrap();
+    history.begin_transaction();
+    for index in 0..4 {
+        history.apply(&add(index, "cube")).unwrap();
+    }
+    assert!(!history.has_checkpoint("empty"));
+    history.commit_transaction();
+    history.undo().unwrap();
+    assert_eq!(history.value().name, "a");
+    assert!(!history.can_undo());
+
+    #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+    struct Picky(u8);
+
+    impl ReflectIntermediate for Picky {
+        fn patch_change_with_report(&mut self, change: &Change, report: &mut crate::PatchReport) {
+            match report.patch_serialized(self, change) {
+                Some(Picky(0)) => report.reject_invalid("zero is not allowed"),
+                Some(value) => *self = value,
+                None => {}
+            }
+        }
+    }
+
+    #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate)]
+    struct Setting {
+        name: String,
+        value: Picky,
+    }
+
+    let mut history = ChangeHistory::new(Setting::default());
+    history.begin_transaction();
+    history
+        .apply(&Change::partial_struct().partial_struct_item(
+            "value",
+            Change::Changed(crate::to_intermediate(&Picky(5)).unwrap()),
+        ))
+        .unwrap();
+    history
+        .apply(&Change::partial_struct().partial_struct_item("name", Change::changed("a")))
+        .unwrap();
+    let edited = Setting {
+        name: "a".to_owned(),
+        value: Picky(5),
+    };
+    assert!(history.cancel_transaction().is_err());
+    assert_eq!(history.value(), &edited);
+    assert!(history.is_in_transaction());
+    assert!(history.undo().is_err());
+    assert_eq!(history.value(), &edited);
+    assert!(history.can_undo());
+    assert!(!history.can_redo());
+}
+
+#[test]
+fn test_schema_validation() {
+    use crate::{
+        schema::{validation::*, *},
+        IntermediatePath,
+    };
+
+    let data = Struct {
+        bool_value: true,
+        i8_value: -1,
+        i16_value: 2,
+        i32_value: -3,
+        i64_value: 4,
+        i128_value: -5,
+        u8_value: 6,
+        u16_value: 7,
+        u32_value: 8,
+        u64_value: 9,
+        u128_value: 10,
+        f32_value: 1.1,
+        f64_value: 1.2,
+        char_value: '@',
+        string_value: "hello".to_owned(),
+        tuple: (false, 13),
+        bytes: vec![14, 15],
+        option: Some(UnitStruct),
+        list: vec![20, 21, 23],
+        set: set![20, 21, 23],
+        string_map: map! {"a".to_owned() => 24},
+        integer_map: map! {27 => 28},
+        enum_value: Enum::NewType(UnitStruct),
+        new_type_struct: NewTypeStruct(true),
+        tuple_struct: TupleStruct(false, 32),
+    };
+    let mut package = SchemaPackage::default();
+    let root = Struct::schema(&mut package);
+    let mut value = crate::to_intermediate(&data).unwrap();
+    assert!(package.validate(&value, &root).is_empty());
+
+    let path = |content: &str| content.parse::<IntermediatePath>().unwrap();
+    value.remove(&path("i8_value")).unwrap();
+    value.remove(&path("option")).unwrap();
+    value.set(&path("u8_value"), 300).unwrap();
+    value
+        .set(&path("tuple"), Intermediate::tuple().item(true))
+        .unwrap();
+    value.set(&path("list[1]"), "text").unwrap();
+    value
+        .set(&path("string_map"), Intermediate::map().property(1, 2))
+        .unwrap();
+    value
+        .set(
+            &path("enum_value"),
+            Intermediate::newtype_variant("Tuple", Intermediate::Bool(true)),
+        )
+        .unwrap();
+    value
+        .set(
+            &path("tuple_struct"),
+            Intermediate::tuple_struct().item(false).item(1).item(2),
+        )
+        .unwrap();
+    value.insert(&path("extra"), 42).unwrap();
+    let wrong_type = |expected: &str, found: &str| SchemaMismatchReason::WrongType {
+        expected: expected.to_owned(),
+        found: found.to_owned(),
+    };
+    assert_eq!(
+        package.validate(&value, &root),
+        vec![
+            SchemaMismatch {
+                path: path("u8_value"),
+                reason: wrong_type("u8", "i32"),
+            },
+            SchemaMismatch {
+                path: path("tuple"),
+                reason: SchemaMismatchReason::WrongArity {
+                    expected: 2,
+                    found: 1,
+                },
+            },
+            SchemaMismatch {
+                path: path("list[1]"),
+                reason: wrong_type("usize", "string"),
+            },
+            SchemaMismatch {
+                path: IntermediatePath::default()
+                    .field("string_map")
+                    .key(Intermediate::I32(1)),
+                reason: wrong_type("alloc::string::String", "i32"),
+            },
+            SchemaMismatch {
+                path: path("enum_value"),
+                reason: SchemaMismatchReason::WrongVariantKind {
+                    variant: "Tuple".to_owned(),
+                    expected: "tuple variant".to_owned(),
+                    found: "newtype variant".to_owned(),
+                },
+            },
+            SchemaMismatch {
+                path: path("tuple_struct"),
+                reason: SchemaMismatchReason::WrongArity {
+                    expected: 2,
+                    found: 3,
+                },
+            },
+            SchemaMismatch {
+                path: IntermediatePath::default(),
+                reason: SchemaMismatchReason::UnknownField("extra".to_owned()),
+            },
+            SchemaMismatch {
+                path: IntermediatePath::default(),
+                reason: SchemaMismatchReason::MissingField("i8_value".to_owned()),
+            },
+        ]
+    );
+
+    let root = Enum::schema(&mut package);
+    let value = Intermediate::unit_variant("Missing");
+    assert_eq!(
+        package.validate(&value, &root),
+        vec![SchemaMismatch {
+            path: IntermediatePath::default(),
+            reason: SchemaMismatchReason::UnknownVariant("Missing".to_owned()),
+        }]
+    );
+    let value = Intermediate::struct_variant("Struct")
+        .field("scalar", 1.5_f32)
+        .field("text", Intermediate::seq());
+    assert_eq!(
+        package.validate(&value, &root),
+        vec![SchemaMismatch {
+            path: path("text"),
+            reason: wrong_type("alloc::string::String", "sequence"),
+        }]
+    );
+
+    let root = SchemaIdContainer::new_id(SchemaId::new::<[u8; 3]>());
+    package.with(
+        root.to_owned(),
+        SchemaType::new_array((SchemaIdTree::new::<u8>(), 3)),
+    );
+    let value = Intermediate::seq().item(1_u8).item(-1);
+    assert_eq!(
+        package.validate(&value, &root),
+        vec![
+            SchemaMismatch {
+                path: IntermediatePath::default(),
+                reason: SchemaMismatchReason::WrongCount {
+                    expected: 3,
+                    found: 2,
+                },
+            },
+            SchemaMismatch {
+                path: path("[1]"),
+                reason: wrong_type("u8", "i32"),
+            },
+        ]
+    );
+    assert_eq!(
+        package.validate(&value, &root)[0].to_string(),
+        "at ``: expected 3 array items, found 2"
+    );
+}
+
+#[test]
+fn test_schema_std_types() {
+    use crate::schema::{validation::*, *};
+
+    #[derive(Serialize, SchemaIntermediate)]
+    struct Inventory {
+        #[schema_intermediate(package)]
+        owner: Option<String>,
+        #[schema_intermediate(package)]
+        slots: Vec<Option<u32>>,
+        #[schema_intermediate(package)]
+        prices: HashMap<String, (bool, f32)>,
+        #[schema_intermediate(package)]
+        size: [u8; 2],
+        #[schema_intermediate(package)]
+        icon: Box<char>,
+    }
+
+    let mut package = SchemaPackage::default().prefer_tree_id(true);
+    let root = Inventory::schema(&mut package);
+    let find = |id: SchemaIdContainer| package.find(&id).unwrap().data_type.to_owned();
+    assert_eq!(
+        find(SchemaIdContainer::new::<u32>(true)),
+        SchemaType::new_primitive(SchemaTypePrimitive::U32)
+    );
+    assert_eq!(
+        find(SchemaIdContainer::new::<Option<String>>(true)),
+        SchemaType::new_option(SchemaIdTree::new::<String>())
+    );
+    assert_eq!(
+        find(SchemaIdContainer::new::<Vec<Option<u32>>>(true)),
+        SchemaType::new_seq(SchemaIdTree::new::<Option<u32>>())
+    );
+    assert_eq!(
+        find(SchemaIdContainer::new::<HashMap<String, (bool, f32)>>(true)),
+        SchemaType::new_map((
+            SchemaIdTree::new::<String>(),
+            SchemaIdTree::new::<(bool, f32)>()
+        ))
+    );
+    assert_eq!(
+        find(SchemaIdContainer::new::<(bool, f32)>(true)),
+        SchemaType::new_tuple(
+            SchemaTypeTuple::default()
+                .item(SchemaIdTree::new::<bool>())
+                .item(SchemaIdTree::new::<f32>())
+        )
+    );
+    assert_eq!(
+        find(SchemaIdContainer::new::<[u8; 2]>(true)),
+        SchemaType::new_array((SchemaIdTree::new::<u8>(), 2))
+    );
+    match &package.find(&root).unwrap().data_type {
+        SchemaType::Struct(content) => {
+            assert_eq!(content.0["icon"].id, SchemaIdTree::new::<char>().into());
+        }
+        _ => unreachable!(),
+    }
+
+    // Every type referenced in package has its schema.
+    for schema in package.schemas.values() {
+        let ids = match &schema.data_type {
+            SchemaType::Primitive(_) => vec![],
+            SchemaType::Option(content) | SchemaType::Seq(content) => vec![&content.id],
+            SchemaType::Map(content) => vec![&content.key.id, &content.value.id],
+            SchemaType::Tuple(content) | SchemaType::TupleStruct(content) => {
+                content.0.iter().map(|item| &item.id).collect()
+            }
+            SchemaType::Array(content) | SchemaType::Slice(content) => {
+                vec![&content.type_instance.id]
+            }
+            SchemaType::Struct(content) => content.0.values().map(|item| &item.id).collect(),
+            SchemaType::Enum(_) => unreachable!(),
+        };
+        for id in ids {
+            assert!(package.find(id).is_some(), "missing schema of: {}", id);
+        }
+    }
+
+    let value = crate::to_intermediate(&Inventory {
+        owner: None,
+        slots: vec![Some(1), None],
+        prices: map! {"apple".to_owned() => (true, 1.5)},
+        size: [3, 4],
+        icon: Box::new('@'),
+    })
+    .unwrap();
+    assert!(package.validate(&value, &root).is_empty());
+    let mut value = value;
+    value
+        .set(
+            &"slots[1]".parse().unwrap(),
+            Intermediate::Option(Some(Box::new(Intermediate::I32(-1)))),
+        )
+        .unwrap();
+    value.remove(&"owner".parse().unwrap()).unwrap();
+    assert_eq!(
+        package.validate(&value, &root),
+        vec![SchemaMismatch {
+            path: "slots[1]?".parse().unwrap(),
+            reason: SchemaMismatchReason::WrongType {
+                expected: "u32".to_owned(),
+                found: "i32".to_owned(),
+            },
+        }]
+    );
+}
+
+#[test]
+fn test_json_schema() {
+    use crate::schema::*;
+    use serde_json::json;
+
+    let mut package = SchemaPackage::default();
+    let root = Enum::schema(&mut package);
+    let document = serde_json::to_value(package.to_json_schema(&root)).unwrap();
+    let enum_id = root.to_string();
+    let unit_struct_id = SchemaIdContainer::new::<UnitStruct>(false).to_string();
+    let unit_struct_ref = format!("#/$defs/{}", unit_struct_id);
+    assert_eq!(
+        document,
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "$ref": format!("#/$defs/{}", enum_id),
+            "$defs": {
+                enum_id: {
+                    "oneOf": [
+                        {
+                            "type": "object",
+                            "properties": { "NewType": { "$ref": unit_struct_ref } },
+                            "required": ["NewType"],
+                            "additionalProperties": false,
+                        },
+                        {
+                            "type": "object",
+                            "properties": {
+                                "Struct": {
+                                    "type": "object",
+                                    "properties": {
+                                        "scalar": { "type": "number" },
+                                        "text": { "type": "string" },
+                                    },
+                                    "required": ["scalar", "text"],
+                                    "additionalProperties": false,
+                                },
+                            },
+                            "required": ["Struct"],
+                            "additionalProperties": false,
+                        },
+                        {
+                            "type": "object",
+                            "properties": {
+                                "Tuple": {
+                                    "type": "array",
+                                    "prefixItems": [
+                                        { "type": "boolean" },
+                                        {
+                                            "type": "integer",
+                                            "minimum": 0,
+                                            "maximum": u64::MAX,
+                                        },
+                                    ],
+                                    "items": false,
+                                    "minItems": 2,
+                                },
+                            },
+                            "required": ["Tuple"],
+                            "additionalProperties": false,
+                        },
+                        { "const": "Unit" },
+                    ],
+                    "description": "Enum.",
+                },
+                unit_struct_id: {
+                    "type": ["null", "object"],
+                    "additionalProperties": false,
+                    "description": "Unit struct.",
+                },
+            },
+        })
+    );
+
+    let mut package = SchemaPackage::default();
+    let root = <(Option<NewTypeStruct>, [u8; 2])>::schema(&mut package);
+    NewTypeStruct::schema(&mut package);
+    let document = serde_json::to_value(package.to_json_schema(&root)).unwrap();
+    let new_type_struct_id = SchemaIdContainer::new::<NewTypeStruct>(false).to_string();
+    assert_eq!(
+        document["$ref"],
+        json!("#/$defs/(core::option::Option%3Cserde_intermediate::tests::NewTypeStruct%3E,%20%5Bu8;%202%5D)")
+    );
+    assert_eq!(
+        document["$defs"][new_type_struct_id],
+        json!({ "type": "boolean", "description": "New type struct." })
+    );
+}
+
+#[test]
+fn test_schema_compatibility() {
+    use crate::{
+        schema::{compatibility::*, *},
+        IntermediatePath,
+    };
+
+    let save = SchemaIdTree::Path {
+        path: vec!["game".to_owned(), "Save".to_owned()],
+        args: vec![],
+    };
+    let item = SchemaIdTree::Path {
+        path: vec!["game".to_owned(), "Item".to_owned()],
+        args: vec![],
+    };
+    let mut old = SchemaPackage::default();
+    old.with(
+        save.to_owned(),
+        SchemaType::new_struct(
+            SchemaTypeStruct::default()
+                .field("name", SchemaIdTree::new::<String>())
+                .field("level", SchemaIdTree::new::<u16>())
+                .field("hp", SchemaIdTree::new::<f32>())
+                .field("nickname", SchemaIdTree::new::<Option<String>>())
+                .field("slots", SchemaIdContainer::new::<[u8; 4]>(true))
+                .field("items", SchemaIdTree::new::<Vec<u32>>()),
+        ),
+    )
+    .with(
+        SchemaIdContainer::new::<[u8; 4]>(true),
+        SchemaType::new_array((SchemaIdTree::new::<u8>(), 4)),
+    )
+    .with(
+        item.to_owned(),
+        SchemaType::new_enum(
+            SchemaTypeEnum::default()
+                .variant("Sword", SchemaTypeEnumVariant::Empty)
+                .variant("Bow", SchemaTypeEnumVariant::Empty)
+                .variant(
+                    "Potion",
+                    SchemaTypeEnumVariant::new_tuple(
+                        SchemaTypeTuple::default().item(SchemaIdTree::new::<u8>()),
+                    ),
+                ),
+        ),
+    );
+    let mut new = SchemaPackage::default();
+    new.with(
+        save.to_owned(),
+        SchemaType::new_struct(
+            SchemaTypeStruct::default()
+                .field("name", SchemaIdTree::new::<String>())
+                .field("level", SchemaIdTree::new::<u32>())
+                .field("hp", SchemaIdTree::new::<i32>())
+                .field("slots", SchemaIdContainer::new::<[u8; 4]>(true))
+                .field("items", SchemaIdTree::new::<Vec<u32>>())
+                .field("gold", SchemaIdTree::new::<u64>())
+                .field("title", SchemaIdTree::new::<Option<String>>()),
+        ),
+    )
+    .with(
+        SchemaIdContainer::new::<[u8; 4]>(true),
+        SchemaType::new_array((SchemaIdTree::new::<u8>(), 6)),
+    )
+    .with(
+        item.to_owned(),
+        SchemaType::new_enum(
+            SchemaTypeEnum::default()
+                .variant("Sword", SchemaTypeEnumVariant::Empty)
+                .variant("Shield", SchemaTypeEnumVariant::Empty)
+                .variant(
+                    "Potion",
+                    SchemaTypeEnumVariant::new_tuple(
+                        SchemaTypeTuple::default()
+                            .item(SchemaIdTree::new::<u8>())
+                            .item(SchemaIdTree::new::<bool>()),
+                    ),
+                ),
+        ),
+    );
+
+    let changes = old.compatibility(&new);
+    let summary = changes
+        .iter()
+        .map(|change| {
+            (
+                change.id.to_string(),
+                change.path.to_string(),
+                change.kind.to_owned(),
+                change.backward_compatible,
+                change.forward_compatible,
+            )
+        })
+        .collect::<Vec<_>>();
+    let array = SchemaIdContainer::new::<[u8; 4]>(true).to_string();
+    let changed = |from: &str, to: &str| SchemaChangeKind::TypeChanged {
+        from: from.to_owned(),
+        to: to.to_owned(),
+    };
+    assert_eq!(
+        summary,
+        vec![
+            (
+                array,
+                "".to_owned(),
+                SchemaChangeKind::CountChanged { from: 4, to: 6 },
+                false,
+                false,
+            ),
+            (
+                "game::Item".to_owned(),
+                "".to_owned(),
+                SchemaChangeKind::VariantRemoved("Bow".to_owned()),
+                false,
+                true,
+            ),
+            (
+                "game::Item".to_owned(),
+                "Potion".to_owned(),
+                changed("newtype variant `Potion`", "tuple variant `Potion`"),
+                false,
+                false,
+            ),
+            (
+                "game::Item".to_owned(),
+                "".to_owned(),
+                SchemaChangeKind::VariantAdded("Shield".to_owned()),
+                true,
+                false,
+            ),
+            (
+                "game::Save".to_owned(),
+                "".to_owned(),
+                SchemaChangeKind::FieldAdded {
+                    name: "gold".to_owned(),
+                    required: true,
+                },
+                false,
+                true,
+            ),
+            (
+                "game::Save".to_owned(),
+                "hp".to_owned(),
+                changed("f32", "i32"),
+                false,
+                true,
+            ),
+            (
+                "game::Save".to_owned(),
+                "level".to_owned(),
+                changed("u16", "u32"),
+                true,
+                false,
+            ),
+            (
+                "game::Save".to_owned(),
+                "".to_owned(),
+                SchemaChangeKind::FieldRemoved("nickname".to_owned()),
+                true,
+                true,
+            ),
+            (
+                "game::Save".to_owned(),
+                "".to_owned(),
+                SchemaChangeKind::FieldAdded {
+                    name: "title".to_owned(),
+                    required: false,
+                },
+                true,
+                true,
+            ),
+        ]
+    );
+    assert_eq!(
+        changes.iter().filter(|change| change.is_breaking()).count(),
+        5
+    );
+    assert_eq!(
+        changes[6].to_string(),
+        "`game::Save` at `level`: type changed from `u16` to `u32` (non-breaking)"
+    );
+    assert_eq!(changes[6].path, IntermediatePath::default().field("level"));
+    assert!(old.compatibility(&old).is_empty());
+}
+
+#[test]
+fn test_codegen() {
+    use crate::schema::*;
+    use std::collections::BTreeMap;
+
+    /// Page of results.
+    #[derive(Serialize, SchemaIntermediate)]
+    struct Page<T: SchemaIntermediate> {
+        #[schema_intermediate(package)]
+        items: Vec<T>,
+        /// Token of next page.
+        #[schema_intermediate(package)]
+        next: Option<String>,
+        #[schema_intermediate(package)]
+        kind: BTreeMap<String, u8>,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Serialize, SchemaIntermediate)]
+    enum Event {
+        Closed,
+        #[schema_intermediate(package)]
+        Moved(f32, f32),
+        #[schema_intermediate(package)]
+        Resized {
+            width: u32,
+            height: u32,
+        },
+        #[schema_intermediate(package)]
+        Loaded(Box<Page<Marker>>),
+    }
+
+    #[derive(Serialize, SchemaIntermediate)]
+    struct Marker;
+
+    let mut package = SchemaPackage::default();
+    Event::schema(&mut package);
+    Page::<Marker>::schema(&mut package);
+    assert_eq!(
+        package.to_rust_code(),
+        r#"use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Event {
+    Closed,
+    Loaded(Box<Page<Marker>>),
+    Moved(f32, f32),
+    Resized {
+        height: u32,
+        width: u32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Marker;
+
+/// Page of results.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub kind: BTreeMap<String, u8>,
+    /// Token of next page.
+    pub next: Option<String>,
+}
+"#
+    );
+    assert_eq!(
+        package.to_typescript_code(),
+        r#"export type Event =
+  | "Closed"
+  | { Loaded: Page<Marker> }
+  | { Moved: [number, number] }
+  | { Resized: { height: number; width: number } };
+
+export type Marker = null;
+
+/** Page of results. */
+export interface Page<T> {
+  items: Array<T>;
+  kind: Record<string, number>;
+  /** Token of next page. */
+  next?: string | null;
+}
+"#
+    );
+}
+
+#[test]
+fn test_patch_change_matches_report() {
+    let changes = vec![
+        Change::changed(Intermediate::seq().item(4).item(5)),
+        Change::partial_seq()
+            .partial_seq_item(1, Change::changed(4))
+            .partial_seq_item(3, Change::changed(5)),
+    ];
+    for change in changes {
+        let mut a = [1, 2, 3];
+        let mut b = a;
+        a.patch_change(&change);
+        let report = b.try_patch_change(&change);
+        assert_eq!(a, b);
+        assert!(!report.is_ok());
+    }
+
+    let mut a = Box::new(Some(1));
+    let mut b = a.clone();
+    let change = Change::partial_change(Change::changed(2));
+    a.patch_change(&change);
+    assert!(b.try_patch_change(&change).is_ok());
+    assert_eq!(a, b);
+    assert_eq!(*a, Some(2));
+
+    let mut a = Intermediate::seq().item(1);
+    let change = Change::partial_seq()
+        .partial_seq_item(0, Change::changed(2))
+        .partial_seq_item(3, Change::Removed);
+    let report = a.try_patch_change(&change);
+    assert_eq!(a, Intermediate::seq().item(2));
+    assert_eq!(report.rejected[0].0.to_string(), "[3]");
+
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ReflectIntermediate)]
+    #[reflect_intermediate(after_patch_change = "on_patched")]
+    struct Counter {
+        value: u8,
+        #[serde(skip)]
+        #[reflect_intermediate(ignore)]
+        patched: usize,
+    }
+
+    impl Counter {
+        fn on_patched(&mut self) {
+            self.patched += 1;
+        }
+    }
+
+    let mut data = Counter::default();
+    let change = Change::partial_struct()
+        .partial_struct_item("value", Change::changed(1))
+        .partial_struct_item("other", Change::changed(2));
+    assert!(data.try_patch_change_atomic(&change).is_err());
+    assert_eq!(data, Counter::default());
+    let change = Change::partial_struct().partial_struct_item("value", Change::changed(1));
+    assert!(data.try_patch_change_atomic(&change).is_ok());
+    assert_eq!(
+        data,
+        Counter {
+            value: 1,
+            patched: 1
+        }
+    );
+}
+
+#[test]
+fn test_reflect_without_diff() {
+    use crate::DiffIntermediate;
+
+    #[derive(Debug, Default, PartialEq, Deserialize)]
+    struct Handle(u32);
+
+    impl ReflectIntermediate for Handle {}
+
+    #[derive(Debug, Default, PartialEq, Deserialize, ReflectIntermediate)]
+    struct Config {
+        value: u8,
+        handle: Handle,
+    }
+
+    let mut data = Config::default();
+    data.patch_change(
+        &Change::partial_struct()
+            .partial_struct_item("value", Change::changed(1))
+            .partial_struct_item("handle", Change::changed(2u32)),
+    );
+    assert_eq!(
+        data,
+        Config {
+            value: 1,
+            handle: Handle(0)
+        }
+    );
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, DiffIntermediate)]
+    struct Pair(u8, Vec<u8>, (u8, [u8; 2]));
+
+    let prev = Pair(1, vec![1, 2], (3, [4, 5]));
+    let next = Pair(1, vec![1, 2, 3], (3, [4, 6]));
+    let change = prev.diff_with(&next, &Default::default()).unwrap();
+    assert_eq!(
+        change,
+        Change::partial_seq()
+            .partial_seq_item(
+                1,
+                Change::partial_seq().partial_seq_item(2, Change::added(3u8))
+            )
+            .partial_seq_item(
+                2,
+                Change::partial_seq().partial_seq_item(
+                    1,
+                    Change::partial_seq().partial_seq_item(1, Change::changed(6u8))
+                )
+            )
+    );
+    assert_eq!(
+        change,
+        Change::data_difference(&prev, &next, &Default::default()).unwrap()
+    );
+}
+
+#[test]
+fn test_reflect_shared_fields() {
+    use std::sync::{Arc, Mutex, RwLock};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate)]
+    #[reflect_intermediate(clone)]
+    struct Pos {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, ReflectIntermediate)]
+    struct World {
+        p: Arc<RwLock<Pos>>,
+        m: Arc<Mutex<Pos>>,
+        owned: Arc<Pos>,
+    }
+
+    let mut world = World {
+        p: Arc::new(RwLock::new(Pos { x: 1, y: 2 })),
+        m: Arc::new(Mutex::new(Pos { x: 1, y: 2 })),
+        owned: Arc::new(Pos { x: 1, y: 2 }),
+    };
+    let p = world.p.clone();
+    let m = world.m.clone();
+    let owned = world.owned.clone();
+    let change = Change::partial_struct()
+        .partial_struct_item(
+            "p",
+            Change::partial_struct().partial_struct_item("x", Change::changed(3)),
+        )
+        .partial_struct_item(
+            "m",
+            Change::partial_struct().partial_struct_item("y", Change::changed(4)),
+        )
+        .partial_struct_item(
+            "owned",
+            Change::partial_struct().partial_struct_item("x", Change::changed(5)),
+        );
+    let report = world.try_patch_change(&change);
+    assert!(report.is_ok());
+    assert_eq!(*p.read().unwrap(), Pos { x: 3, y: 2 });
+    assert_eq!(*m.lock().unwrap(), Pos { x: 1, y: 4 });
+    assert_eq!(*world.owned, Pos { x: 5, y: 2 });
+    assert_eq!(*owned, Pos { x: 1, y: 2 });
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate)]
+    #[serde(bound(deserialize = "T: DeserializeOwned"))]
+    #[reflect_intermediate(clone)]
+    struct Tagged<T: ReflectIntermediate + DeserializeOwned> {
+        tag: String,
+        value: T,
+    }
+
+    let mut a = Arc::new(Tagged {
+        tag: "a".to_owned(),
+        value: 1u8,
+    });
+    let b = a.clone();
+    a.patch_change(&Change::partial_struct().partial_struct_item("value", Change::changed(2u8)));
+    assert_eq!((a.value, b.value), (2, 1));
+}
+
+#[test]
+fn test_schema_self_describing() {
+    use crate::schema::{validation::*, *};
+
+    #[derive(Serialize, SchemaIntermediate)]
+    #[allow(dead_code)]
+    enum Mode {
+        Idle,
+        Busy(u8),
+    }
+
+    #[derive(Serialize, SchemaIntermediate)]
+    struct Task {
+        name: String,
+        #[schema_intermediate(package)]
+        mode: Mode,
+    }
+
+    let mut package = SchemaPackage::default();
+    let root = Task::schema(&mut package);
+    let task = Task {
+        name: "a".to_owned(),
+        mode: Mode::Idle,
+    };
+    let content = serde_json::to_string(&task).unwrap();
+    let value = serde_json::from_str::<Intermediate>(&content).unwrap();
+    assert!(matches!(value, Intermediate::Map(_)));
+    assert!(package.validate(&value, &root).is_empty());
+
+    let value = serde_json::from_str::<Intermediate>(r#"{"name":"a","mode":"Busy"}"#).unwrap();
+    assert_eq!(
+        package.validate(&value, &root),
+        vec![SchemaMismatch {
+            path: "mode".parse().unwrap(),
+            reason: SchemaMismatchReason::WrongVariantKind {
+                variant: "Busy".to_owned(),
+                expected: "newtype variant".to_owned(),
+                found: "string".to_owned(),
+            },
+        }]
+    );
+    let value = serde_json::from_str::<Intermediate>(r#"{"mode":"Stopped"}"#).unwrap();
+    assert_eq!(
+        package
+            .validate(&value, &root)
+            .into_iter()
+            .map(|mismatch| mismatch.reason)
+            .collect::<Vec<_>>(),
+        vec![
+            SchemaMismatchReason::UnknownVariant("Stopped".to_owned()),
+            SchemaMismatchReason::MissingField("name".to_owned()),
+        ]
+    );
+
+    let mut package = SchemaPackage::default();
+    package.with(
+        SchemaId::new::<Vec<(u8, bool)>>(),
+        SchemaType::new_seq(SchemaIdTree::new::<(u8, bool)>()),
+    );
+    package.with(
+        SchemaIdTree::new::<Option<u8>>(),
+        SchemaType::new_option(SchemaIdTree::new::<u8>()),
+    );
+    assert!(package
+        .find(&SchemaIdTree::new::<Vec<(u8, bool)>>().into())
+        .is_some());
+    assert!(package
+        .find(&SchemaId::new::<Option<u8>>().into())
+        .is_some());
+    assert!(package
+        .find(&SchemaId::new::<Option<u16>>().into())
+        .is_none());
+}
+
+#[test]
+fn test_codegen_generics_and_collisions() {
+    use crate::schema::*;
+
+    mod a {
+        use crate::{self as serde_intermediate, SchemaIntermediate};
+
+        #[allow(dead_code)]
+        #[derive(SchemaIntermediate)]
+        pub struct Item {
+            pub value: u8,
+        }
+    }
+
+    mod b {
+        use crate::{self as serde_intermediate, SchemaIntermediate};
+
+        #[allow(dead_code)]
+        #[derive(SchemaIntermediate)]
+        pub struct Item(pub String);
+    }
+
+    #[allow(dead_code)]
+    #[derive(SchemaIntermediate)]
+    struct Wrapper<T: SchemaIntermediate> {
+        value: T,
+        count: u32,
+    }
+
+    #[allow(dead_code)]
+    #[derive(SchemaIntermediate)]
+    struct Inventory {
+        #[schema_intermediate(package)]
+        first: a::Item,
+        #[schema_intermediate(package)]
+        second: b::Item,
+        #[schema_intermediate(package)]
+        counts: Wrapper<u32>,
+        #[schema_intermediate(package)]
+        names: Option<Wrapper<String>>,
+    }
+
+    let mut package = SchemaPackage::default();
+    Inventory::schema(&mut package);
+    Wrapper::<u32>::schema(&mut package);
+    Wrapper::<String>::schema(&mut package);
+    assert_eq!(
+        package.to_rust_code(),
+        r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AItem {
+    pub value: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BItem(pub String);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Inventory {
+    pub counts: Wrapper<u32>,
+    pub first: AItem,
+    pub names: Option<Wrapper<String>>,
+    pub second: BItem,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Wrapper<T> {
+    pub count: u32,
+    pub value: T,
+}
+"#
+    );
+    assert_eq!(
+        package.to_typescript_code(),
+        r#"export interface AItem {
+  value: number;
+}
+
+export type BItem = string;
+
+export interface Inventory {
+  counts: Wrapper<number>;
+  first: AItem;
+  names?: Wrapper<string> | null;
+  second: BItem;
+}
+
+export interface Wrapper<T> {
+  count: number;
+  value: T;
+}
+"#
+    );
+}
+
+#[test]
+fn test_diff_with_map_order() {
+    use crate::DiffIntermediate;
+
+    let mut a = HashMap::<u32, u32>::new();
+    let mut b = HashMap::<u32, u32>::new();
+    for key in 0..32 {
+        a.insert(key, key * 2);
+    }
+    for key in (0..32).rev() {
+        b.insert(key, key * 2);
+    }
+    let options = DiffOptions::default();
+    let change = a.diff_with(&b, &options).unwrap();
+    assert_eq!(change, Change::Same);
+    assert_eq!(change, Change::data_difference(&a, &b, &options).unwrap());
+
+    b.insert(7, 0);
+    let change = a.diff_with(&b, &options).unwrap();
+    assert_eq!(
+        change,
+        Change::partial_map().partial_map_item(7u32, Change::changed(0u32))
+    );
+}
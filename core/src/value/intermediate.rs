@@ -1,5 +1,9 @@
 use crate::{
-    de::intermediate::IntermediateVisitor, reflect::ReflectIntermediate, versioning::Change,
+    de::intermediate::IntermediateVisitor,
+    error::Error,
+    reflect::{PatchReport, ReflectIntermediate},
+    value::path::{IntermediatePath, IntermediatePathSegment},
+    versioning::Change,
 };
 use serde::{
     ser::{
@@ -145,6 +149,50 @@ pub enum Intermediate {
 
 impl Eq for Intermediate {}
 
+/// Floats are hashed by their bits, with negative zero treated as positive zero, so that
+/// values equal to each other share hash.
+impl std::hash::Hash for Intermediate {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Self::Unit | Self::UnitStruct => {}
+            Self::Bool(v) => v.hash(state),
+            Self::I8(v) => v.hash(state),
+            Self::I16(v) => v.hash(state),
+            Self::I32(v) => v.hash(state),
+            Self::I64(v) => v.hash(state),
+            Self::I128(v) => v.hash(state),
+            Self::U8(v) => v.hash(state),
+            Self::U16(v) => v.hash(state),
+            Self::U32(v) => v.hash(state),
+            Self::U64(v) => v.hash(state),
+            Self::U128(v) => v.hash(state),
+            Self::F32(v) => (if *v == 0.0 { 0.0f32 } else { *v }).to_bits().hash(state),
+            Self::F64(v) => (if *v == 0.0 { 0.0f64 } else { *v }).to_bits().hash(state),
+            Self::Char(v) => v.hash(state),
+            Self::String(v) | Self::UnitVariant(v) => v.hash(state),
+            Self::Bytes(v) => v.hash(state),
+            Self::Option(v) => v.hash(state),
+            Self::NewTypeStruct(v) => v.hash(state),
+            Self::NewTypeVariant(n, v) => {
+                n.hash(state);
+                v.hash(state);
+            }
+            Self::Seq(v) | Self::Tuple(v) | Self::TupleStruct(v) => v.hash(state),
+            Self::TupleVariant(n, v) => {
+                n.hash(state);
+                v.hash(state);
+            }
+            Self::Map(v) => v.hash(state),
+            Self::Struct(v) => v.hash(state),
+            Self::StructVariant(n, v) => {
+                n.hash(state);
+                v.hash(state);
+            }
+        }
+    }
+}
+
 impl Intermediate {
     pub fn unit_struct() -> Self {
         Self::UnitStruct
@@ -276,10 +324,236 @@ impl Intermediate {
     }
 }
 
+impl Intermediate {
+    /// Returns reference to value at given path.
+    /// `Option` and newtype wrappers are traversed implicitly when path does not point to their content.
+    pub fn get(&self, path: &IntermediatePath) -> Option<&Self> {
+        path.segments()
+            .iter()
+            .try_fold(self, |value, segment| value.child(segment))
+    }
+
+    /// Returns mutable reference to value at given path.
+    pub fn get_mut(&mut self, path: &IntermediatePath) -> Option<&mut Self> {
+        path.segments()
+            .iter()
+            .try_fold(self, |value, segment| value.child_mut(segment))
+    }
+
+    /// Replaces existing value at given path and returns previous one.
+    pub fn set(
+        &mut self,
+        path: &IntermediatePath,
+        value: impl Into<Self>,
+    ) -> crate::error::Result<Self> {
+        let target = self
+            .get_mut(path)
+            .ok_or_else(|| Error::PathNotFound(path.to_owned()))?;
+        Ok(std::mem::replace(target, value.into()))
+    }
+
+    /// Inserts value at given path: shifts sequence items, adds map entries and struct fields,
+    /// fills `Option` content. Returns previous value if one was replaced.
+    pub fn insert(
+        &mut self,
+        path: &IntermediatePath,
+        value: impl Into<Self>,
+    ) -> crate::error::Result<Option<Self>> {
+        let (segment, parent) = match (path.last(), path.parent()) {
+            (Some(segment), Some(parent)) => (segment, parent),
+            _ => return Err(Error::PathNotFound(path.to_owned())),
+        };
+        self.get_mut(&parent)
+            .ok_or_else(|| Error::PathNotFound(path.to_owned()))?
+            .insert_child(segment, value.into(), path)
+    }
+
+    /// Removes value at given path and returns it.
+    pub fn remove(&mut self, path: &IntermediatePath) -> crate::error::Result<Self> {
+        let (segment, parent) = match (path.last(), path.parent()) {
+            (Some(segment), Some(parent)) => (segment, parent),
+            _ => return Err(Error::PathNotFound(path.to_owned())),
+        };
+        self.get_mut(&parent)
+            .ok_or_else(|| Error::PathNotFound(path.to_owned()))?
+            .remove_child(segment, path)
+    }
+
+    fn child(&self, segment: &IntermediatePathSegment) -> Option<&Self> {
+        match (self, segment) {
+            (
+                Self::Seq(v) | Self::Tuple(v) | Self::TupleStruct(v) | Self::TupleVariant(_, v),
+                IntermediatePathSegment::Index(index),
+            ) => v.get(*index),
+            (Self::Map(v), IntermediatePathSegment::Key(key)) => {
+                v.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+            }
+            (Self::Map(v), IntermediatePathSegment::Field(name)) => v
+                .iter()
+                .find(|(k, _)| k.as_str() == Some(name))
+                .map(|(_, v)| v),
+            (Self::Struct(v) | Self::StructVariant(_, v), IntermediatePathSegment::Field(name)) => {
+                v.iter().find(|(k, _)| k == name).map(|(_, v)| v)
+            }
+            (
+                Self::Option(Some(v)) | Self::NewTypeStruct(v) | Self::NewTypeVariant(_, v),
+                IntermediatePathSegment::Inner,
+            ) => Some(v),
+            (
+                Self::Option(Some(v)) | Self::NewTypeStruct(v) | Self::NewTypeVariant(_, v),
+                segment,
+            ) => v.child(segment),
+            _ => None,
+        }
+    }
+
+    fn child_mut(&mut self, segment: &IntermediatePathSegment) -> Option<&mut Self> {
+        match (self, segment) {
+            (
+                Self::Seq(v) | Self::Tuple(v) | Self::TupleStruct(v) | Self::TupleVariant(_, v),
+                IntermediatePathSegment::Index(index),
+            ) => v.get_mut(*index),
+            (Self::Map(v), IntermediatePathSegment::Key(key)) => {
+                v.iter_mut().find(|(k, _)| k == key).map(|(_, v)| v)
+            }
+            (Self::Map(v), IntermediatePathSegment::Field(name)) => v
+                .iter_mut()
+                .find(|(k, _)| k.as_str() == Some(name))
+                .map(|(_, v)| v),
+            (Self::Struct(v) | Self::StructVariant(_, v), IntermediatePathSegment::Field(name)) => {
+                v.iter_mut().find(|(k, _)| k == name).map(|(_, v)| v)
+            }
+            (
+                Self::Option(Some(v)) | Self::NewTypeStruct(v) | Self::NewTypeVariant(_, v),
+                IntermediatePathSegment::Inner,
+            ) => Some(v),
+            (
+                Self::Option(Some(v)) | Self::NewTypeStruct(v) | Self::NewTypeVariant(_, v),
+                segment,
+            ) => v.child_mut(segment),
+            _ => None,
+        }
+    }
+
+    fn insert_child(
+        &mut self,
+        segment: &IntermediatePathSegment,
+        item: Self,
+        path: &IntermediatePath,
+    ) -> crate::error::Result<Option<Self>> {
+        match (self, segment) {
+            (
+                Self::Seq(v) | Self::Tuple(v) | Self::TupleStruct(v) | Self::TupleVariant(_, v),
+                IntermediatePathSegment::Index(index),
+            ) => {
+                if *index > v.len() {
+                    return Err(Error::PathNotFound(path.to_owned()));
+                }
+                v.insert(*index, item);
+                Ok(None)
+            }
+            (Self::Map(v), IntermediatePathSegment::Key(key)) => {
+                if let Some((_, value)) = v.iter_mut().find(|(k, _)| k == key) {
+                    Ok(Some(std::mem::replace(value, item)))
+                } else {
+                    v.push((key.to_owned(), item));
+                    Ok(None)
+                }
+            }
+            (Self::Map(v), IntermediatePathSegment::Field(name)) => {
+                if let Some((_, value)) = v.iter_mut().find(|(k, _)| k.as_str() == Some(name)) {
+                    Ok(Some(std::mem::replace(value, item)))
+                } else {
+                    v.push((Self::String(name.to_owned()), item));
+                    Ok(None)
+                }
+            }
+            (Self::Struct(v) | Self::StructVariant(_, v), IntermediatePathSegment::Field(name)) => {
+                if let Some((_, value)) = v.iter_mut().find(|(k, _)| k == name) {
+                    Ok(Some(std::mem::replace(value, item)))
+                } else {
+                    v.push((name.to_owned(), item));
+                    Ok(None)
+                }
+            }
+            (Self::Option(v), IntermediatePathSegment::Inner) => {
+                Ok(v.replace(Box::new(item)).map(|v| *v))
+            }
+            (
+                Self::NewTypeStruct(v) | Self::NewTypeVariant(_, v),
+                IntermediatePathSegment::Inner,
+            ) => Ok(Some(std::mem::replace(v.as_mut(), item))),
+            (
+                Self::Option(Some(v)) | Self::NewTypeStruct(v) | Self::NewTypeVariant(_, v),
+                segment,
+            ) => v.insert_child(segment, item, path),
+            (value, _) => Err(Error::CannotAdd(value.to_owned())),
+        }
+    }
+
+    fn remove_child(
+        &mut self,
+        segment: &IntermediatePathSegment,
+        path: &IntermediatePath,
+    ) -> crate::error::Result<Self> {
+        match (self, segment) {
+            (
+                Self::Seq(v) | Self::Tuple(v) | Self::TupleStruct(v) | Self::TupleVariant(_, v),
+                IntermediatePathSegment::Index(index),
+            ) => {
+                if *index >= v.len() {
+                    return Err(Error::PathNotFound(path.to_owned()));
+                }
+                Ok(v.remove(*index))
+            }
+            (Self::Map(v), IntermediatePathSegment::Key(key)) => v
+                .iter()
+                .position(|(k, _)| k == key)
+                .map(|index| v.remove(index).1)
+                .ok_or_else(|| Error::PathNotFound(path.to_owned())),
+            (Self::Map(v), IntermediatePathSegment::Field(name)) => v
+                .iter()
+                .position(|(k, _)| k.as_str() == Some(name))
+                .map(|index| v.remove(index).1)
+                .ok_or_else(|| Error::PathNotFound(path.to_owned())),
+            (Self::Struct(v) | Self::StructVariant(_, v), IntermediatePathSegment::Field(name)) => {
+                v.iter()
+                    .position(|(k, _)| k == name)
+                    .map(|index| v.remove(index).1)
+                    .ok_or_else(|| Error::PathNotFound(path.to_owned()))
+            }
+            (Self::Option(v), IntermediatePathSegment::Inner) => v
+                .take()
+                .map(|v| *v)
+                .ok_or_else(|| Error::PathNotFound(path.to_owned())),
+            (
+                Self::Option(Some(v)) | Self::NewTypeStruct(v) | Self::NewTypeVariant(_, v),
+                segment,
+            ) if segment != &IntermediatePathSegment::Inner => v.remove_child(segment, path),
+            (value, _) => Err(Error::CannotRemove(value.to_owned())),
+        }
+    }
+}
+
 impl ReflectIntermediate for Intermediate {
     fn patch_change(&mut self, change: &Change) {
-        if let Ok(Some(v)) = change.patch(self) {
-            *self = v;
+        self.patch_change_with_report(change, &mut Default::default());
+    }
+
+    fn patch_change_with_report(&mut self, change: &Change, report: &mut PatchReport) {
+        if change.is_same() {
+            return;
+        }
+        let (patched, errors) = change.patch_collect(self);
+        report.reject_nested(errors);
+        match patched {
+            Some(v) => {
+                if v != *self {
+                    *self = v;
+                    report.apply();
+                }
+            }
+            None => report.reject(Error::CannotRemove(self.to_owned())),
         }
     }
 }
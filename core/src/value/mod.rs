@@ -1,2 +1,3 @@
 pub mod intermediate;
 pub mod object;
+pub mod path;
@@ -0,0 +1,612 @@
+use crate::{error::*, value::intermediate::Intermediate};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Single step of `IntermediatePath`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Serialize, Deserialize)]
+pub enum IntermediatePathSegment {
+    /// Sequence/tuple item index: `[3]`.
+    Index(usize),
+    /// Map entry key: `{"key"}`.
+    Key(Intermediate),
+    /// Structure field name: `.name`.
+    Field(String),
+    /// Content of `Option` or newtype wrapper: `?`.
+    Inner,
+}
+
+impl std::fmt::Display for IntermediatePathSegment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Index(index) => write!(f, "[{}]", index),
+            Self::Key(key) => write!(f, "{{{}}}", key),
+            Self::Field(name) => {
+                if is_identifier(name) {
+                    write!(f, ".{}", name)
+                } else {
+                    write!(f, ".{:?}", name)
+                }
+            }
+            Self::Inner => write!(f, "?"),
+        }
+    }
+}
+
+/// Location of value inside `Intermediate` tree.
+///
+/// # Example
+/// ```rust
+/// use serde_intermediate::{Intermediate, IntermediatePath};
+///
+/// let mut value = Intermediate::struct_type().field(
+///     "players",
+///     Intermediate::seq().item(Intermediate::struct_type().field("hp", 100)),
+/// );
+/// let path = "players[0].hp".parse::<IntermediatePath>().unwrap();
+/// assert_eq!(path, IntermediatePath::default().field("players").index(0).field("hp"));
+/// assert_eq!(value.get(&path), Some(&Intermediate::I32(100)));
+/// value.set(&path, 42).unwrap();
+/// assert_eq!(value.get(&path), Some(&Intermediate::I32(42)));
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Serialize, Deserialize)]
+pub struct IntermediatePath(pub Vec<IntermediatePathSegment>);
+
+impl IntermediatePath {
+    pub fn new(segments: impl IntoIterator<Item = IntermediatePathSegment>) -> Self {
+        Self(segments.into_iter().collect())
+    }
+
+    pub fn index(mut self, index: usize) -> Self {
+        self.0.push(IntermediatePathSegment::Index(index));
+        self
+    }
+
+    pub fn key(mut self, key: impl Into<Intermediate>) -> Self {
+        self.0.push(IntermediatePathSegment::Key(key.into()));
+        self
+    }
+
+    pub fn field(mut self, name: impl ToString) -> Self {
+        self.0
+            .push(IntermediatePathSegment::Field(name.to_string()));
+        self
+    }
+
+    pub fn inner(mut self) -> Self {
+        self.0.push(IntermediatePathSegment::Inner);
+        self
+    }
+
+    pub fn segment(mut self, segment: IntermediatePathSegment) -> Self {
+        self.0.push(segment);
+        self
+    }
+
+    pub fn push(&mut self, segment: IntermediatePathSegment) {
+        self.0.push(segment);
+    }
+
+    pub fn pop(&mut self) -> Option<IntermediatePathSegment> {
+        self.0.pop()
+    }
+
+    pub fn segments(&self) -> &[IntermediatePathSegment] {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn last(&self) -> Option<&IntermediatePathSegment> {
+        self.0.last()
+    }
+
+    pub fn parent(&self) -> Option<Self> {
+        if self.0.is_empty() {
+            None
+        } else {
+            Some(Self(self.0[..(self.0.len() - 1)].to_vec()))
+        }
+    }
+
+    pub fn join(&self, other: &Self) -> Self {
+        Self(self.0.iter().chain(other.0.iter()).cloned().collect())
+    }
+
+    pub fn starts_with(&self, other: &Self) -> bool {
+        self.0.starts_with(&other.0)
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let invalid = |position: usize| {
+            Error::InvalidPath(format!(
+                "Unexpected token at position {}: `{}`",
+                position, content
+            ))
+        };
+        let chars = content.char_indices().collect::<Vec<_>>();
+        let mut result = vec![];
+        let mut index = 0;
+        while index < chars.len() {
+            let (position, c) = chars[index];
+            match c {
+                '.' => {
+                    index += 1;
+                    match chars.get(index) {
+                        Some((_, '"')) => {
+                            let (name, next) = parse_string(content, &chars, index)
+                                .ok_or_else(|| invalid(position))?;
+                            result.push(IntermediatePathSegment::Field(name));
+                            index = next;
+                        }
+                        Some(_) => {
+                            let (name, next) = parse_identifier(&chars, index);
+                            if name.is_empty() {
+                                return Err(invalid(position));
+                            }
+                            result.push(IntermediatePathSegment::Field(name));
+                            index = next;
+                        }
+                        None => return Err(invalid(position)),
+                    }
+                }
+                '[' => {
+                    let end = chars[index..]
+                        .iter()
+                        .position(|(_, c)| *c == ']')
+                        .map(|offset| index + offset)
+                        .ok_or_else(|| invalid(position))?;
+                    let text = chars[(index + 1)..end]
+                        .iter()
+                        .map(|(_, c)| c)
+                        .collect::<String>();
+                    let item = text.trim().parse().map_err(|_| invalid(position))?;
+                    result.push(IntermediatePathSegment::Index(item));
+                    index = end + 1;
+                }
+                '{' => {
+                    let end = find_closing_brace(&chars, index).ok_or_else(|| invalid(position))?;
+                    let text = &content[(position + 1)..chars[end].0];
+                    let key = crate::intermediate_from_str(text).map_err(|_| invalid(position))?;
+                    result.push(IntermediatePathSegment::Key(key));
+                    index = end + 1;
+                }
+                '?' => {
+                    result.push(IntermediatePathSegment::Inner);
+                    index += 1;
+                }
+                c if index == 0 && is_identifier_char(c) => {
+                    let (name, next) = parse_identifier(&chars, index);
+                    result.push(IntermediatePathSegment::Field(name));
+                    index = next;
+                }
+                _ => return Err(invalid(position)),
+            }
+        }
+        Ok(Self(result))
+    }
+}
+
+impl std::fmt::Display for IntermediatePath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, segment) in self.0.iter().enumerate() {
+            match segment {
+                IntermediatePathSegment::Field(name) if index == 0 && is_identifier(name) => {
+                    write!(f, "{}", name)?;
+                }
+                segment => segment.fmt(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for IntermediatePath {
+    type Err = Error;
+
+    fn from_str(content: &str) -> Result<Self> {
+        Self::parse(content)
+    }
+}
+
+impl From<IntermediatePathSegment> for IntermediatePath {
+    fn from(segment: IntermediatePathSegment) -> Self {
+        Self(vec![segment])
+    }
+}
+
+impl FromIterator<IntermediatePathSegment> for IntermediatePath {
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = IntermediatePathSegment>,
+    {
+        Self(iter.into_iter().collect())
+    }
+}
+
+/// Single step of `IntermediatePathPattern`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Serialize, Deserialize)]
+pub enum IntermediatePathPatternSegment {
+    /// Exact path segment.
+    Segment(IntermediatePathSegment),
+    /// Any structure field: `.*`.
+    AnyField,
+    /// Any sequence/tuple item index: `[*]`.
+    AnyIndex,
+    /// Any map entry key: `{*}`.
+    AnyKey,
+    /// Any number of segments, including none: `**`.
+    AnyDepth,
+}
+
+impl std::fmt::Display for IntermediatePathPatternSegment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Segment(segment) => segment.fmt(f),
+            Self::AnyField => write!(f, ".*"),
+            Self::AnyIndex => write!(f, "[*]"),
+            Self::AnyKey => write!(f, "{{*}}"),
+            Self::AnyDepth => write!(f, ".**"),
+        }
+    }
+}
+
+/// Result of matching `IntermediatePath` against `IntermediatePathPattern`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IntermediatePathMatch {
+    /// Neither path nor its descendants match pattern.
+    None,
+    /// Path does not match pattern, but some of its descendants might.
+    Ancestor,
+    /// Path or one of its ancestors matches pattern.
+    Matched,
+}
+
+/// Glob-like pattern of `IntermediatePath`.
+///
+/// Uses `IntermediatePath` syntax extended with wildcards: `.*` matches any field, `[*]` any
+/// index, `{*}` any key and `**` any number of segments.
+///
+/// # Example
+/// ```rust
+/// use serde_intermediate::{IntermediatePath, IntermediatePathPattern};
+///
+/// let pattern = "**.last_modified".parse::<IntermediatePathPattern>().unwrap();
+/// assert!(pattern.matches(&"last_modified".parse().unwrap()));
+/// assert!(pattern.matches(&"entities[3].last_modified".parse().unwrap()));
+/// assert!(!pattern.matches(&"entities[3].name".parse().unwrap()));
+///
+/// let pattern = "entities[*].cache".parse::<IntermediatePathPattern>().unwrap();
+/// assert!(pattern.matches(&"entities[0].cache".parse().unwrap()));
+/// assert!(!pattern.matches(&"entities.cache".parse().unwrap()));
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Serialize, Deserialize)]
+pub struct IntermediatePathPattern(pub Vec<IntermediatePathPatternSegment>);
+
+impl IntermediatePathPattern {
+    pub fn new(segments: impl IntoIterator<Item = IntermediatePathPatternSegment>) -> Self {
+        Self(segments.into_iter().collect())
+    }
+
+    pub fn segment(mut self, segment: IntermediatePathPatternSegment) -> Self {
+        self.0.push(segment);
+        self
+    }
+
+    pub fn segments(&self) -> &[IntermediatePathPatternSegment] {
+        &self.0
+    }
+
+    /// Tells if path exactly matches this pattern.
+    pub fn matches(&self, path: &IntermediatePath) -> bool {
+        matches_segments(&self.0, &path.0)
+    }
+
+    /// Tells how path relates to this pattern, treating subtrees of matched paths as matched.
+    pub fn match_path(&self, path: &IntermediatePath) -> IntermediatePathMatch {
+        match_segments(&self.0, &path.0)
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let invalid = |position: usize| {
+            Error::InvalidPath(format!(
+                "Unexpected token at position {}: `{}`",
+                position, content
+            ))
+        };
+        let chars = content.char_indices().collect::<Vec<_>>();
+        let wildcard = |index: usize| match (chars.get(index), chars.get(index + 1)) {
+            (Some((_, '*')), Some((_, '*'))) => Some((IntermediatePathPatternSegment::AnyDepth, 2)),
+            (Some((_, '*')), _) => Some((IntermediatePathPatternSegment::AnyField, 1)),
+            _ => None,
+        };
+        let mut result = vec![];
+        let mut index = 0;
+        while index < chars.len() {
+            let (position, c) = chars[index];
+            let start = match c {
+                '.' => index + 1,
+                '[' | '{' => {
+                    let end = if c == '[' {
+                        chars[index..]
+                            .iter()
+                            .position(|(_, c)| *c == ']')
+                            .map(|offset| index + offset)
+                    } else {
+                        find_closing_brace(&chars, index)
+                    }
+                    .ok_or_else(|| invalid(position))?;
+                    let text = content[(position + 1)..chars[end].0].trim();
+                    if text == "*" {
+                        result.push(if c == '[' {
+                            IntermediatePathPatternSegment::AnyIndex
+                        } else {
+                            IntermediatePathPatternSegment::AnyKey
+                        });
+                    } else {
+                        let path = IntermediatePath::parse(&content[position..=chars[end].0])?;
+                        result.extend(
+                            path.0
+                                .into_iter()
+                                .map(IntermediatePathPatternSegment::Segment),
+                        );
+                    }
+                    index = end + 1;
+                    continue;
+                }
+                '?' => {
+                    result.push(IntermediatePathPatternSegment::Segment(
+                        IntermediatePathSegment::Inner,
+                    ));
+                    index += 1;
+                    continue;
+                }
+                c if index == 0 && (c == '*' || is_identifier_char(c)) => index,
+                _ => return Err(invalid(position)),
+            };
+            if let Some((segment, length)) = wildcard(start) {
+                result.push(segment);
+                index = start + length;
+                continue;
+            }
+            let end = match chars.get(start) {
+                Some((_, '"')) => skip_quoted(&chars, start).map(|end| end + 1),
+                Some(_) => Some(parse_identifier(&chars, start).1),
+                None => None,
+            }
+            .filter(|end| *end > start)
+            .ok_or_else(|| invalid(position))?;
+            let text =
+                &content[chars[start].0..chars.get(end).map(|(p, _)| *p).unwrap_or(content.len())];
+            let path = IntermediatePath::parse(&format!(".{}", text))?;
+            result.extend(
+                path.0
+                    .into_iter()
+                    .map(IntermediatePathPatternSegment::Segment),
+            );
+            index = end;
+        }
+        Ok(Self(result))
+    }
+}
+
+impl std::fmt::Display for IntermediatePathPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, segment) in self.0.iter().enumerate() {
+            match segment {
+                IntermediatePathPatternSegment::Segment(IntermediatePathSegment::Field(name))
+                    if index == 0 && is_identifier(name) =>
+                {
+                    write!(f, "{}", name)?;
+                }
+                IntermediatePathPatternSegment::AnyField if index == 0 => write!(f, "*")?,
+                IntermediatePathPatternSegment::AnyDepth if index == 0 => write!(f, "**")?,
+                segment => segment.fmt(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for IntermediatePathPattern {
+    type Err = Error;
+
+    fn from_str(content: &str) -> Result<Self> {
+        Self::parse(content)
+    }
+}
+
+impl From<IntermediatePath> for IntermediatePathPattern {
+    fn from(path: IntermediatePath) -> Self {
+        Self(
+            path.0
+                .into_iter()
+                .map(IntermediatePathPatternSegment::Segment)
+                .collect(),
+        )
+    }
+}
+
+/// Set of included and excluded path patterns.
+///
+/// Path passes filter when it is not matched by any of excluded patterns and, if there are any
+/// included patterns, it is matched by one of them. Subtrees of matched paths are matched too.
+///
+/// # Example
+/// ```rust
+/// use serde_intermediate::{IntermediatePathFilter, IntermediatePathMatch};
+///
+/// let filter = IntermediatePathFilter::default()
+///     .include("entities".parse().unwrap())
+///     .exclude("entities[*].cache".parse().unwrap());
+/// assert_eq!(filter.test(&Default::default()), IntermediatePathMatch::Ancestor);
+/// assert_eq!(filter.test(&"entities[0].name".parse().unwrap()), IntermediatePathMatch::Matched);
+/// assert_eq!(filter.test(&"entities[0].cache".parse().unwrap()), IntermediatePathMatch::None);
+/// assert_eq!(filter.test(&"settings".parse().unwrap()), IntermediatePathMatch::None);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntermediatePathFilter {
+    /// Patterns of paths to include. Empty list includes everything.
+    pub include: Vec<IntermediatePathPattern>,
+    /// Patterns of paths to exclude.
+    pub exclude: Vec<IntermediatePathPattern>,
+}
+
+impl IntermediatePathFilter {
+    pub fn include(mut self, pattern: IntermediatePathPattern) -> Self {
+        self.include.push(pattern);
+        self
+    }
+
+    pub fn exclude(mut self, pattern: IntermediatePathPattern) -> Self {
+        self.exclude.push(pattern);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Tells if path passes filter (`Matched`), is only an ancestor of paths that might pass
+    /// filter (`Ancestor`), or is filtered out together with its subtree (`None`).
+    pub fn test(&self, path: &IntermediatePath) -> IntermediatePathMatch {
+        if self
+            .exclude
+            .iter()
+            .any(|pattern| pattern.match_path(path) == IntermediatePathMatch::Matched)
+        {
+            return IntermediatePathMatch::None;
+        }
+        if self.include.is_empty() {
+            return IntermediatePathMatch::Matched;
+        }
+        self.include
+            .iter()
+            .map(|pattern| pattern.match_path(path))
+            .max()
+            .unwrap_or(IntermediatePathMatch::None)
+    }
+}
+
+fn matches_segment(
+    expected: &IntermediatePathPatternSegment,
+    segment: &IntermediatePathSegment,
+) -> bool {
+    match (expected, segment) {
+        (IntermediatePathPatternSegment::Segment(expected), segment) => expected == segment,
+        (IntermediatePathPatternSegment::AnyField, IntermediatePathSegment::Field(_))
+        | (IntermediatePathPatternSegment::AnyIndex, IntermediatePathSegment::Index(_))
+        | (IntermediatePathPatternSegment::AnyKey, IntermediatePathSegment::Key(_)) => true,
+        _ => false,
+    }
+}
+
+fn matches_segments(
+    pattern: &[IntermediatePathPatternSegment],
+    path: &[IntermediatePathSegment],
+) -> bool {
+    match (pattern.split_first(), path.split_first()) {
+        (None, None) => true,
+        (Some((IntermediatePathPatternSegment::AnyDepth, rest)), _) => {
+            matches_segments(rest, path)
+                || (!path.is_empty() && matches_segments(pattern, &path[1..]))
+        }
+        (Some((expected, pattern)), Some((segment, path))) => {
+            matches_segment(expected, segment) && matches_segments(pattern, path)
+        }
+        _ => false,
+    }
+}
+
+fn match_segments(
+    pattern: &[IntermediatePathPatternSegment],
+    path: &[IntermediatePathSegment],
+) -> IntermediatePathMatch {
+    match (pattern.split_first(), path.split_first()) {
+        (None, _) => IntermediatePathMatch::Matched,
+        (Some((IntermediatePathPatternSegment::AnyDepth, rest)), _) => {
+            let result = match_segments(rest, path);
+            if result == IntermediatePathMatch::Matched || path.is_empty() {
+                result.max(IntermediatePathMatch::Ancestor)
+            } else {
+                result.max(match_segments(pattern, &path[1..]))
+            }
+        }
+        (Some(_), None) => IntermediatePathMatch::Ancestor,
+        (Some((expected, pattern)), Some((segment, path))) => {
+            if matches_segment(expected, segment) {
+                match_segments(pattern, path)
+            } else {
+                IntermediatePathMatch::None
+            }
+        }
+    }
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn is_identifier(content: &str) -> bool {
+    !content.is_empty() && content.chars().all(is_identifier_char)
+}
+
+fn parse_identifier(chars: &[(usize, char)], start: usize) -> (String, usize) {
+    let end = chars[start..]
+        .iter()
+        .position(|(_, c)| !is_identifier_char(*c))
+        .map(|offset| start + offset)
+        .unwrap_or(chars.len());
+    let name = chars[start..end].iter().map(|(_, c)| c).collect();
+    (name, end)
+}
+
+fn parse_string(content: &str, chars: &[(usize, char)], start: usize) -> Option<(String, usize)> {
+    let end = skip_quoted(chars, start)?;
+    let text = &content[chars[start].0..=chars[end].0];
+    let name = crate::intermediate_from_str(text)
+        .ok()?
+        .as_str()?
+        .to_owned();
+    Some((name, end + 1))
+}
+
+/// Returns index of closing quote for quoted text starting at `start`.
+fn skip_quoted(chars: &[(usize, char)], start: usize) -> Option<usize> {
+    let quote = chars.get(start)?.1;
+    let mut index = start + 1;
+    while index < chars.len() {
+        match chars[index].1 {
+            '\\' => index += 2,
+            c if c == quote => return Some(index),
+            _ => index += 1,
+        }
+    }
+    None
+}
+
+/// Returns index of brace that closes one opened at `start`.
+fn find_closing_brace(chars: &[(usize, char)], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut index = start;
+    while index < chars.len() {
+        match chars[index].1 {
+            '"' | '\'' => index = skip_quoted(chars, index)?,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+    None
+}
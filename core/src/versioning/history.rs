@@ -0,0 +1,337 @@
+use crate::{
+    error::*,
+    reflect::{PatchReport, ReflectIntermediate},
+    ser::intermediate::serialize as to_intermediate,
+    versioning::{Change, ReversibleChange},
+};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Value with history of changes applied to it, that can be undone and redone.
+///
+/// Each edit is stored as `ReversibleChange`. Edits that can't be inverted store snapshot of
+/// value from before the edit as their backward change.
+///
+/// # Example
+/// ```rust
+/// use serde::{Serialize, Deserialize};
+/// use serde_intermediate::{Change, ChangeHistory, ReflectIntermediate};
+///
+/// #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate)]
+/// struct Document {
+///     title: String,
+///     lines: Vec<String>,
+/// }
+///
+/// let mut history = ChangeHistory::new(Document::default());
+/// history
+///     .apply(&Change::partial_struct().partial_struct_item("title", Change::changed("Notes")))
+///     .unwrap();
+/// history.checkpoint("titled");
+/// history.begin_transaction();
+/// for line in ["a", "b"] {
+///     let index = history.value().lines.len();
+///     history
+///         .apply(&Change::partial_struct().partial_struct_item(
+///             "lines",
+///             Change::partial_seq().partial_seq_item(index, Change::added(line)),
+///         ))
+///         .unwrap();
+/// }
+/// history.commit_transaction();
+/// assert_eq!(history.value().lines, vec!["a".to_owned(), "b".to_owned()]);
+///
+/// history.undo().unwrap();
+/// assert!(history.value().lines.is_empty());
+/// history.redo().unwrap();
+/// assert_eq!(history.value().lines.len(), 2);
+/// history.restore_checkpoint("titled").unwrap();
+/// assert_eq!(history.value(), &Document { title: "Notes".to_owned(), lines: vec![] });
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChangeHistory<T> {
+    value: T,
+    undo: Vec<Vec<ReversibleChange>>,
+    redo: Vec<Vec<ReversibleChange>>,
+    transaction: Option<Vec<ReversibleChange>>,
+    /// Positions in history (number of edits applied since its start), under their names.
+    checkpoints: HashMap<String, usize>,
+    /// Number of oldest edits forgotten to fit in `max_bytesize`.
+    forgotten: usize,
+    max_bytesize: Option<usize>,
+}
+
+impl<T> Default for ChangeHistory<T>
+where
+    T: Default,
+{
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> ChangeHistory<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            undo: Default::default(),
+            redo: Default::default(),
+            transaction: None,
+            checkpoints: Default::default(),
+            forgotten: 0,
+            max_bytesize: None,
+        }
+    }
+
+    /// Limits total bytesize of stored edits. When limit is exceeded, edits that were undone are
+    /// forgotten first (starting with the ones furthest from current value), then oldest edits.
+    /// Edits of started transaction are never forgotten.
+    pub fn max_bytesize(mut self, bytesize: usize) -> Self {
+        self.max_bytesize = Some(bytesize);
+        self.enforce_max_bytesize();
+        self
+    }
+
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+            || self
+                .transaction
+                .as_ref()
+                .is_some_and(|edits| !edits.is_empty())
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn is_in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+
+    /// Starts grouping edits, so they get undone and redone together.
+    /// Does nothing if transaction is already started.
+    pub fn begin_transaction(&mut self) {
+        if self.transaction.is_none() {
+            self.transaction = Some(vec![]);
+        }
+    }
+
+    /// Stores edits grouped since `begin_transaction` as single history entry.
+    pub fn commit_transaction(&mut self) {
+        if let Some(edits) = self.transaction.take() {
+            if !edits.is_empty() {
+                self.undo.push(edits);
+                self.enforce_max_bytesize();
+            }
+        }
+    }
+
+    /// Names current position in history, to get back to it with `restore_checkpoint`.
+    /// Commits started transaction.
+    pub fn checkpoint(&mut self, name: impl ToString) {
+        self.commit_transaction();
+        self.checkpoints.insert(name.to_string(), self.position());
+    }
+
+    pub fn has_checkpoint(&self, name: &str) -> bool {
+        self.checkpoints.contains_key(name)
+    }
+
+    pub fn remove_checkpoint(&mut self, name: &str) -> bool {
+        self.checkpoints.remove(name).is_some()
+    }
+
+    /// Forgets all stored edits and checkpoints, keeping current value.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.transaction = None;
+        self.checkpoints.clear();
+        self.forgotten = 0;
+    }
+
+    /// Bytesize of all stored edits.
+    pub fn total_bytesize(&self) -> usize {
+        self.undo
+            .iter()
+            .chain(self.redo.iter())
+            .chain(self.transaction.iter())
+            .map(|edits| group_bytesize(edits))
+            .sum()
+    }
+
+    fn position(&self) -> usize {
+        self.forgotten + self.undo.len()
+    }
+
+    fn enforce_max_bytesize(&mut self) {
+        let max_bytesize = match self.max_bytesize {
+            Some(max_bytesize) => max_bytesize,
+            None => return,
+        };
+        let mut bytesize = self.total_bytesize();
+        let mut count = 0;
+        while bytesize > max_bytesize && count < self.redo.len() {
+            bytesize -= group_bytesize(&self.redo[count]);
+            count += 1;
+        }
+        if count > 0 {
+            self.redo.drain(0..count);
+            let reachable = self.position() + self.redo.len();
+            self.checkpoints
+                .retain(|_, position| *position <= reachable);
+        }
+        let mut count = 0;
+        while bytesize > max_bytesize && count < self.undo.len() {
+            bytesize -= group_bytesize(&self.undo[count]);
+            count += 1;
+        }
+        if count > 0 {
+            self.undo.drain(0..count);
+            self.forgotten += count;
+            let forgotten = self.forgotten;
+            self.checkpoints
+                .retain(|_, position| *position >= forgotten);
+        }
+    }
+}
+
+impl<T> ChangeHistory<T>
+where
+    T: ReflectIntermediate + Serialize,
+{
+    /// Applies change to value and stores it as new edit, forgetting edits that were undone.
+    /// Change that doesn't match value fails without changing anything, with error of its first
+    /// operation that doesn't match value - use `Change::invalid_operations` to list all of them.
+    pub fn apply(&mut self, change: &Change) -> Result<PatchReport> {
+        if change.is_same() {
+            return Ok(Default::default());
+        }
+        let source = to_intermediate(&self.value)?;
+        change.validate(&source)?;
+        let edit = ReversibleChange::new(change.to_owned(), &source).unwrap_or_else(|_| {
+            ReversibleChange {
+                forward: change.to_owned(),
+                backward: Change::Changed(source.to_owned()),
+            }
+        });
+        let report = match patch(&mut self.value, &edit.forward) {
+            Ok(report) => report,
+            Err(error) => {
+                self.value.patch_change(&Change::Changed(source));
+                return Err(error);
+            }
+        };
+        self.redo.clear();
+        let position = self.position();
+        self.checkpoints
+            .retain(|_, checkpoint| *checkpoint <= position);
+        match &mut self.transaction {
+            Some(edits) => edits.push(edit),
+            None => self.undo.push(vec![edit]),
+        }
+        self.enforce_max_bytesize();
+        Ok(report)
+    }
+
+    /// Undoes last edit (or group of edits made in transaction), committing started transaction.
+    /// Returns false if there is nothing to undo. Group that fails to be undone is kept in
+    /// history, with value left unchanged.
+    pub fn undo(&mut self) -> Result<bool> {
+        self.commit_transaction();
+        let edits = match self.undo.pop() {
+            Some(edits) => edits,
+            None => return Ok(false),
+        };
+        if let Err(error) = self.patch_all(edits.iter().rev().map(|edit| &edit.backward)) {
+            self.undo.push(edits);
+            return Err(error);
+        }
+        self.redo.push(edits);
+        Ok(true)
+    }
+
+    /// Redoes last undone edit (or group of edits made in transaction).
+    /// Returns false if there is nothing to redo. Group that fails to be redone is kept in
+    /// history, with value left unchanged.
+    pub fn redo(&mut self) -> Result<bool> {
+        self.commit_transaction();
+        let edits = match self.redo.pop() {
+            Some(edits) => edits,
+            None => return Ok(false),
+        };
+        if let Err(error) = self.patch_all(edits.iter().map(|edit| &edit.forward)) {
+            self.redo.push(edits);
+            return Err(error);
+        }
+        self.undo.push(edits);
+        Ok(true)
+    }
+
+    /// Undoes edits made in started transaction and ends it. Transaction that fails to be
+    /// undone is kept started, with value left unchanged.
+    pub fn cancel_transaction(&mut self) -> Result<()> {
+        if let Some(edits) = self.transaction.take() {
+            if let Err(error) = self.patch_all(edits.iter().rev().map(|edit| &edit.backward)) {
+                self.transaction = Some(edits);
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    /// Undoes or redoes edits until value gets back to state named by `checkpoint`.
+    pub fn restore_checkpoint(&mut self, name: &str) -> Result<()> {
+        self.commit_transaction();
+        let target = match self.checkpoints.get(name) {
+            Some(position) => *position,
+            None => return Err(Error::UnknownCheckpoint(name.to_owned())),
+        };
+        while self.position() > target {
+            self.undo()?;
+        }
+        while self.position() < target {
+            if !self.redo()? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Patches changes into value in order, restoring value from before the first of them when
+    /// any of them fails, so value is never left half-patched.
+    fn patch_all<'a>(&mut self, changes: impl IntoIterator<Item = &'a Change>) -> Result<()> {
+        let source = to_intermediate(&self.value)?;
+        for change in changes {
+            if let Err(error) = patch(&mut self.value, change) {
+                self.value.patch_change(&Change::Changed(source));
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn group_bytesize(edits: &[ReversibleChange]) -> usize {
+    edits.iter().map(|edit| edit.total_bytesize()).sum()
+}
+
+/// Patches change into value, failing with first rejection.
+fn patch<T>(value: &mut T, change: &Change) -> Result<PatchReport>
+where
+    T: ReflectIntermediate,
+{
+    let report = value.try_patch_change(change);
+    match report.rejected.first() {
+        Some((_, error)) => Err(error.to_owned()),
+        None => Ok(report),
+    }
+}
@@ -0,0 +1,495 @@
+use crate::{
+    error::*,
+    value::{
+        intermediate::Intermediate,
+        path::{IntermediatePath, IntermediatePathSegment},
+    },
+    versioning::Change,
+};
+use serde::{Deserialize, Serialize};
+
+/// Single JSON Patch (RFC 6902) operation.
+///
+/// Paths are JSON Pointers (RFC 6901). Enums are assumed to use default (externally tagged)
+/// representation, so variant name is a part of the path to its content.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum JsonPatchOperation {
+    Add { path: String, value: Intermediate },
+    Remove { path: String },
+    Replace { path: String, value: Intermediate },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Intermediate },
+}
+
+impl JsonPatchOperation {
+    /// Name of operation as used in `op` field.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Add { .. } => "add",
+            Self::Remove { .. } => "remove",
+            Self::Replace { .. } => "replace",
+            Self::Move { .. } => "move",
+            Self::Copy { .. } => "copy",
+            Self::Test { .. } => "test",
+        }
+    }
+
+    /// Converts operation into change of `value`.
+    ///
+    /// `copy` and `test` operations cannot be expressed as change and report
+    /// `Error::UnsupportedPatchOperation`.
+    pub fn to_change(&self, value: &Intermediate) -> Result<Change> {
+        match self {
+            Self::Add { path, value: item } => {
+                if path.is_empty() {
+                    return Ok(Change::Changed(item.to_owned()));
+                }
+                let path = resolve_pointer(value, path, true)?;
+                let item = conform_option(item, target_sample(value, &path));
+                match (path.last(), value.get(&path)) {
+                    (Some(IntermediatePathSegment::Inner), _)
+                    | (
+                        Some(IntermediatePathSegment::Field(_) | IntermediatePathSegment::Key(_)),
+                        Some(_),
+                    ) => Ok(Change::at_path(&path, Change::Changed(item))),
+                    _ => Ok(Change::at_path(&path, Change::Added(item))),
+                }
+            }
+            Self::Remove { path } => {
+                if path.is_empty() {
+                    return Err(Error::CannotRemove(value.to_owned()));
+                }
+                let path = resolve_existing_pointer(value, path)?;
+                match path.last() {
+                    Some(IntermediatePathSegment::Inner) => {
+                        Err(Error::CannotRemove(value.to_owned()))
+                    }
+                    _ => Ok(Change::at_path(&path, Change::Removed)),
+                }
+            }
+            Self::Replace { path, value: item } => {
+                if path.is_empty() {
+                    return Ok(Change::Changed(item.to_owned()));
+                }
+                let path = resolve_existing_pointer(value, path)?;
+                let item = conform_option(item, value.get(&path));
+                Ok(Change::at_path(&path, Change::Changed(item)))
+            }
+            Self::Move { from, path } => {
+                if from == path {
+                    return Ok(Change::Same);
+                }
+                let source = resolve_existing_pointer(value, from)?;
+                let target = resolve_pointer(value, path, true)?;
+                if let (
+                    Some(parent),
+                    Some(IntermediatePathSegment::Index(from)),
+                    Some(IntermediatePathSegment::Index(to)),
+                ) = (source.parent(), source.last(), target.last())
+                {
+                    let len = value
+                        .get(&parent)
+                        .and_then(sequence_len)
+                        .unwrap_or_default();
+                    if target.parent().as_ref() == Some(&parent) && len > 0 {
+                        let to = (*to).min(len - 1);
+                        return Ok(Change::at_path(
+                            &parent,
+                            Change::PartialSeq(vec![(*from, Change::Moved(to))]),
+                        ));
+                    }
+                }
+                let item = value
+                    .get(&source)
+                    .ok_or_else(|| Error::PathNotFound(source.to_owned()))?
+                    .to_owned();
+                let remove = Self::Remove {
+                    path: from.to_owned(),
+                }
+                .to_change(value)?;
+                let removed = remove
+                    .patch(value)?
+                    .ok_or_else(|| Error::CannotRemove(value.to_owned()))?;
+                let add = Self::Add {
+                    path: path.to_owned(),
+                    value: item,
+                }
+                .to_change(&removed)?;
+                remove.compose(&add)
+            }
+            Self::Copy { .. } | Self::Test { .. } => {
+                Err(Error::UnsupportedPatchOperation(self.name().to_owned()))
+            }
+        }
+    }
+}
+
+impl Change {
+    /// Converts change of `base` value into list of JSON Patch (RFC 6902) operations.
+    ///
+    /// # Example
+    /// ```rust
+    /// use serde_intermediate::{Change, Intermediate, JsonPatchOperation};
+    ///
+    /// let prev = Intermediate::struct_type().field("a", 1).field("b", Intermediate::seq().item(1));
+    /// let next = Intermediate::struct_type().field("a", 2).field("b", Intermediate::seq());
+    /// let change = Change::difference(&prev, &next, &Default::default());
+    /// let operations = change.to_json_patch(&prev).unwrap();
+    /// assert_eq!(
+    ///     operations,
+    ///     vec![
+    ///         JsonPatchOperation::Replace { path: "/a".to_owned(), value: 2.into() },
+    ///         JsonPatchOperation::Remove { path: "/b/0".to_owned() },
+    ///     ]
+    /// );
+    /// let change = Change::from_json_patch(&prev, &operations).unwrap();
+    /// assert_eq!(change.patch(&prev).unwrap().unwrap(), next);
+    /// ```
+    pub fn to_json_patch(&self, base: &Intermediate) -> Result<Vec<JsonPatchOperation>> {
+        let mut result = vec![];
+        export(self, base, "", &mut result)?;
+        Ok(result)
+    }
+
+    /// Converts list of JSON Patch (RFC 6902) operations into change of `base` value.
+    ///
+    /// Values put in place of optional values are wrapped in `Option`, with `null` (unit)
+    /// becoming `None`. Map keys are read as keys of the same type as keys already in map.
+    pub fn from_json_patch(base: &Intermediate, operations: &[JsonPatchOperation]) -> Result<Self> {
+        let mut value = base.to_owned();
+        let mut result = Self::Same;
+        for operation in operations {
+            let change = operation.to_change(&value)?;
+            value = change
+                .patch(&value)?
+                .ok_or_else(|| Error::CannotRemove(value.to_owned()))?;
+            result = result.compose(&change)?;
+        }
+        Ok(result)
+    }
+}
+
+fn export(
+    change: &Change,
+    value: &Intermediate,
+    pointer: &str,
+    result: &mut Vec<JsonPatchOperation>,
+) -> Result<()> {
+    match (change, value) {
+        (Change::Same, _) => {}
+        (Change::Removed, _) => result.push(JsonPatchOperation::Remove {
+            path: pointer.to_owned(),
+        }),
+        (Change::Changed(v), _) => result.push(JsonPatchOperation::Replace {
+            path: pointer.to_owned(),
+            value: v.to_owned(),
+        }),
+        (Change::Added(v), _) => result.push(JsonPatchOperation::Add {
+            path: pointer.to_owned(),
+            value: v.to_owned(),
+        }),
+        (Change::Moved(_), _) => return Err(Error::NotSeq(value.to_owned())),
+        (
+            Change::PartialChange(change),
+            Intermediate::Option(Some(v)) | Intermediate::NewTypeStruct(v),
+        ) => export(change, v, pointer, result)?,
+        (Change::PartialChange(change), Intermediate::NewTypeVariant(n, v)) => {
+            export(change, v, &pointer_join(pointer, n), result)?
+        }
+        (Change::PartialChange(_), _) => return Err(Error::NotPartial(value.to_owned())),
+        (
+            Change::PartialSeq(changes),
+            Intermediate::Seq(v) | Intermediate::Tuple(v) | Intermediate::TupleStruct(v),
+        ) => {
+            let mut items = v.to_owned();
+            for (index, change) in changes {
+                let index = *index;
+                let path = pointer_join(pointer, &index.to_string());
+                let not_found = || Error::InvalidPath(path.to_owned());
+                match change {
+                    Change::Same => {}
+                    Change::Removed => {
+                        if index >= items.len() {
+                            return Err(not_found());
+                        }
+                        items.remove(index);
+                        result.push(JsonPatchOperation::Remove { path });
+                    }
+                    Change::Added(v) => {
+                        if index > items.len() {
+                            return Err(not_found());
+                        }
+                        items.insert(index, v.to_owned());
+                        result.push(JsonPatchOperation::Add {
+                            path,
+                            value: v.to_owned(),
+                        });
+                    }
+                    Change::Moved(to) => {
+                        if index >= items.len() || *to >= items.len() {
+                            return Err(not_found());
+                        }
+                        let item = items.remove(index);
+                        items.insert(*to, item);
+                        result.push(JsonPatchOperation::Move {
+                            from: path,
+                            path: pointer_join(pointer, &to.to_string()),
+                        });
+                    }
+                    change => {
+                        let item = items.get_mut(index).ok_or_else(not_found)?;
+                        export(change, item, &path, result)?;
+                        if let Some(patched) = change.patch(item)? {
+                            *item = patched;
+                        }
+                    }
+                }
+            }
+        }
+        (Change::PartialSeq(_), Intermediate::TupleVariant(n, v)) => export(
+            change,
+            &Intermediate::Tuple(v.to_owned()),
+            &pointer_join(pointer, n),
+            result,
+        )?,
+        (Change::PartialSeq(_), _) => return Err(Error::NotSeq(value.to_owned())),
+        (Change::PartialMap(changes), Intermediate::Map(v)) => {
+            for (key, change) in changes {
+                let path = pointer_join(pointer, &key_token(key)?);
+                let item = v.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+                export_entry(change, item, &path, result)?;
+            }
+        }
+        (Change::PartialMap(_), _) => return Err(Error::NotMap(value.to_owned())),
+        (Change::PartialStruct(changes), Intermediate::Struct(v)) => {
+            for (name, change) in changes {
+                let path = pointer_join(pointer, name);
+                let item = v.iter().find(|(k, _)| k == name).map(|(_, v)| v);
+                export_entry(change, item, &path, result)?;
+            }
+        }
+        (Change::PartialStruct(changes), Intermediate::StructVariant(n, v)) => {
+            let pointer = pointer_join(pointer, n);
+            for (name, change) in changes {
+                let path = pointer_join(&pointer, name);
+                let item = v.iter().find(|(k, _)| k == name).map(|(_, v)| v);
+                export_entry(change, item, &path, result)?;
+            }
+        }
+        (Change::PartialStruct(_), _) => return Err(Error::NotStruct(value.to_owned())),
+    }
+    Ok(())
+}
+
+/// Changes of missing map entries or struct fields are ignored (except additions),
+/// same as when patching.
+fn export_entry(
+    change: &Change,
+    value: Option<&Intermediate>,
+    pointer: &str,
+    result: &mut Vec<JsonPatchOperation>,
+) -> Result<()> {
+    match (change, value) {
+        (Change::Added(v), _) => {
+            result.push(JsonPatchOperation::Add {
+                path: pointer.to_owned(),
+                value: v.to_owned(),
+            });
+            Ok(())
+        }
+        (change, Some(value)) => export(change, value, pointer, result),
+        _ => Ok(()),
+    }
+}
+
+fn pointer_join(pointer: &str, token: &str) -> String {
+    format!(
+        "{}/{}",
+        pointer,
+        token.replace('~', "~0").replace('/', "~1")
+    )
+}
+
+fn pointer_tokens(pointer: &str) -> Result<Vec<String>> {
+    match pointer.strip_prefix('/') {
+        Some(pointer) => Ok(pointer
+            .split('/')
+            .map(|token| token.replace("~1", "/").replace("~0", "~"))
+            .collect()),
+        None if pointer.is_empty() => Ok(vec![]),
+        None => Err(Error::InvalidPath(pointer.to_owned())),
+    }
+}
+
+/// Token of map key in JSON Pointer: keys are written the same way JSON object keys are.
+pub(crate) fn key_token(key: &Intermediate) -> Result<String> {
+    match key {
+        Intermediate::String(v) | Intermediate::UnitVariant(v) => Ok(v.to_owned()),
+        Intermediate::Bool(v) => Ok(v.to_string()),
+        Intermediate::I8(v) => Ok(v.to_string()),
+        Intermediate::I16(v) => Ok(v.to_string()),
+        Intermediate::I32(v) => Ok(v.to_string()),
+        Intermediate::I64(v) => Ok(v.to_string()),
+        Intermediate::I128(v) => Ok(v.to_string()),
+        Intermediate::U8(v) => Ok(v.to_string()),
+        Intermediate::U16(v) => Ok(v.to_string()),
+        Intermediate::U32(v) => Ok(v.to_string()),
+        Intermediate::U64(v) => Ok(v.to_string()),
+        Intermediate::U128(v) => Ok(v.to_string()),
+        Intermediate::Char(v) => Ok(v.to_string()),
+        _ => Err(Error::InvalidPath(key.to_string())),
+    }
+}
+
+/// Creates key of new map entry, of the same kind as `sample` key.
+pub(crate) fn key_from_token(token: &str, sample: Option<&Intermediate>) -> Result<Intermediate> {
+    let result = match sample {
+        None | Some(Intermediate::String(_)) => Some(Intermediate::String(token.to_owned())),
+        Some(Intermediate::UnitVariant(_)) => Some(Intermediate::UnitVariant(token.to_owned())),
+        Some(Intermediate::Bool(_)) => token.parse().ok().map(Intermediate::Bool),
+        Some(Intermediate::I8(_)) => token.parse().ok().map(Intermediate::I8),
+        Some(Intermediate::I16(_)) => token.parse().ok().map(Intermediate::I16),
+        Some(Intermediate::I32(_)) => token.parse().ok().map(Intermediate::I32),
+        Some(Intermediate::I64(_)) => token.parse().ok().map(Intermediate::I64),
+        Some(Intermediate::I128(_)) => token.parse().ok().map(Intermediate::I128),
+        Some(Intermediate::U8(_)) => token.parse().ok().map(Intermediate::U8),
+        Some(Intermediate::U16(_)) => token.parse().ok().map(Intermediate::U16),
+        Some(Intermediate::U32(_)) => token.parse().ok().map(Intermediate::U32),
+        Some(Intermediate::U64(_)) => token.parse().ok().map(Intermediate::U64),
+        Some(Intermediate::U128(_)) => token.parse().ok().map(Intermediate::U128),
+        Some(Intermediate::Char(_)) => token.parse().ok().map(Intermediate::Char),
+        Some(_) => None,
+    };
+    result.ok_or_else(|| Error::InvalidPath(token.to_owned()))
+}
+
+/// Puts value in `Option` when it replaces or joins optional values, as JSON doesn't
+/// distinguish optional values from their content and `null` from unit.
+pub(crate) fn conform_option(item: &Intermediate, sample: Option<&Intermediate>) -> Intermediate {
+    match (sample, item) {
+        (Some(Intermediate::Option(_)), Intermediate::Unit) => Intermediate::Option(None),
+        (Some(Intermediate::Option(_)), item) if !matches!(item, Intermediate::Option(_)) => {
+            Intermediate::Option(Some(Box::new(item.to_owned())))
+        }
+        (_, item) => item.to_owned(),
+    }
+}
+
+/// Value at `path`, or when it's missing, its future sibling in parent sequence or map.
+fn target_sample<'a>(value: &'a Intermediate, path: &IntermediatePath) -> Option<&'a Intermediate> {
+    if let Some(result) = value.get(path) {
+        return Some(result);
+    }
+    match value.get(&path.parent()?)? {
+        Intermediate::Seq(v) => v.first(),
+        Intermediate::Map(v) => v.first().map(|(_, v)| v),
+        _ => None,
+    }
+}
+
+fn sequence_len(value: &Intermediate) -> Option<usize> {
+    match value {
+        Intermediate::Seq(v) | Intermediate::Tuple(v) | Intermediate::TupleStruct(v) => {
+            Some(v.len())
+        }
+        _ => None,
+    }
+}
+
+fn resolve_existing_pointer(value: &Intermediate, pointer: &str) -> Result<IntermediatePath> {
+    let path = resolve_pointer(value, pointer, false)?;
+    if value.get(&path).is_some() {
+        Ok(path)
+    } else {
+        Err(Error::PathNotFound(path))
+    }
+}
+
+/// Turns JSON Pointer into path of `value`.
+/// With `create` last token can point to new sequence item, map entry or struct field.
+fn resolve_pointer(value: &Intermediate, pointer: &str, create: bool) -> Result<IntermediatePath> {
+    let tokens = pointer_tokens(pointer)?;
+    let mut result = IntermediatePath::default();
+    let mut current = value;
+    // Whether variant name of current value was already consumed.
+    let mut entered = false;
+    let mut index = 0;
+    while index < tokens.len() {
+        let token = tokens[index].as_str();
+        let last = index + 1 == tokens.len();
+        match current {
+            Intermediate::Option(Some(v)) | Intermediate::NewTypeStruct(v) => {
+                result.push(IntermediatePathSegment::Inner);
+                current = v;
+                continue;
+            }
+            Intermediate::NewTypeVariant(n, v) if n == token => {
+                result.push(IntermediatePathSegment::Inner);
+                current = v;
+            }
+            Intermediate::TupleVariant(n, _) | Intermediate::StructVariant(n, _)
+                if !entered && n == token =>
+            {
+                entered = true;
+                index += 1;
+                continue;
+            }
+            Intermediate::Seq(v)
+            | Intermediate::Tuple(v)
+            | Intermediate::TupleStruct(v)
+            | Intermediate::TupleVariant(_, v) => {
+                let item = if token == "-" && last && create {
+                    v.len()
+                } else {
+                    token
+                        .parse::<usize>()
+                        .map_err(|_| Error::InvalidPath(pointer.to_owned()))?
+                };
+                result.push(IntermediatePathSegment::Index(item));
+                match v.get(item) {
+                    Some(v) => current = v,
+                    None if last && create && item == v.len() => {}
+                    None => return Err(Error::PathNotFound(result)),
+                }
+            }
+            Intermediate::Map(v) => {
+                let found = v.iter().find(|(k, _)| {
+                    key_token(k)
+                        .map(|k| k.as_str() == token)
+                        .unwrap_or_default()
+                });
+                match found {
+                    Some((k, v)) => {
+                        result.push(IntermediatePathSegment::Key(k.to_owned()));
+                        current = v;
+                    }
+                    None if last && create => {
+                        let key = key_from_token(token, v.first().map(|(k, _)| k))?;
+                        result.push(IntermediatePathSegment::Key(key));
+                    }
+                    None => {
+                        result.push(IntermediatePathSegment::Field(token.to_owned()));
+                        return Err(Error::PathNotFound(result));
+                    }
+                }
+            }
+            Intermediate::Struct(v) | Intermediate::StructVariant(_, v) => {
+                result.push(IntermediatePathSegment::Field(token.to_owned()));
+                match v.iter().find(|(k, _)| k == token) {
+                    Some((_, v)) => current = v,
+                    None if last && create => {}
+                    None => return Err(Error::PathNotFound(result)),
+                }
+            }
+            _ => {
+                result.push(IntermediatePathSegment::Field(token.to_owned()));
+                return Err(Error::PathNotFound(result));
+            }
+        }
+        entered = false;
+        index += 1;
+    }
+    Ok(result)
+}
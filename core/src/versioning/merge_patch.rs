@@ -0,0 +1,292 @@
+use crate::{
+    error::*,
+    value::intermediate::Intermediate,
+    versioning::{
+        json_patch::{conform_option, key_from_token, key_token},
+        Change,
+    },
+};
+use std::collections::HashMap;
+
+impl Change {
+    /// Converts JSON Merge Patch (RFC 7386) document into change of `target` value.
+    ///
+    /// Maps and structures are treated as objects, `Intermediate::Unit` and
+    /// `Intermediate::Option(None)` are treated as `null`. `null` removes map entry, while for
+    /// structure field holding `Option` it sets field to `None`, so structure keeps its layout.
+    /// Values merged into `Option` values are wrapped in `Option` the same way. Map keys of
+    /// other types than strings are read from their text, like JSON object keys. Newtype and
+    /// struct variants are objects with single member named after variant, like in JSON, and
+    /// member named after other variant replaces the whole value.
+    ///
+    /// # Example
+    /// ```rust
+    /// use serde_intermediate::{Change, Intermediate};
+    ///
+    /// let target = Intermediate::map().property("a", 1).property("b", Intermediate::map().property("c", 2));
+    /// let patch = Intermediate::map()
+    ///     .property("a", Intermediate::Unit)
+    ///     .property("b", Intermediate::map().property("d", 3));
+    /// let change = Change::from_merge_patch(&target, &patch).unwrap();
+    /// assert_eq!(
+    ///     change.patch(&target).unwrap().unwrap(),
+    ///     Intermediate::map().property("b", Intermediate::map().property("c", 2).property("d", 3)),
+    /// );
+    /// ```
+    pub fn from_merge_patch(target: &Intermediate, patch: &Intermediate) -> Result<Self> {
+        match (target, patch) {
+            (_, Intermediate::Option(Some(patch))) => Self::from_merge_patch(target, patch),
+            (Intermediate::Option(None), patch) if is_null(patch) => Ok(Self::Same),
+            (Intermediate::Option(_), patch) if is_null(patch) => {
+                Ok(Self::Changed(Intermediate::Option(None)))
+            }
+            (Intermediate::Option(Some(target)), patch) => {
+                match Self::from_merge_patch(target, patch)? {
+                    Self::Same => Ok(Self::Same),
+                    change => Ok(Self::PartialChange(Box::new(change))),
+                }
+            }
+            (Intermediate::Option(None), patch) => Ok(Self::Changed(Intermediate::Option(Some(
+                Box::new(merge_patch_value(patch)),
+            )))),
+            (
+                Intermediate::NewTypeStruct(target),
+                Intermediate::Map(_) | Intermediate::Struct(_),
+            ) => Ok(Self::PartialChange(Box::new(Self::from_merge_patch(
+                target, patch,
+            )?))),
+            (Intermediate::Map(target), Intermediate::Map(_) | Intermediate::Struct(_)) => {
+                let mut result = vec![];
+                for (key, value) in object_entries(patch) {
+                    let found = target.iter().find(|(k, _)| match &key {
+                        Intermediate::String(key) if !matches!(k, Intermediate::String(_)) => {
+                            key_token(k).map(|k| &k == key).unwrap_or_default()
+                        }
+                        key => k == key,
+                    });
+                    let change = match (found, is_null(value)) {
+                        (Some((key, _)), true) => Some((key.to_owned(), Self::Removed)),
+                        (Some((key, item)), false) => {
+                            Some((key.to_owned(), Self::from_merge_patch(item, value)?))
+                        }
+                        (None, true) => None,
+                        (None, false) => {
+                            let key = match key {
+                                Intermediate::String(key) => {
+                                    key_from_token(&key, target.first().map(|(k, _)| k))?
+                                }
+                                key => key,
+                            };
+                            let value = conform_option(
+                                &merge_patch_value(value),
+                                target.first().map(|(_, v)| v),
+                            );
+                            Some((key, Self::Added(value)))
+                        }
+                    };
+                    if let Some(change) = change.filter(|(_, change)| !change.is_same()) {
+                        result.push(change);
+                    }
+                }
+                Ok(Self::PartialMap(result))
+            }
+            (
+                Intermediate::NewTypeVariant(name, _) | Intermediate::StructVariant(name, _),
+                Intermediate::Map(_) | Intermediate::Struct(_),
+            ) => {
+                // Like in JSON, variant is an object with single member named after it.
+                let entries = object_entries(patch);
+                let (key, value) = match entries.as_slice() {
+                    [(Intermediate::String(key), value)] => (key, *value),
+                    _ => return Ok(Self::Changed(merge_patch_value(patch))),
+                };
+                match target {
+                    _ if key != name => Ok(Self::Changed(variant_value(key, value))),
+                    Intermediate::NewTypeVariant(_, target) => Ok(Self::PartialChange(Box::new(
+                        Self::from_merge_patch(target, value)?,
+                    ))),
+                    Intermediate::StructVariant(_, target)
+                        if matches!(value, Intermediate::Map(_) | Intermediate::Struct(_)) =>
+                    {
+                        Self::from_merge_patch(&Intermediate::Struct(target.to_owned()), value)
+                    }
+                    _ => Ok(Self::Changed(variant_value(key, value))),
+                }
+            }
+            (Intermediate::Struct(target), Intermediate::Map(_) | Intermediate::Struct(_)) => {
+                let mut result = vec![];
+                for (key, value) in object_entries(patch) {
+                    let name = key_token(&key)?;
+                    let found = target.iter().find(|(k, _)| k == &name);
+                    let change = match (found, is_null(value)) {
+                        (Some((_, Intermediate::Option(Some(_)))), true) => {
+                            Some(Self::Changed(Intermediate::Option(None)))
+                        }
+                        (Some((_, item)), true) if is_null(item) => None,
+                        (Some(_), true) => Some(Self::Removed),
+                        (Some((_, item)), false) => Some(Self::from_merge_patch(item, value)?),
+                        (None, true) => None,
+                        (None, false) => Some(Self::Added(merge_patch_value(value))),
+                    };
+                    if let Some(change) = change.filter(|change| !change.is_same()) {
+                        result.push((name, change));
+                    }
+                }
+                Ok(Self::PartialStruct(result))
+            }
+            (_, Intermediate::Map(_) | Intermediate::Struct(_)) => {
+                Ok(Self::Changed(merge_patch_value(patch)))
+            }
+            (target, patch) if target == patch => Ok(Self::Same),
+            (_, patch) => Ok(Self::Changed(patch.to_owned())),
+        }
+    }
+
+    /// Converts change of `base` value into JSON Merge Patch (RFC 7386) document.
+    ///
+    /// Removed map entries and structure fields are marked with `Intermediate::Unit` (`null`).
+    /// Sequences are always replaced as a whole, as merge patch can't describe their changes.
+    ///
+    /// # Example
+    /// ```rust
+    /// use serde_intermediate::{Change, Intermediate};
+    ///
+    /// let prev = Intermediate::map().property("a", 1).property("b", 2);
+    /// let next = Intermediate::map().property("a", 1).property("c", 3);
+    /// let change = Change::difference(&prev, &next, &Default::default());
+    /// assert_eq!(
+    ///     change.to_merge_patch(&prev).unwrap(),
+    ///     Intermediate::map().property("c", 3).property("b", Intermediate::Unit),
+    /// );
+    /// ```
+    pub fn to_merge_patch(&self, base: &Intermediate) -> Result<Intermediate> {
+        match self.patch(base)? {
+            Some(patched) => Ok(merge_patch_difference(base, &patched)),
+            None => Ok(Intermediate::Unit),
+        }
+    }
+}
+
+fn is_null(value: &Intermediate) -> bool {
+    matches!(value, Intermediate::Unit | Intermediate::Option(None))
+}
+
+fn object_entries(value: &Intermediate) -> Vec<(Intermediate, &Intermediate)> {
+    match value {
+        Intermediate::Map(v) => v.iter().map(|(k, v)| (k.to_owned(), v)).collect(),
+        Intermediate::Struct(v) => v
+            .iter()
+            .map(|(k, v)| (Intermediate::String(k.to_owned()), v))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Value of merge patch applied to nothing, which is patch without `null` object members.
+fn merge_patch_value(patch: &Intermediate) -> Intermediate {
+    match patch {
+        Intermediate::Map(v) => Intermediate::Map(
+            v.iter()
+                .filter(|(_, v)| !is_null(v))
+                .map(|(k, v)| (k.to_owned(), merge_patch_value(v)))
+                .collect(),
+        ),
+        Intermediate::Struct(v) => Intermediate::Struct(
+            v.iter()
+                .filter(|(_, v)| !is_null(v))
+                .map(|(k, v)| (k.to_owned(), merge_patch_value(v)))
+                .collect(),
+        ),
+        patch => patch.to_owned(),
+    }
+}
+
+/// Value of enum variant described by merge patch member: objects with text keys describe
+/// struct variants, other values describe newtype variants.
+fn variant_value(name: &str, value: &Intermediate) -> Intermediate {
+    match merge_patch_value(value) {
+        Intermediate::Struct(fields) => Intermediate::StructVariant(name.to_owned(), fields),
+        Intermediate::Map(entries)
+            if entries
+                .iter()
+                .all(|(key, _)| matches!(key, Intermediate::String(_))) =>
+        {
+            let fields = entries
+                .into_iter()
+                .filter_map(|(key, value)| match key {
+                    Intermediate::String(key) => Some((key, value)),
+                    _ => None,
+                })
+                .collect();
+            Intermediate::StructVariant(name.to_owned(), fields)
+        }
+        value => Intermediate::NewTypeVariant(name.to_owned(), Box::new(value)),
+    }
+}
+
+fn merge_patch_difference(prev: &Intermediate, next: &Intermediate) -> Intermediate {
+    match (prev, next) {
+        (Intermediate::Option(Some(prev)), Intermediate::Option(Some(next)))
+        | (Intermediate::NewTypeStruct(prev), Intermediate::NewTypeStruct(next)) => {
+            merge_patch_difference(prev, next)
+        }
+        (
+            Intermediate::NewTypeVariant(prev_name, prev),
+            Intermediate::NewTypeVariant(next_name, next),
+        ) if prev_name == next_name => Intermediate::Map(vec![(
+            Intermediate::String(next_name.to_owned()),
+            merge_patch_difference(prev, next),
+        )]),
+        (Intermediate::Map(prev), Intermediate::Map(next)) => {
+            Intermediate::Map(entries_difference(prev, next))
+        }
+        (Intermediate::Struct(prev), Intermediate::Struct(next)) => {
+            Intermediate::Map(fields_difference(prev, next))
+        }
+        (
+            Intermediate::StructVariant(prev_name, prev),
+            Intermediate::StructVariant(next_name, next),
+        ) if prev_name == next_name => Intermediate::Map(vec![(
+            Intermediate::String(next_name.to_owned()),
+            Intermediate::Map(fields_difference(prev, next)),
+        )]),
+        (_, next) => next.to_owned(),
+    }
+}
+
+fn entries_difference(
+    prev: &[(Intermediate, Intermediate)],
+    next: &[(Intermediate, Intermediate)],
+) -> Vec<(Intermediate, Intermediate)> {
+    let lookup = prev.iter().map(|(k, v)| (k, v)).collect::<HashMap<_, _>>();
+    let mut result = vec![];
+    for (key, value) in next {
+        match lookup.get(key) {
+            Some(item) if *item == value => {}
+            Some(item) => result.push((key.to_owned(), merge_patch_difference(item, value))),
+            None => result.push((key.to_owned(), value.to_owned())),
+        }
+    }
+    let lookup = next.iter().map(|(k, v)| (k, v)).collect::<HashMap<_, _>>();
+    for (key, _) in prev {
+        if !lookup.contains_key(key) {
+            result.push((key.to_owned(), Intermediate::Unit));
+        }
+    }
+    result
+}
+
+fn fields_difference(
+    prev: &[(String, Intermediate)],
+    next: &[(String, Intermediate)],
+) -> Vec<(Intermediate, Intermediate)> {
+    let prev = prev
+        .iter()
+        .map(|(k, v)| (Intermediate::String(k.to_owned()), v.to_owned()))
+        .collect::<Vec<_>>();
+    let next = next
+        .iter()
+        .map(|(k, v)| (Intermediate::String(k.to_owned()), v.to_owned()))
+        .collect::<Vec<_>>();
+    entries_difference(&prev, &next)
+}
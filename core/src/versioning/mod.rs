@@ -0,0 +1,2551 @@
+pub mod history;
+pub mod json_patch;
+pub mod merge_patch;
+pub mod tracked;
+
+use crate::{
+    error::*,
+    value::{
+        intermediate::Intermediate,
+        path::{
+            IntermediatePath, IntermediatePathFilter, IntermediatePathMatch,
+            IntermediatePathPattern, IntermediatePathSegment,
+        },
+    },
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+};
+
+/// Optimization hint used in calculating change between two intermediate data.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum DiffOptimizationHint {
+    /// Don't assume any optimization.
+    #[default]
+    Default,
+    /// Put entire new object if change information size is greater than source (old) size.
+    SizeSource,
+    /// Put entire new object if change information size is greater than target (new) size.
+    SizeTarget,
+    /// Put entire new object if change information size is greater than bytesize threshold.
+    SizeValue(
+        /// Bytesize threshold.
+        usize,
+    ),
+    /// Put entire new object if change information size is greater than percentage of source size.
+    SizePercentage(
+        /// Percentage threshold.
+        f64,
+    ),
+}
+
+/// Function extracting identity key from sequence item.
+pub type SequenceKeyExtractor = Arc<dyn Fn(&Intermediate) -> Option<Intermediate> + Send + Sync>;
+
+/// Identity of sequence items, used to match them between versions of sequence.
+#[derive(Clone)]
+pub enum SequenceKey {
+    /// Value of structure field (or map entry under string key).
+    Field(String),
+    /// Custom key extractor.
+    Extractor(SequenceKeyExtractor),
+}
+
+impl SequenceKey {
+    pub fn field(name: impl ToString) -> Self {
+        Self::Field(name.to_string())
+    }
+
+    pub fn extractor<F>(f: F) -> Self
+    where
+        F: Fn(&Intermediate) -> Option<Intermediate> + Send + Sync + 'static,
+    {
+        Self::Extractor(Arc::new(f))
+    }
+
+    pub fn extract(&self, value: &Intermediate) -> Option<Intermediate> {
+        match self {
+            Self::Field(name) => match value {
+                Intermediate::Struct(v) | Intermediate::StructVariant(_, v) => {
+                    v.iter().find(|(k, _)| k == name).map(|(_, v)| v.to_owned())
+                }
+                Intermediate::Map(v) => v
+                    .iter()
+                    .find(|(k, _)| k.as_str() == Some(name))
+                    .map(|(_, v)| v.to_owned()),
+                _ => None,
+            },
+            Self::Extractor(f) => f(value),
+        }
+    }
+}
+
+impl std::fmt::Debug for SequenceKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Field(name) => f.debug_tuple("Field").field(name).finish(),
+            Self::Extractor(_) => f.debug_tuple("Extractor").finish_non_exhaustive(),
+        }
+    }
+}
+
+impl PartialEq for SequenceKey {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Field(a), Self::Field(b)) => a == b,
+            (Self::Extractor(a), Self::Extractor(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// Tolerance used when comparing floats.
+///
+/// Floats are same when difference between them is not greater than `absolute`, or not greater
+/// than `relative` fraction of greater of their magnitudes.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct FloatTolerance {
+    /// Absolute epsilon.
+    pub absolute: f64,
+    /// Relative epsilon.
+    pub relative: f64,
+}
+
+impl FloatTolerance {
+    pub fn absolute(epsilon: f64) -> Self {
+        Self {
+            absolute: epsilon,
+            relative: 0.0,
+        }
+    }
+
+    pub fn relative(epsilon: f64) -> Self {
+        Self {
+            absolute: 0.0,
+            relative: epsilon,
+        }
+    }
+
+    pub fn equals(&self, prev: f64, next: f64) -> bool {
+        if prev == next {
+            return true;
+        }
+        let difference = (prev - next).abs();
+        difference <= self.absolute || difference <= self.relative * prev.abs().max(next.abs())
+    }
+}
+
+/// Function deciding if values at given location are same.
+/// `None` falls back to default comparison.
+pub type DiffEqualityPredicate =
+    Arc<dyn Fn(&IntermediatePath, &Intermediate, &Intermediate) -> Option<bool> + Send + Sync>;
+
+/// Custom equality of values, used in change calculation.
+#[derive(Clone)]
+pub struct DiffEquality(pub DiffEqualityPredicate);
+
+impl DiffEquality {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&IntermediatePath, &Intermediate, &Intermediate) -> Option<bool>
+            + Send
+            + Sync
+            + 'static,
+    {
+        Self(Arc::new(f))
+    }
+
+    pub fn test(
+        &self,
+        path: &IntermediatePath,
+        prev: &Intermediate,
+        next: &Intermediate,
+    ) -> Option<bool> {
+        (self.0)(path, prev, next)
+    }
+}
+
+impl std::fmt::Debug for DiffEquality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("DiffEquality").finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for DiffEquality {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Change calculation options.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DiffOptions {
+    /// Optimization hint.
+    pub optimization_hint: DiffOptimizationHint,
+    /// Identity of sequence items. When set, items of sequences are matched by their keys
+    /// instead of positions, and reordered items are reported as `Change::Moved`.
+    pub sequence_key: Option<SequenceKey>,
+    /// Tolerance of `F32` and `F64` values comparison.
+    pub float_tolerance: Option<FloatTolerance>,
+    /// Custom equality of values, consulted for every compared location before default
+    /// comparison. Values that are exactly equal are always considered same.
+    pub equality: Option<DiffEquality>,
+    /// Paths to compare. Subtrees filtered out are considered same, except for items added to
+    /// or removed from sequences, which are always reported.
+    pub filter: IntermediatePathFilter,
+}
+
+impl DiffOptions {
+    pub fn optimization_hint(mut self, hint: DiffOptimizationHint) -> Self {
+        self.optimization_hint = hint;
+        self
+    }
+
+    pub fn sequence_key(mut self, key: SequenceKey) -> Self {
+        self.sequence_key = Some(key);
+        self
+    }
+
+    pub fn float_tolerance(mut self, tolerance: FloatTolerance) -> Self {
+        self.float_tolerance = Some(tolerance);
+        self
+    }
+
+    pub fn equality<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&IntermediatePath, &Intermediate, &Intermediate) -> Option<bool>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.equality = Some(DiffEquality::new(f));
+        self
+    }
+
+    pub fn filter(mut self, filter: IntermediatePathFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn include(mut self, pattern: IntermediatePathPattern) -> Self {
+        self.filter.include.push(pattern);
+        self
+    }
+
+    pub fn exclude(mut self, pattern: IntermediatePathPattern) -> Self {
+        self.filter.exclude.push(pattern);
+        self
+    }
+
+    /// Tells if options are same as default ones, so change doesn't depend on anything but
+    /// compared values.
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    fn is_exact(&self) -> bool {
+        self.float_tolerance.is_none() && self.equality.is_none() && self.filter.is_empty()
+    }
+
+    fn is_filtered_out(&self, path: &IntermediatePath) -> bool {
+        !self.filter.is_empty() && self.filter.test(path) == IntermediatePathMatch::None
+    }
+
+    fn equals(
+        &self,
+        path: &mut IntermediatePath,
+        prev: &Intermediate,
+        next: &Intermediate,
+    ) -> bool {
+        if prev == next {
+            return true;
+        }
+        if self.is_exact() {
+            return false;
+        }
+        if self.is_filtered_out(path) {
+            return true;
+        }
+        if let Some(result) = self
+            .equality
+            .as_ref()
+            .and_then(|equality| equality.test(path, prev, next))
+        {
+            return result;
+        }
+        match (prev, next) {
+            (Intermediate::F32(_), Intermediate::F32(_))
+            | (Intermediate::F64(_), Intermediate::F64(_)) => self.floats_equal(prev, next),
+            (Intermediate::Option(Some(prev)), Intermediate::Option(Some(next)))
+            | (Intermediate::NewTypeStruct(prev), Intermediate::NewTypeStruct(next)) => {
+                with_segment(path, IntermediatePathSegment::Inner, |path| {
+                    self.equals(path, prev, next)
+                })
+            }
+            (
+                Intermediate::NewTypeVariant(prev_name, prev),
+                Intermediate::NewTypeVariant(next_name, next),
+            ) => {
+                prev_name == next_name
+                    && with_segment(path, IntermediatePathSegment::Inner, |path| {
+                        self.equals(path, prev, next)
+                    })
+            }
+            (Intermediate::Seq(prev), Intermediate::Seq(next))
+            | (Intermediate::Tuple(prev), Intermediate::Tuple(next))
+            | (Intermediate::TupleStruct(prev), Intermediate::TupleStruct(next)) => {
+                self.items_equal(path, prev, next)
+            }
+            (
+                Intermediate::TupleVariant(prev_name, prev),
+                Intermediate::TupleVariant(next_name, next),
+            ) => prev_name == next_name && self.items_equal(path, prev, next),
+            (Intermediate::Map(prev), Intermediate::Map(next)) => {
+                self.entries_equal(path, prev, next, |key| {
+                    IntermediatePathSegment::Key(key.to_owned())
+                })
+            }
+            (Intermediate::Struct(prev), Intermediate::Struct(next)) => {
+                self.fields_equal(path, prev, next)
+            }
+            (
+                Intermediate::StructVariant(prev_name, prev),
+                Intermediate::StructVariant(next_name, next),
+            ) => prev_name == next_name && self.fields_equal(path, prev, next),
+            _ => false,
+        }
+    }
+
+    fn floats_equal(&self, prev: &Intermediate, next: &Intermediate) -> bool {
+        match (self.float_tolerance, prev, next) {
+            (Some(tolerance), Intermediate::F32(prev), Intermediate::F32(next)) => {
+                tolerance.equals(*prev as f64, *next as f64)
+            }
+            (Some(tolerance), Intermediate::F64(prev), Intermediate::F64(next)) => {
+                tolerance.equals(*prev, *next)
+            }
+            _ => false,
+        }
+    }
+
+    fn items_equal(
+        &self,
+        path: &mut IntermediatePath,
+        prev: &[Intermediate],
+        next: &[Intermediate],
+    ) -> bool {
+        prev.len() == next.len()
+            && prev
+                .iter()
+                .zip(next.iter())
+                .enumerate()
+                .all(|(index, (prev, next))| {
+                    with_segment(path, IntermediatePathSegment::Index(index), |path| {
+                        self.equals(path, prev, next)
+                    })
+                })
+    }
+
+    fn fields_equal(
+        &self,
+        path: &mut IntermediatePath,
+        prev: &[(String, Intermediate)],
+        next: &[(String, Intermediate)],
+    ) -> bool {
+        self.entries_equal(path, prev, next, |name| {
+            IntermediatePathSegment::Field(name.to_owned())
+        })
+    }
+
+    /// Entries missing on either side are same only if they are filtered out.
+    fn entries_equal<K: Eq + Hash>(
+        &self,
+        path: &mut IntermediatePath,
+        prev: &[(K, Intermediate)],
+        next: &[(K, Intermediate)],
+        segment: impl Fn(&K) -> IntermediatePathSegment,
+    ) -> bool {
+        let missing = |path: &mut IntermediatePath, key: &K| {
+            with_segment(path, segment(key), |path| self.is_filtered_out(path))
+        };
+        let mut next_lookup = HashMap::<&K, &Intermediate>::with_capacity(next.len());
+        for (key, value) in next {
+            next_lookup.entry(key).or_insert(value);
+        }
+        let prev_lookup = prev.iter().map(|(key, _)| key).collect::<HashSet<_>>();
+        prev.iter().all(|(key, prev)| match next_lookup.get(key) {
+            Some(next) => with_segment(path, segment(key), |path| self.equals(path, prev, next)),
+            None => missing(path, key),
+        }) && next
+            .iter()
+            .filter(|(key, _)| !prev_lookup.contains(key))
+            .all(|(key, _)| missing(path, key))
+    }
+}
+
+/// How patching treats operations that don't match patched value.
+enum PatchMode {
+    /// Skips operations at missing locations, fails on mismatched values.
+    Lenient,
+    /// Skips every mismatched operation, collecting them with their locations.
+    Strict(Vec<(IntermediatePath, Error)>),
+}
+
+impl PatchMode {
+    fn is_strict(&self) -> bool {
+        matches!(self, Self::Strict(_))
+    }
+
+    /// Operation at location that doesn't exist.
+    fn skip(&mut self, path: IntermediatePath, reason: &str) {
+        if let Self::Strict(errors) = self {
+            errors.push((
+                path.to_owned(),
+                Error::InvalidPatch(path, reason.to_owned()),
+            ));
+        }
+    }
+
+    /// Operation that doesn't match value at its location.
+    fn mismatch(&mut self, path: &IntermediatePath, error: Error) -> Result<()> {
+        match self {
+            Self::Lenient => Err(error),
+            Self::Strict(errors) => {
+                errors.push((
+                    path.to_owned(),
+                    Error::InvalidPatch(path.to_owned(), error.to_string()),
+                ));
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Conflict found during three-way merge.
+///
+/// For conflicting sequence regions `path` points to first item of region in merged sequence,
+/// and values hold `Intermediate::Seq` with items of that region.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MergeConflict {
+    /// Location of conflicting value.
+    pub path: IntermediatePath,
+    /// Value in common ancestor, `None` if it did not exist.
+    pub base: Option<Intermediate>,
+    /// Value in our version, `None` if it was removed.
+    pub ours: Option<Intermediate>,
+    /// Value in their version, `None` if it was removed.
+    pub theirs: Option<Intermediate>,
+}
+
+/// Decision made for single merge conflict.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum MergeResolution {
+    /// Keep value from common ancestor and report conflict.
+    #[default]
+    Unresolved,
+    /// Use value from common ancestor.
+    Base,
+    /// Use value from our version.
+    Ours,
+    /// Use value from their version.
+    Theirs,
+    /// Use custom value (`None` removes it).
+    Value(Option<Intermediate>),
+}
+
+/// Result of three-way merge.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeResult {
+    /// Merged value.
+    pub value: Intermediate,
+    /// Change between common ancestor and merged value.
+    pub change: Change,
+    /// Conflicts that were left unresolved.
+    pub conflicts: Vec<MergeConflict>,
+}
+
+impl MergeResult {
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// Information about change between two intermediate data.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub enum Change {
+    /// Values are same.
+    Same,
+    /// Value was removed.
+    Removed,
+    /// Value was entirely changed.
+    Changed(
+        /// Value.
+        Intermediate,
+    ),
+    /// Value was added.
+    Added(
+        /// Value.
+        Intermediate,
+    ),
+    /// Value was partially changed.
+    PartialChange(
+        /// Content change.
+        Box<Change>,
+    ),
+    /// Sequence item was moved to another position.
+    /// Valid only as `PartialSeq` entry, whose index points to moved item.
+    Moved(
+        /// Index of item after the move.
+        usize,
+    ),
+    /// Sequence of values was partially changed.
+    PartialSeq(
+        /// List of changes: `(index, change)`.
+        Vec<(usize, Change)>,
+    ),
+    /// Map of key-values was partially changed.
+    PartialMap(
+        /// List of changes: `(key, change)`.
+        Vec<(Intermediate, Change)>,
+    ),
+    /// Structure with field-value was partially changed.
+    PartialStruct(
+        /// List of changes: `(field, change)`.
+        Vec<(String, Change)>,
+    ),
+}
+
+impl Change {
+    pub fn same() -> Self {
+        Self::Same
+    }
+
+    pub fn removed() -> Self {
+        Self::Removed
+    }
+
+    pub fn changed(value: impl Into<Intermediate>) -> Self {
+        Self::Changed(value.into())
+    }
+
+    pub fn added(value: impl Into<Intermediate>) -> Self {
+        Self::Added(value.into())
+    }
+
+    pub fn moved(to: usize) -> Self {
+        Self::Moved(to)
+    }
+
+    pub fn partial_change(change: Self) -> Self {
+        Self::PartialChange(Box::new(change))
+    }
+
+    pub fn partial_seq() -> Self {
+        Self::PartialSeq(vec![])
+    }
+
+    pub fn partial_map() -> Self {
+        Self::PartialMap(vec![])
+    }
+
+    pub fn partial_struct() -> Self {
+        Self::PartialStruct(vec![])
+    }
+
+    pub fn partial_seq_item(mut self, index: usize, change: Self) -> Self {
+        if let Self::PartialSeq(v) = &mut self {
+            v.push((index, change));
+        }
+        self
+    }
+
+    pub fn partial_map_item(mut self, key: impl Into<Intermediate>, change: Self) -> Self {
+        if let Self::PartialMap(v) = &mut self {
+            let key = key.into();
+            if let Some(item) = v.iter_mut().find(|(k, _)| k == &key) {
+                item.1 = change;
+            } else {
+                v.push((key, change));
+            }
+        }
+        self
+    }
+
+    pub fn partial_struct_item(mut self, name: impl ToString, change: Self) -> Self {
+        if let Self::PartialStruct(v) = &mut self {
+            let name = name.to_string();
+            if let Some(item) = v.iter_mut().find(|(n, _)| n == &name) {
+                item.1 = change;
+            } else {
+                v.push((name, change));
+            }
+        }
+        self
+    }
+
+    /// Wraps change so it applies to value at given location.
+    ///
+    /// # Example
+    /// ```rust
+    /// use serde_intermediate::{Change, IntermediatePath};
+    ///
+    /// let path = IntermediatePath::default().field("players").index(1);
+    /// assert_eq!(
+    ///     Change::at_path(&path, Change::Removed),
+    ///     Change::partial_struct().partial_struct_item(
+    ///         "players",
+    ///         Change::partial_seq().partial_seq_item(1, Change::Removed),
+    ///     )
+    /// );
+    /// ```
+    pub fn at_path(path: &IntermediatePath, change: Self) -> Self {
+        path.segments()
+            .iter()
+            .rev()
+            .fold(change, |change, segment| match segment {
+                IntermediatePathSegment::Index(index) => Self::PartialSeq(vec![(*index, change)]),
+                IntermediatePathSegment::Key(key) => {
+                    Self::PartialMap(vec![(key.to_owned(), change)])
+                }
+                IntermediatePathSegment::Field(name) => {
+                    Self::PartialStruct(vec![(name.to_owned(), change)])
+                }
+                IntermediatePathSegment::Inner => Self::PartialChange(Box::new(change)),
+            })
+    }
+
+    pub fn is_same(&self) -> bool {
+        matches!(self, Self::Same)
+    }
+
+    /// Tells if change made of changes of parts leaves value as it is.
+    fn is_unchanged(&self) -> bool {
+        match self {
+            Self::Same => true,
+            Self::PartialChange(change) => change.is_same(),
+            Self::PartialSeq(changes) => changes.is_empty(),
+            Self::PartialMap(changes) => changes.is_empty(),
+            Self::PartialStruct(changes) => changes.is_empty(),
+            _ => false,
+        }
+    }
+
+    fn optimize(
+        self,
+        source: &Intermediate,
+        target: &Intermediate,
+        hint: DiffOptimizationHint,
+    ) -> Self {
+        match hint {
+            DiffOptimizationHint::Default => self,
+            DiffOptimizationHint::SizeSource => {
+                if self.total_bytesize() > source.total_bytesize() {
+                    Self::Changed(target.to_owned())
+                } else {
+                    self
+                }
+            }
+            DiffOptimizationHint::SizeTarget => {
+                if self.total_bytesize() > target.total_bytesize() {
+                    Self::Changed(target.to_owned())
+                } else {
+                    self
+                }
+            }
+            DiffOptimizationHint::SizeValue(threshold) => {
+                if self.total_bytesize() > threshold {
+                    Self::Changed(target.to_owned())
+                } else {
+                    self
+                }
+            }
+            DiffOptimizationHint::SizePercentage(threshold) => {
+                if self.total_bytesize()
+                    > (threshold.clamp(0.0, 1.0) * source.total_bytesize() as f64) as _
+                {
+                    Self::Changed(target.to_owned())
+                } else {
+                    self
+                }
+            }
+        }
+    }
+
+    pub fn difference(prev: &Intermediate, next: &Intermediate, options: &DiffOptions) -> Self {
+        Self::difference_at(prev, next, options, &mut Default::default())
+    }
+
+    fn difference_at(
+        prev: &Intermediate,
+        next: &Intermediate,
+        options: &DiffOptions,
+        path: &mut IntermediatePath,
+    ) -> Self {
+        if prev == next {
+            return Self::Same;
+        }
+        // Custom equality that tells values differ makes change, even if their parts are same.
+        let mut differs = false;
+        if !options.is_exact() {
+            if options.is_filtered_out(path) {
+                return Self::Same;
+            }
+            match options
+                .equality
+                .as_ref()
+                .and_then(|equality| equality.test(path, prev, next))
+            {
+                Some(true) => return Self::Same,
+                Some(false) => differs = true,
+                None if options.floats_equal(prev, next) => return Self::Same,
+                None => {}
+            }
+        }
+        let result = match (prev, next) {
+            (Intermediate::Option(Some(prev)), Intermediate::Option(Some(next)))
+            | (Intermediate::NewTypeStruct(prev), Intermediate::NewTypeStruct(next)) => {
+                Self::PartialChange(Box::new(with_segment(
+                    path,
+                    IntermediatePathSegment::Inner,
+                    |path| Self::difference_at(prev, next, options, path),
+                )))
+            }
+            (
+                Intermediate::NewTypeVariant(prev_name, prev_value),
+                Intermediate::NewTypeVariant(next_name, next_value),
+            ) => {
+                if prev_name != next_name {
+                    Self::Changed(next.to_owned())
+                } else {
+                    Self::PartialChange(Box::new(with_segment(
+                        path,
+                        IntermediatePathSegment::Inner,
+                        |path| Self::difference_at(prev_value, next_value, options, path),
+                    )))
+                }
+            }
+            (Intermediate::Seq(prev), Intermediate::Seq(next)) => Self::PartialSeq(
+                Self::keyed_sequence_difference_at(prev, next, options, path)
+                    .unwrap_or_else(|| Self::sequence_difference_at(prev, next, options, path)),
+            ),
+            (Intermediate::Tuple(prev_items), Intermediate::Tuple(next_items))
+            | (Intermediate::TupleStruct(prev_items), Intermediate::TupleStruct(next_items)) => {
+                if prev_items.len() != next_items.len() {
+                    Self::Changed(next.to_owned())
+                } else {
+                    Self::PartialSeq(Self::items_difference_at(
+                        prev_items, next_items, options, path,
+                    ))
+                }
+            }
+            (Intermediate::Map(prev), Intermediate::Map(next)) => {
+                Self::PartialMap(Self::entries_difference(prev, next, options, path, |key| {
+                    IntermediatePathSegment::Key(key.to_owned())
+                }))
+            }
+            (
+                Intermediate::TupleVariant(prev_name, prev_items),
+                Intermediate::TupleVariant(next_name, next_items),
+            ) => {
+                if prev_name != next_name || prev_items.len() != next_items.len() {
+                    Self::Changed(next.to_owned())
+                } else {
+                    Self::PartialSeq(Self::items_difference_at(
+                        prev_items, next_items, options, path,
+                    ))
+                }
+            }
+            (Intermediate::Struct(prev), Intermediate::Struct(next)) => Self::PartialStruct(
+                Self::entries_difference(prev, next, options, path, |name| {
+                    IntermediatePathSegment::Field(name.to_owned())
+                }),
+            ),
+            (
+                Intermediate::StructVariant(prev_name, prev_fields),
+                Intermediate::StructVariant(next_name, next_fields),
+            ) => {
+                if prev_name != next_name {
+                    Self::Changed(next.to_owned())
+                } else {
+                    Self::PartialStruct(Self::entries_difference(
+                        prev_fields,
+                        next_fields,
+                        options,
+                        path,
+                        |name| IntermediatePathSegment::Field(name.to_owned()),
+                    ))
+                }
+            }
+            _ => Self::Changed(next.to_owned()),
+        };
+        match (result.is_unchanged(), differs) {
+            (true, false) => Self::Same,
+            (true, true) => Self::Changed(next.to_owned()),
+            (false, _) => result.optimize(prev, next, options.optimization_hint),
+        }
+    }
+
+    /// Index-wise difference of fixed-arity items, such as tuple fields.
+    fn items_difference_at(
+        prev: &[Intermediate],
+        next: &[Intermediate],
+        options: &DiffOptions,
+        path: &mut IntermediatePath,
+    ) -> Vec<(usize, Self)> {
+        prev.iter()
+            .zip(next.iter())
+            .enumerate()
+            .map(|(index, (prev, next))| {
+                let change = with_segment(path, IntermediatePathSegment::Index(index), |path| {
+                    Self::difference_at(prev, next, options, path)
+                });
+                (index, change)
+            })
+            .filter(|(_, change)| !change.is_same())
+            .collect()
+    }
+
+    fn entries_difference<K>(
+        prev: &[(K, Intermediate)],
+        next: &[(K, Intermediate)],
+        options: &DiffOptions,
+        path: &mut IntermediatePath,
+        segment: impl Fn(&K) -> IntermediatePathSegment,
+    ) -> Vec<(K, Self)>
+    where
+        K: Clone + Eq + Hash,
+    {
+        let mut prev_lookup = HashMap::<&K, &Intermediate>::with_capacity(prev.len());
+        for (key, value) in prev {
+            prev_lookup.entry(key).or_insert(value);
+        }
+        let mut next_lookup = HashMap::<&K, &Intermediate>::with_capacity(next.len());
+        for (key, value) in next {
+            next_lookup.entry(key).or_insert(value);
+        }
+        let mut result = vec![];
+        let filtered_out = |path: &mut IntermediatePath, key: &K| {
+            !options.filter.is_empty()
+                && with_segment(path, segment(key), |path| options.is_filtered_out(path))
+        };
+        for (nk, nv) in next {
+            if !prev_lookup.contains_key(nk) && !filtered_out(path, nk) {
+                result.push((nk.to_owned(), Self::Added(nv.to_owned())));
+            }
+        }
+        for (pk, _) in prev {
+            if !next_lookup.contains_key(pk) && !filtered_out(path, pk) {
+                result.push((pk.to_owned(), Self::Removed));
+            }
+        }
+        for (pk, pv) in prev {
+            if let Some(nv) = next_lookup.get(pk) {
+                let diff = with_segment(path, segment(pk), |path| {
+                    Self::difference_at(pv, nv, options, path)
+                });
+                if !diff.is_same() {
+                    result.push((pk.to_owned(), diff));
+                }
+            }
+        }
+        result
+    }
+
+    pub fn sequence_difference(
+        prev: &[Intermediate],
+        next: &[Intermediate],
+        options: &DiffOptions,
+    ) -> Vec<(usize, Self)> {
+        Self::sequence_difference_at(prev, next, options, &mut Default::default())
+    }
+
+    fn sequence_difference_at(
+        prev: &[Intermediate],
+        next: &[Intermediate],
+        options: &DiffOptions,
+        path: &mut IntermediatePath,
+    ) -> Vec<(usize, Self)> {
+        let mut result = vec![];
+        let mut position = 0;
+        let (mut prev_start, mut next_start) = (0, 0);
+        let matches = sequence_matches(prev, next, options, path);
+        for (prev_index, next_index) in matches
+            .into_iter()
+            .chain(std::iter::once((prev.len(), next.len())))
+        {
+            let prev_gap = &prev[prev_start..prev_index];
+            let next_gap = &next[next_start..next_index];
+            let paired = prev_gap.len().min(next_gap.len());
+            for item in &next_gap[..(next_gap.len() - paired)] {
+                result.push((position, Self::Added(item.to_owned())));
+                position += 1;
+            }
+            for _ in paired..prev_gap.len() {
+                result.push((position, Self::Removed));
+            }
+            let offset = prev_start + prev_gap.len() - paired;
+            for (index, (prev, next)) in prev_gap[(prev_gap.len() - paired)..]
+                .iter()
+                .zip(next_gap[(next_gap.len() - paired)..].iter())
+                .enumerate()
+            {
+                let diff = with_segment(
+                    path,
+                    IntermediatePathSegment::Index(offset + index),
+                    |path| Self::difference_at(prev, next, options, path),
+                );
+                if !diff.is_same() {
+                    result.push((position, diff));
+                }
+                position += 1;
+            }
+            position += 1;
+            prev_start = prev_index + 1;
+            next_start = next_index + 1;
+        }
+        result
+    }
+
+    /// Calculates sequence change by matching items with `DiffOptions::sequence_key`.
+    ///
+    /// Returns `None` if key is not set, or when any item has no key or keys are not unique.
+    ///
+    /// # Example
+    /// ```rust
+    /// use serde_intermediate::{Change, DiffOptions, Intermediate, SequenceKey};
+    ///
+    /// let entity = |id: u32, hp: u32| Intermediate::struct_type().field("id", id).field("hp", hp);
+    /// let prev = Intermediate::seq().item(entity(1, 10)).item(entity(2, 20)).item(entity(3, 30));
+    /// let next = Intermediate::seq().item(entity(3, 30)).item(entity(1, 10)).item(entity(2, 25));
+    /// let options = DiffOptions::default().sequence_key(SequenceKey::field("id"));
+    /// let change = Change::difference(&prev, &next, &options);
+    /// assert_eq!(
+    ///     change,
+    ///     Change::partial_seq()
+    ///         .partial_seq_item(2, Change::moved(0))
+    ///         .partial_seq_item(
+    ///             2,
+    ///             Change::partial_struct().partial_struct_item("hp", Change::changed(25u32)),
+    ///         )
+    /// );
+    /// assert_eq!(change.patch(&prev).unwrap().unwrap(), next);
+    /// ```
+    pub fn keyed_sequence_difference(
+        prev: &[Intermediate],
+        next: &[Intermediate],
+        options: &DiffOptions,
+    ) -> Option<Vec<(usize, Self)>> {
+        Self::keyed_sequence_difference_at(prev, next, options, &mut Default::default())
+    }
+
+    fn keyed_sequence_difference_at(
+        prev: &[Intermediate],
+        next: &[Intermediate],
+        options: &DiffOptions,
+        path: &mut IntermediatePath,
+    ) -> Option<Vec<(usize, Self)>> {
+        let key = options.sequence_key.as_ref()?;
+        let prev_keys = prev
+            .iter()
+            .map(|item| key.extract(item))
+            .collect::<Option<Vec<_>>>()?;
+        let next_keys = next
+            .iter()
+            .map(|item| key.extract(item))
+            .collect::<Option<Vec<_>>>()?;
+        let prev_lookup = unique_lookup(&prev_keys)?;
+        let next_lookup = unique_lookup(&next_keys)?;
+        let mut result = vec![];
+        // Target indices of kept items, in their current order.
+        let mut current = Vec::with_capacity(prev.len());
+        for key in &prev_keys {
+            match next_lookup.get(key) {
+                Some(target) => current.push(*target),
+                None => result.push((current.len(), Self::Removed)),
+            }
+        }
+        let mut stable = vec![false; next.len()];
+        for (target, flag) in current.iter().zip(longest_increasing_subsequence(&current)) {
+            stable[*target] = flag;
+        }
+        let sources = next_keys
+            .iter()
+            .map(|key| prev_lookup.get(key).copied())
+            .collect::<Vec<_>>();
+        for (target, source) in sources.iter().enumerate() {
+            if source.is_some() && stable[target] {
+                continue;
+            }
+            if source.is_some() {
+                if let Some(from) = current.iter().position(|t| *t == target) {
+                    current.remove(from);
+                    let to = insert_position(&current, target);
+                    current.insert(to, target);
+                    if from != to {
+                        result.push((from, Self::Moved(to)));
+                    }
+                }
+            } else {
+                let to = insert_position(&current, target);
+                current.insert(to, target);
+                result.push((to, Self::Added(next[target].to_owned())));
+            }
+        }
+        for (target, source) in sources.into_iter().enumerate() {
+            if let Some(source) = source {
+                let diff = with_segment(path, IntermediatePathSegment::Index(source), |path| {
+                    Self::difference_at(&prev[source], &next[target], options, path)
+                });
+                if !diff.is_same() {
+                    result.push((target, diff));
+                }
+            }
+        }
+        Some(result)
+    }
+
+    /// Combines changes made independently to common ancestor, leaving conflicting locations
+    /// with ancestor value and reporting them in result.
+    ///
+    /// Values are compared with `options`: sequence items are matched by
+    /// `DiffOptions::sequence_key` when every item has unique key, differences within float
+    /// tolerance or custom equality are not changes, and locations filtered out keep ancestor
+    /// value.
+    ///
+    /// # Example
+    /// ```rust
+    /// use serde_intermediate::{Change, Intermediate};
+    ///
+    /// let base = Intermediate::struct_type().field("a", 1).field("b", 2);
+    /// let ours = Intermediate::struct_type().field("a", 10).field("b", 2);
+    /// let theirs = Intermediate::struct_type().field("a", 1).field("b", 20);
+    /// let result = Change::merge3(&base, &ours, &theirs, &Default::default());
+    /// assert!(result.is_clean());
+    /// assert_eq!(result.value, Intermediate::struct_type().field("a", 10).field("b", 20));
+    /// ```
+    pub fn merge3(
+        base: &Intermediate,
+        ours: &Intermediate,
+        theirs: &Intermediate,
+        options: &DiffOptions,
+    ) -> MergeResult {
+        Self::merge3_with(base, ours, theirs, options, |_| MergeResolution::Unresolved)
+    }
+
+    /// Same as `merge3`, but every conflict is passed to `resolver` to decide its outcome.
+    /// Use `|_| MergeResolution::Ours` or `|_| MergeResolution::Theirs` to prefer one side.
+    pub fn merge3_with<F>(
+        base: &Intermediate,
+        ours: &Intermediate,
+        theirs: &Intermediate,
+        options: &DiffOptions,
+        resolver: F,
+    ) -> MergeResult
+    where
+        F: FnMut(&MergeConflict) -> MergeResolution,
+    {
+        let mut merge = Merge3 {
+            options,
+            resolver,
+            conflicts: vec![],
+        };
+        let value = merge
+            .value(&Default::default(), Some(base), Some(ours), Some(theirs))
+            .unwrap_or_else(|| base.to_owned());
+        MergeResult {
+            change: Self::difference(base, &value, options),
+            value,
+            conflicts: merge.conflicts,
+        }
+    }
+
+    /// Produces change that undoes this change after it was applied to `source`.
+    /// Change does not store values it replaces, so `source` must be the value it was applied to.
+    ///
+    /// # Example
+    /// ```rust
+    /// use serde_intermediate::{Change, Intermediate};
+    ///
+    /// let prev = Intermediate::seq().item(1).item(2).item(3);
+    /// let next = Intermediate::seq().item(2).item(3).item(4);
+    /// let change = Change::difference(&prev, &next, &Default::default());
+    /// let undo = change.invert(&prev).unwrap();
+    /// let patched = change.patch(&prev).unwrap().unwrap();
+    /// assert_eq!(patched, next);
+    /// assert_eq!(undo.patch(&patched).unwrap().unwrap(), prev);
+    /// ```
+    pub fn invert(&self, source: &Intermediate) -> Result<Self> {
+        match self {
+            Self::Same => Ok(Self::Same),
+            Self::Removed | Self::Changed(_) => Ok(Self::Changed(source.to_owned())),
+            Self::Added(_) => Err(Error::CannotAdd(source.to_owned())),
+            Self::Moved(_) => Err(Error::NotSeq(source.to_owned())),
+            Self::PartialChange(change) => match source {
+                Intermediate::Option(Some(v))
+                | Intermediate::NewTypeStruct(v)
+                | Intermediate::NewTypeVariant(_, v) => {
+                    Ok(Self::PartialChange(Box::new(change.invert(v)?)))
+                }
+                _ => Err(Error::NotPartial(source.to_owned())),
+            },
+            Self::PartialSeq(changes) => {
+                let mut items = match source {
+                    Intermediate::Seq(v)
+                    | Intermediate::Tuple(v)
+                    | Intermediate::TupleStruct(v)
+                    | Intermediate::TupleVariant(_, v) => v.to_owned(),
+                    _ => return Err(Error::NotSeq(source.to_owned())),
+                };
+                let mut result = Vec::with_capacity(changes.len());
+                for (index, change) in changes {
+                    let index = *index;
+                    match change {
+                        Self::Same => {}
+                        Self::Removed => {
+                            if index >= items.len() {
+                                return Err(Error::PathNotFound(
+                                    IntermediatePath::default().index(index),
+                                ));
+                            }
+                            result.push((index, Self::Added(items.remove(index))));
+                        }
+                        Self::Added(v) => {
+                            if index > items.len() {
+                                return Err(Error::PathNotFound(
+                                    IntermediatePath::default().index(index),
+                                ));
+                            }
+                            items.insert(index, v.to_owned());
+                            result.push((index, Self::Removed));
+                        }
+                        Self::Moved(to) => {
+                            if index >= items.len() || *to >= items.len() {
+                                return Err(Error::PathNotFound(
+                                    IntermediatePath::default().index(index),
+                                ));
+                            }
+                            let item = items.remove(index);
+                            items.insert(*to, item);
+                            result.push((*to, Self::Moved(index)));
+                        }
+                        change => {
+                            if let Some(item) = items.get_mut(index) {
+                                let inverse = change.invert(item)?;
+                                if let Some(patched) = change.patch(item)? {
+                                    *item = patched;
+                                }
+                                result.push((index, inverse));
+                            }
+                        }
+                    }
+                }
+                result.reverse();
+                Ok(Self::PartialSeq(result))
+            }
+            Self::PartialMap(changes) => match source {
+                Intermediate::Map(v) => {
+                    let mut entries = v.to_owned();
+                    let mut result = Vec::with_capacity(changes.len());
+                    for (key, change) in changes {
+                        let index = entries.iter().position(|(k, _)| k == key);
+                        if let Some(inverse) = Self::invert_entry(&mut entries, index, key, change)?
+                        {
+                            result.push((key.to_owned(), inverse));
+                        }
+                    }
+                    result.reverse();
+                    Ok(Self::PartialMap(result))
+                }
+                _ => Err(Error::NotMap(source.to_owned())),
+            },
+            Self::PartialStruct(changes) => match source {
+                Intermediate::Struct(v) | Intermediate::StructVariant(_, v) => {
+                    let mut fields = v.to_owned();
+                    let mut result = Vec::with_capacity(changes.len());
+                    for (name, change) in changes {
+                        let index = fields.iter().position(|(n, _)| n == name);
+                        if let Some(inverse) = Self::invert_entry(&mut fields, index, name, change)?
+                        {
+                            result.push((name.to_owned(), inverse));
+                        }
+                    }
+                    result.reverse();
+                    Ok(Self::PartialStruct(result))
+                }
+                _ => Err(Error::NotMap(source.to_owned())),
+            },
+        }
+    }
+
+    /// Inverts change of single map entry or struct field and applies it to `entries`.
+    fn invert_entry<K>(
+        entries: &mut Vec<(K, Intermediate)>,
+        index: Option<usize>,
+        key: &K,
+        change: &Self,
+    ) -> Result<Option<Self>>
+    where
+        K: Clone,
+    {
+        let index = match (index, change) {
+            (None, Self::Added(v)) => {
+                entries.push((key.to_owned(), v.to_owned()));
+                return Ok(Some(Self::Removed));
+            }
+            (None, _) | (_, Self::Same) => return Ok(None),
+            (Some(index), _) => index,
+        };
+        match change {
+            Self::Removed => Ok(Some(Self::Added(entries.remove(index).1))),
+            Self::Added(v) => Ok(Some(Self::Changed(std::mem::replace(
+                &mut entries[index].1,
+                v.to_owned(),
+            )))),
+            change => {
+                let item = &mut entries[index].1;
+                let inverse = change.invert(item)?;
+                if let Some(patched) = change.patch(item)? {
+                    *item = patched;
+                }
+                Ok(Some(inverse))
+            }
+        }
+    }
+
+    /// Squashes this change and `next` change into single change that gives the same result
+    /// as applying both of them in order.
+    ///
+    /// `Added` followed by `Removed` of map entry or struct field cancels out, which assumes
+    /// that `Added` introduced new entry (as produced by `Change::difference`).
+    ///
+    /// # Example
+    /// ```rust
+    /// use serde_intermediate::{Change, Intermediate};
+    ///
+    /// let a = Intermediate::seq().item(1).item(2).item(3);
+    /// let b = Intermediate::seq().item(0).item(2).item(3);
+    /// let c = Intermediate::seq().item(0).item(3).item(4);
+    /// let first = Change::difference(&a, &b, &Default::default());
+    /// let next = Change::difference(&b, &c, &Default::default());
+    /// let composed = first.compose(&next).unwrap();
+    /// assert_eq!(composed.patch(&a).unwrap().unwrap(), c);
+    /// ```
+    pub fn compose(&self, next: &Self) -> Result<Self> {
+        match (self, next) {
+            (change, Self::Same) | (Self::Same, change) => Ok(change.to_owned()),
+            (Self::Removed, Self::Added(v)) => Ok(Self::Changed(v.to_owned())),
+            (_, Self::Removed) | (Self::Removed, _) => Ok(Self::Removed),
+            // Entry that didn't exist before stays added, whatever happens to it later.
+            (Self::Added(v), change) => match change.patch(v)? {
+                Some(v) => Ok(Self::Added(v)),
+                None => Ok(Self::Removed),
+            },
+            (_, Self::Added(v)) => Ok(Self::Added(v.to_owned())),
+            (_, Self::Changed(v)) => Ok(Self::Changed(v.to_owned())),
+            (Self::Changed(v), change) => match change.patch(v)? {
+                Some(v) => Ok(Self::Changed(v)),
+                None => Ok(Self::Removed),
+            },
+            (Self::PartialChange(a), Self::PartialChange(b)) => {
+                Ok(Self::PartialChange(Box::new(a.compose(b)?)))
+            }
+            (Self::PartialSeq(a), Self::PartialSeq(b))
+                if a.iter()
+                    .chain(b.iter())
+                    .any(|(_, change)| matches!(change, Self::Moved(_))) =>
+            {
+                // Sequence entries are applied in order, so moves are just kept in sequence.
+                Ok(Self::PartialSeq(
+                    a.iter().chain(b.iter()).cloned().collect(),
+                ))
+            }
+            (Self::PartialSeq(a), Self::PartialSeq(b)) => {
+                let mut state = ComposeSeq::default();
+                for (index, change) in a.iter().chain(b.iter()) {
+                    state.apply(*index, change)?;
+                }
+                Ok(Self::PartialSeq(state.into_changes()))
+            }
+            (Self::PartialMap(a), Self::PartialMap(b)) => {
+                Ok(Self::PartialMap(Self::compose_entries(a, b)?))
+            }
+            (Self::PartialStruct(a), Self::PartialStruct(b)) => {
+                Ok(Self::PartialStruct(Self::compose_entries(a, b)?))
+            }
+            (a, b) => Err(Error::CannotCompose(
+                Box::new(a.to_owned()),
+                Box::new(b.to_owned()),
+            )),
+        }
+    }
+
+    /// Squashes list of consecutive changes into single change.
+    pub fn compose_all<'a>(changes: impl IntoIterator<Item = &'a Self>) -> Result<Self> {
+        changes
+            .into_iter()
+            .try_fold(Self::Same, |result, change| result.compose(change))
+    }
+
+    fn compose_entries<K>(first: &[(K, Self)], next: &[(K, Self)]) -> Result<Vec<(K, Self)>>
+    where
+        K: Clone + PartialEq,
+    {
+        let mut result = first.to_owned();
+        for (key, change) in next {
+            match result.iter().rposition(|(k, _)| k == key) {
+                Some(index) => match (&result[index].1, change) {
+                    (Self::Added(_), Self::Removed) => {
+                        result.remove(index);
+                    }
+                    (prev, change) => {
+                        result[index].1 = prev.compose(change)?;
+                    }
+                },
+                None => result.push((key.to_owned(), change.to_owned())),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Applies change to value.
+    /// Operations that don't match value, such as changes of missing sequence items or
+    /// structure fields, are skipped.
+    pub fn patch(&self, value: &Intermediate) -> Result<Option<Intermediate>> {
+        self.patch_at(value, &mut Default::default(), &mut PatchMode::Lenient)
+    }
+
+    /// Applies change to value, failing with `Error::InvalidPatch` that points at location of
+    /// first operation that doesn't match value. Use `invalid_operations` to list all of them.
+    ///
+    /// # Example
+    /// ```rust
+    /// use serde_intermediate::{Change, Error, Intermediate};
+    ///
+    /// let value = Intermediate::struct_type().field("items", Intermediate::seq().item(1));
+    /// let change = Change::partial_struct().partial_struct_item(
+    ///     "items",
+    ///     Change::partial_seq().partial_seq_item(3, Change::Removed),
+    /// );
+    /// assert_eq!(change.patch(&value).unwrap().unwrap(), value);
+    /// match change.patch_strict(&value) {
+    ///     Err(Error::InvalidPatch(path, _)) => assert_eq!(path.to_string(), "items[3]"),
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    pub fn patch_strict(&self, value: &Intermediate) -> Result<Option<Intermediate>> {
+        let (result, errors) = self.patch_collect(value);
+        match errors.into_iter().next() {
+            Some((_, error)) => Err(error),
+            None => Ok(result),
+        }
+    }
+
+    /// Checks if every operation of change matches value, without applying it.
+    /// Fails with first operation that doesn't match value.
+    pub fn validate(&self, value: &Intermediate) -> Result<()> {
+        self.patch_strict(value).map(|_| ())
+    }
+
+    /// Lists every operation of change that doesn't match value, with its location.
+    ///
+    /// # Example
+    /// ```rust
+    /// use serde_intermediate::{Change, Intermediate};
+    ///
+    /// let value = Intermediate::struct_type().field("a", 1).field("b", Intermediate::seq());
+    /// let change = Change::partial_struct()
+    ///     .partial_struct_item("a", Change::partial_seq())
+    ///     .partial_struct_item("b", Change::partial_seq().partial_seq_item(1, Change::Removed))
+    ///     .partial_struct_item("c", Change::changed(3));
+    /// let paths = change
+    ///     .invalid_operations(&value)
+    ///     .into_iter()
+    ///     .map(|(path, _)| path.to_string())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(paths, vec!["a", "b[1]", "c"]);
+    /// ```
+    pub fn invalid_operations(&self, value: &Intermediate) -> Vec<(IntermediatePath, Error)> {
+        self.patch_collect(value).1
+    }
+
+    /// Applies change to value, skipping operations that don't match value and collecting them
+    /// as `Error::InvalidPatch` errors with their locations.
+    pub(crate) fn patch_collect(
+        &self,
+        value: &Intermediate,
+    ) -> (Option<Intermediate>, Vec<(IntermediatePath, Error)>) {
+        let mut mode = PatchMode::Strict(vec![]);
+        let result = self
+            .patch_at(value, &mut Default::default(), &mut mode)
+            .unwrap_or_else(|_| Some(value.to_owned()));
+        match mode {
+            PatchMode::Strict(errors) => (result, errors),
+            PatchMode::Lenient => unreachable!(),
+        }
+    }
+
+    fn patch_at(
+        &self,
+        value: &Intermediate,
+        path: &mut IntermediatePath,
+        mode: &mut PatchMode,
+    ) -> Result<Option<Intermediate>> {
+        let unchanged = || Ok(Some(value.to_owned()));
+        match self {
+            Self::Same => Ok(Some(value.to_owned())),
+            Self::Removed => Ok(None),
+            Self::Changed(v) => Ok(Some(v.to_owned())),
+            Self::Added(_) => {
+                mode.mismatch(path, Error::CannotAdd(value.to_owned()))?;
+                unchanged()
+            }
+            Self::Moved(_) => {
+                mode.mismatch(path, Error::NotSeq(value.to_owned()))?;
+                unchanged()
+            }
+            Self::PartialChange(change) => {
+                let inner = match value {
+                    Intermediate::Option(Some(v))
+                    | Intermediate::NewTypeStruct(v)
+                    | Intermediate::NewTypeVariant(_, v) => v,
+                    _ => {
+                        mode.mismatch(path, Error::NotPartial(value.to_owned()))?;
+                        return unchanged();
+                    }
+                };
+                let patched = with_segment(path, IntermediatePathSegment::Inner, |path| {
+                    change.patch_at(inner, path, mode)
+                })?;
+                let patched = match patched {
+                    Some(v) => Box::new(v),
+                    None => {
+                        mode.mismatch(path, Error::NotPartial(value.to_owned()))?;
+                        return unchanged();
+                    }
+                };
+                let result = match value {
+                    Intermediate::Option(_) => Intermediate::Option(Some(patched)),
+                    Intermediate::NewTypeStruct(_) => Intermediate::NewTypeStruct(patched),
+                    Intermediate::NewTypeVariant(n, _) => {
+                        Intermediate::NewTypeVariant(n.to_owned(), patched)
+                    }
+                    _ => unreachable!(),
+                };
+                Ok(Some(result))
+            }
+            Self::PartialSeq(changes) => {
+                let items = match value {
+                    Intermediate::Seq(v)
+                    | Intermediate::Tuple(v)
+                    | Intermediate::TupleStruct(v)
+                    | Intermediate::TupleVariant(_, v) => v,
+                    _ => {
+                        mode.mismatch(path, Error::NotSeq(value.to_owned()))?;
+                        return unchanged();
+                    }
+                };
+                let items = Self::patch_items(items, changes, path, mode)?;
+                let result = match value {
+                    Intermediate::Seq(_) => Intermediate::Seq(items),
+                    Intermediate::Tuple(_) => Intermediate::Tuple(items),
+                    Intermediate::TupleStruct(_) => Intermediate::TupleStruct(items),
+                    Intermediate::TupleVariant(n, _) => {
+                        Intermediate::TupleVariant(n.to_owned(), items)
+                    }
+                    _ => unreachable!(),
+                };
+                Ok(Some(result))
+            }
+            Self::PartialMap(changes) => match value {
+                Intermediate::Map(v) => Ok(Some(Intermediate::Map(Self::patch_entries(
+                    v,
+                    changes,
+                    path,
+                    mode,
+                    |key| IntermediatePathSegment::Key(key.to_owned()),
+                )?))),
+                _ => {
+                    mode.mismatch(path, Error::NotMap(value.to_owned()))?;
+                    unchanged()
+                }
+            },
+            Self::PartialStruct(changes) => {
+                let segment = |name: &String| IntermediatePathSegment::Field(name.to_owned());
+                match value {
+                    Intermediate::Struct(v) => Ok(Some(Intermediate::Struct(Self::patch_entries(
+                        v, changes, path, mode, segment,
+                    )?))),
+                    Intermediate::StructVariant(n, v) => Ok(Some(Intermediate::StructVariant(
+                        n.to_owned(),
+                        Self::patch_entries(v, changes, path, mode, segment)?,
+                    ))),
+                    _ => {
+                        mode.mismatch(path, Error::NotMap(value.to_owned()))?;
+                        unchanged()
+                    }
+                }
+            }
+        }
+    }
+
+    fn patch_items(
+        items: &[Intermediate],
+        changes: &[(usize, Self)],
+        path: &mut IntermediatePath,
+        mode: &mut PatchMode,
+    ) -> Result<Vec<Intermediate>> {
+        let mut result = items.to_owned();
+        for (index, change) in changes {
+            let index = *index;
+            let location = || path.to_owned().index(index);
+            match change {
+                Self::Removed => {
+                    if index < result.len() {
+                        result.remove(index);
+                    } else {
+                        mode.skip(location(), "cannot remove missing item");
+                    }
+                }
+                Self::Added(v) => {
+                    if index <= result.len() {
+                        result.insert(index, v.to_owned());
+                    } else {
+                        mode.skip(location(), "cannot add item past the end");
+                    }
+                }
+                Self::Moved(to) => {
+                    if index >= result.len() || *to >= result.len() {
+                        mode.skip(location(), "cannot move missing item");
+                    } else {
+                        let item = result.remove(index);
+                        result.insert(*to, item);
+                    }
+                }
+                change => match result.get_mut(index) {
+                    Some(item) => {
+                        let patched =
+                            with_segment(path, IntermediatePathSegment::Index(index), |path| {
+                                change.patch_at(item, path, mode)
+                            })?;
+                        if let Some(patched) = patched {
+                            *item = patched;
+                        }
+                    }
+                    None if !change.is_same() => {
+                        mode.skip(location(), "cannot change missing item");
+                    }
+                    None => {}
+                },
+            }
+        }
+        Ok(result)
+    }
+
+    fn patch_entries<'a, K>(
+        entries: &'a [(K, Intermediate)],
+        changes: &'a [(K, Self)],
+        path: &mut IntermediatePath,
+        mode: &mut PatchMode,
+        segment: impl Fn(&K) -> IntermediatePathSegment,
+    ) -> Result<Vec<(K, Intermediate)>>
+    where
+        K: Clone + Eq + Hash,
+    {
+        let mut result = entries.iter().cloned().map(Some).collect::<Vec<_>>();
+        let mut lookup = HashMap::<&K, usize>::with_capacity(entries.len());
+        for (index, (key, _)) in entries.iter().enumerate() {
+            lookup.entry(key).or_insert(index);
+        }
+        for (key, change) in changes {
+            let location = || path.to_owned().segment(segment(key));
+            match change {
+                Self::Removed => match lookup.remove(key) {
+                    Some(index) => result[index] = None,
+                    None => mode.skip(location(), "cannot remove missing entry"),
+                },
+                Self::Added(v) => match lookup.get(key) {
+                    Some(_) if mode.is_strict() => mode.skip(location(), "entry already exists"),
+                    Some(index) => {
+                        if let Some((_, item)) = &mut result[*index] {
+                            *item = v.to_owned();
+                        }
+                    }
+                    None => {
+                        lookup.insert(key, result.len());
+                        result.push(Some((key.to_owned(), v.to_owned())));
+                    }
+                },
+                change => match lookup.get(key).and_then(|index| result[*index].as_mut()) {
+                    Some((_, item)) => {
+                        let patched = with_segment(path, segment(key), |path| {
+                            change.patch_at(item, path, mode)
+                        })?;
+                        if let Some(patched) = patched {
+                            *item = patched;
+                        }
+                    }
+                    None if !change.is_same() => {
+                        mode.skip(location(), "cannot change missing entry");
+                    }
+                    None => {}
+                },
+            }
+        }
+        Ok(result.into_iter().flatten().collect())
+    }
+
+    pub fn data_difference<P, N>(prev: &P, next: &N, options: &DiffOptions) -> Result<Self>
+    where
+        P: Serialize,
+        N: Serialize,
+    {
+        let prev = crate::to_intermediate(prev)?;
+        let next = crate::to_intermediate(next)?;
+        Ok(Self::difference(&prev, &next, options))
+    }
+
+    pub fn data_patch<T>(&self, data: &T) -> Result<Option<T>>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let serialized = crate::to_intermediate(data)?;
+        let patched = match self.patch(&serialized)? {
+            Some(patched) => patched,
+            None => return Ok(None),
+        };
+        Ok(Some(crate::from_intermediate::<T>(&patched)?))
+    }
+
+    /// Keeps only changes at locations passing `filter`.
+    /// Items added to, removed from or moved within sequences are always kept, as they shift
+    /// positions of following items.
+    ///
+    /// # Example
+    /// ```rust
+    /// use serde_intermediate::{Change, Intermediate, IntermediatePathFilter};
+    ///
+    /// let change = Change::partial_struct()
+    ///     .partial_struct_item("name", Change::changed("bar"))
+    ///     .partial_struct_item("last_modified", Change::changed(42));
+    /// let filter = IntermediatePathFilter::default().exclude("**.last_modified".parse().unwrap());
+    /// assert_eq!(
+    ///     change.filter(&filter),
+    ///     Change::partial_struct().partial_struct_item("name", Change::changed("bar")),
+    /// );
+    /// ```
+    pub fn filter(&self, filter: &IntermediatePathFilter) -> Self {
+        fn implement(
+            change: &Change,
+            filter: &IntermediatePathFilter,
+            path: &mut IntermediatePath,
+        ) -> Change {
+            if filter.test(path) == IntermediatePathMatch::None {
+                return Change::Same;
+            }
+            let result = match change {
+                Change::PartialChange(change) => Change::PartialChange(Box::new(with_segment(
+                    path,
+                    IntermediatePathSegment::Inner,
+                    |path| implement(change, filter, path),
+                ))),
+                Change::PartialSeq(v) => Change::PartialSeq(
+                    v.iter()
+                        .filter_map(|(index, change)| {
+                            let change = match change {
+                                Change::Added(_) | Change::Removed | Change::Moved(_) => {
+                                    change.to_owned()
+                                }
+                                change => with_segment(
+                                    path,
+                                    IntermediatePathSegment::Index(*index),
+                                    |path| implement(change, filter, path),
+                                ),
+                            };
+                            (!change.is_same()).then_some((*index, change))
+                        })
+                        .collect(),
+                ),
+                Change::PartialMap(v) => Change::PartialMap(
+                    v.iter()
+                        .filter_map(|(key, change)| {
+                            let change = with_segment(
+                                path,
+                                IntermediatePathSegment::Key(key.to_owned()),
+                                |path| implement(change, filter, path),
+                            );
+                            (!change.is_same()).then(|| (key.to_owned(), change))
+                        })
+                        .collect(),
+                ),
+                Change::PartialStruct(v) => Change::PartialStruct(
+                    v.iter()
+                        .filter_map(|(name, change)| {
+                            let change = with_segment(
+                                path,
+                                IntermediatePathSegment::Field(name.to_owned()),
+                                |path| implement(change, filter, path),
+                            );
+                            (!change.is_same()).then(|| (name.to_owned(), change))
+                        })
+                        .collect(),
+                ),
+                change => return change.to_owned(),
+            };
+            match result {
+                Change::PartialChange(change) if change.is_same() => Change::Same,
+                Change::PartialSeq(v) if v.is_empty() => Change::Same,
+                Change::PartialMap(v) if v.is_empty() => Change::Same,
+                Change::PartialStruct(v) if v.is_empty() => Change::Same,
+                result => result,
+            }
+        }
+
+        if filter.is_empty() {
+            self.to_owned()
+        } else {
+            implement(self, filter, &mut Default::default())
+        }
+    }
+
+    /// Applies only changes at locations passing `filter`.
+    pub fn patch_filtered(
+        &self,
+        value: &Intermediate,
+        filter: &IntermediatePathFilter,
+    ) -> Result<Option<Intermediate>> {
+        self.filter(filter).patch(value)
+    }
+
+    /// Lists all non-partial changes together with locations they apply to.
+    /// Sequence indices are relative to state after applying preceding changes of that sequence.
+    pub fn flatten(&self) -> Vec<(IntermediatePath, &Self)> {
+        fn implement<'a>(
+            change: &'a Change,
+            path: &IntermediatePath,
+            result: &mut Vec<(IntermediatePath, &'a Change)>,
+        ) {
+            match change {
+                Change::Same => {}
+                Change::PartialChange(change) => {
+                    implement(change, &path.to_owned().inner(), result)
+                }
+                Change::PartialSeq(v) => {
+                    for (index, change) in v {
+                        implement(change, &path.to_owned().index(*index), result);
+                    }
+                }
+                Change::PartialMap(v) => {
+                    for (key, change) in v {
+                        implement(change, &path.to_owned().key(key.to_owned()), result);
+                    }
+                }
+                Change::PartialStruct(v) => {
+                    for (name, change) in v {
+                        implement(change, &path.to_owned().field(name), result);
+                    }
+                }
+                change => result.push((path.to_owned(), change)),
+            }
+        }
+
+        let mut result = vec![];
+        implement(self, &Default::default(), &mut result);
+        result
+    }
+
+    pub fn total_bytesize(&self) -> usize {
+        fn string_bytesize(v: &str) -> usize {
+            std::mem::size_of_val(v.as_bytes())
+        }
+
+        std::mem::size_of_val(self)
+            + match self {
+                Self::Changed(v) | Self::Added(v) => v.total_bytesize(),
+                Self::PartialChange(v) => v.total_bytesize(),
+                Self::PartialSeq(v) => v
+                    .iter()
+                    .map(|(i, v)| std::mem::size_of_val(i) + v.total_bytesize())
+                    .sum(),
+                Self::PartialMap(v) => v
+                    .iter()
+                    .map(|(k, v)| k.total_bytesize() + v.total_bytesize())
+                    .sum(),
+                Self::PartialStruct(v) => v
+                    .iter()
+                    .map(|(k, v)| string_bytesize(k) + v.total_bytesize())
+                    .sum(),
+                _ => 0,
+            }
+    }
+}
+
+/// Change paired with change that undoes it.
+///
+/// # Example
+/// ```rust
+/// use serde::{Serialize, Deserialize};
+/// use serde_intermediate::{ReflectIntermediate, ReversibleChange};
+///
+/// #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate)]
+/// struct Foo {
+///     a: usize,
+///     b: String,
+/// }
+///
+/// let prev = Foo { a: 1, b: "hello".to_owned() };
+/// let next = Foo { a: 2, b: "world".to_owned() };
+/// let change = ReversibleChange::data_difference(&prev, &next, &Default::default()).unwrap();
+/// let mut data = prev.clone();
+/// data.patch_change(&change.forward);
+/// assert_eq!(data, next);
+/// data.patch_change(&change.backward);
+/// assert_eq!(data, prev);
+/// ```
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct ReversibleChange {
+    /// Change that turns source value into target value.
+    pub forward: Change,
+    /// Change that turns target value back into source value.
+    pub backward: Change,
+}
+
+impl ReversibleChange {
+    /// Records values replaced by `change` when applied to `source`.
+    pub fn new(change: Change, source: &Intermediate) -> Result<Self> {
+        Ok(Self {
+            backward: change.invert(source)?,
+            forward: change,
+        })
+    }
+
+    pub fn difference(prev: &Intermediate, next: &Intermediate, options: &DiffOptions) -> Self {
+        let forward = Change::difference(prev, next, options);
+        let backward = forward
+            .invert(prev)
+            .unwrap_or_else(|_| Change::difference(next, prev, options));
+        Self { forward, backward }
+    }
+
+    pub fn data_difference<P, N>(prev: &P, next: &N, options: &DiffOptions) -> Result<Self>
+    where
+        P: Serialize,
+        N: Serialize,
+    {
+        let prev = crate::to_intermediate(prev)?;
+        let next = crate::to_intermediate(next)?;
+        Ok(Self::difference(&prev, &next, options))
+    }
+
+    /// Swaps forward and backward changes.
+    pub fn invert(self) -> Self {
+        Self {
+            forward: self.backward,
+            backward: self.forward,
+        }
+    }
+
+    pub fn is_same(&self) -> bool {
+        self.forward.is_same()
+    }
+
+    pub fn total_bytesize(&self) -> usize {
+        self.forward.total_bytesize() + self.backward.total_bytesize()
+    }
+}
+
+/// Item of sequence being composed from consecutive `PartialSeq` changes.
+enum ComposeSeqSlot {
+    /// Item of source sequence with accumulated change.
+    Source(usize, Change),
+    /// Item that was added by changes.
+    Added(Intermediate),
+}
+
+/// Symbolic state of sequence after applying consecutive `PartialSeq` changes,
+/// without knowing source sequence content.
+#[derive(Default)]
+struct ComposeSeq {
+    slots: Vec<ComposeSeqSlot>,
+    removed: Vec<usize>,
+    /// Number of source items accounted for in `slots` and `removed`.
+    consumed: usize,
+}
+
+impl ComposeSeq {
+    fn reach(&mut self, len: usize) {
+        while self.slots.len() < len {
+            self.slots
+                .push(ComposeSeqSlot::Source(self.consumed, Change::Same));
+            self.consumed += 1;
+        }
+    }
+
+    fn apply(&mut self, index: usize, change: &Change) -> Result<()> {
+        match change {
+            Change::Same => {}
+            Change::Removed => {
+                self.reach(index + 1);
+                if let ComposeSeqSlot::Source(source, _) = self.slots.remove(index) {
+                    self.removed.push(source);
+                }
+            }
+            Change::Added(v) => {
+                self.reach(index);
+                self.slots
+                    .insert(index, ComposeSeqSlot::Added(v.to_owned()));
+            }
+            change => {
+                self.reach(index + 1);
+                match &mut self.slots[index] {
+                    ComposeSeqSlot::Source(_, prev) => *prev = prev.compose(change)?,
+                    ComposeSeqSlot::Added(v) => {
+                        if let Some(patched) = change.patch(v)? {
+                            *v = patched;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn into_changes(self) -> Vec<(usize, Change)> {
+        let mut removed = self.removed;
+        removed.sort_unstable();
+        let mut removed = removed.into_iter().peekable();
+        let mut added = vec![];
+        let mut result = vec![];
+        let mut position = 0;
+        for slot in self.slots {
+            match slot {
+                ComposeSeqSlot::Added(v) => added.push(v),
+                ComposeSeqSlot::Source(source, change) => {
+                    while removed.next_if(|index| *index < source).is_some() {
+                        result.push((position, Change::Removed));
+                    }
+                    for v in added.drain(..) {
+                        result.push((position, Change::Added(v)));
+                        position += 1;
+                    }
+                    if !change.is_same() {
+                        result.push((position, change));
+                    }
+                    position += 1;
+                }
+            }
+        }
+        for _ in removed {
+            result.push((position, Change::Removed));
+        }
+        for v in added {
+            result.push((position, Change::Added(v)));
+            position += 1;
+        }
+        result
+    }
+}
+
+struct Merge3<'a, F>
+where
+    F: FnMut(&MergeConflict) -> MergeResolution,
+{
+    options: &'a DiffOptions,
+    resolver: F,
+    conflicts: Vec<MergeConflict>,
+}
+
+impl<F> Merge3<'_, F>
+where
+    F: FnMut(&MergeConflict) -> MergeResolution,
+{
+    fn value(
+        &mut self,
+        path: &IntermediatePath,
+        base: Option<&Intermediate>,
+        ours: Option<&Intermediate>,
+        theirs: Option<&Intermediate>,
+    ) -> Option<Intermediate> {
+        if ours == theirs || theirs == base {
+            return ours.cloned();
+        }
+        if ours == base {
+            return theirs.cloned();
+        }
+        if let (Some(base), Some(ours), Some(theirs)) = (base, ours, theirs) {
+            if self.options.is_filtered_out(path) {
+                return Some(base.to_owned());
+            }
+            if let Some(result) = self.structure(path, base, ours, theirs) {
+                return Some(result);
+            }
+        }
+        // Values that cannot be merged deeper are compared with options.
+        if self.equals(path, ours, theirs) || self.equals(path, theirs, base) {
+            return ours.cloned();
+        }
+        if self.equals(path, ours, base) {
+            return theirs.cloned();
+        }
+        self.resolve(path, base, ours, theirs)
+    }
+
+    fn equals(
+        &self,
+        path: &IntermediatePath,
+        prev: Option<&Intermediate>,
+        next: Option<&Intermediate>,
+    ) -> bool {
+        match (prev, next) {
+            (Some(prev), Some(next)) => {
+                prev == next || self.options.equals(&mut path.to_owned(), prev, next)
+            }
+            (prev, next) => prev == next,
+        }
+    }
+
+    fn resolve(
+        &mut self,
+        path: &IntermediatePath,
+        base: Option<&Intermediate>,
+        ours: Option<&Intermediate>,
+        theirs: Option<&Intermediate>,
+    ) -> Option<Intermediate> {
+        let conflict = MergeConflict {
+            path: path.to_owned(),
+            base: base.cloned(),
+            ours: ours.cloned(),
+            theirs: theirs.cloned(),
+        };
+        match (self.resolver)(&conflict) {
+            MergeResolution::Unresolved => {
+                let result = conflict.base.to_owned();
+                self.conflicts.push(conflict);
+                result
+            }
+            MergeResolution::Base => conflict.base,
+            MergeResolution::Ours => conflict.ours,
+            MergeResolution::Theirs => conflict.theirs,
+            MergeResolution::Value(value) => value,
+        }
+    }
+
+    fn structure(
+        &mut self,
+        path: &IntermediatePath,
+        base: &Intermediate,
+        ours: &Intermediate,
+        theirs: &Intermediate,
+    ) -> Option<Intermediate> {
+        match (base, ours, theirs) {
+            (
+                Intermediate::Option(Some(base)),
+                Intermediate::Option(Some(ours)),
+                Intermediate::Option(Some(theirs)),
+            ) => Some(Intermediate::Option(Some(Box::new(
+                self.inner(path, base, ours, theirs),
+            )))),
+            (
+                Intermediate::NewTypeStruct(base),
+                Intermediate::NewTypeStruct(ours),
+                Intermediate::NewTypeStruct(theirs),
+            ) => Some(Intermediate::NewTypeStruct(Box::new(
+                self.inner(path, base, ours, theirs),
+            ))),
+            (
+                Intermediate::NewTypeVariant(name, base),
+                Intermediate::NewTypeVariant(ours_name, ours),
+                Intermediate::NewTypeVariant(theirs_name, theirs),
+            ) if name == ours_name && name == theirs_name => Some(Intermediate::NewTypeVariant(
+                name.to_owned(),
+                Box::new(self.inner(path, base, ours, theirs)),
+            )),
+            (Intermediate::Seq(base), Intermediate::Seq(ours), Intermediate::Seq(theirs)) => {
+                Some(Intermediate::Seq(self.sequence(path, base, ours, theirs)))
+            }
+            (Intermediate::Tuple(base), Intermediate::Tuple(ours), Intermediate::Tuple(theirs)) => {
+                Some(Intermediate::Tuple(self.sequence(path, base, ours, theirs)))
+            }
+            (
+                Intermediate::TupleStruct(base),
+                Intermediate::TupleStruct(ours),
+                Intermediate::TupleStruct(theirs),
+            ) => Some(Intermediate::TupleStruct(
+                self.sequence(path, base, ours, theirs),
+            )),
+            (
+                Intermediate::TupleVariant(name, base),
+                Intermediate::TupleVariant(ours_name, ours),
+                Intermediate::TupleVariant(theirs_name, theirs),
+            ) if name == ours_name && name == theirs_name => Some(Intermediate::TupleVariant(
+                name.to_owned(),
+                self.sequence(path, base, ours, theirs),
+            )),
+            (Intermediate::Map(base), Intermediate::Map(ours), Intermediate::Map(theirs)) => {
+                Some(Intermediate::Map(self.map(path, base, ours, theirs)))
+            }
+            (
+                Intermediate::Struct(base),
+                Intermediate::Struct(ours),
+                Intermediate::Struct(theirs),
+            ) => Some(Intermediate::Struct(self.fields(path, base, ours, theirs))),
+            (
+                Intermediate::StructVariant(name, base),
+                Intermediate::StructVariant(ours_name, ours),
+                Intermediate::StructVariant(theirs_name, theirs),
+            ) if name == ours_name && name == theirs_name => Some(Intermediate::StructVariant(
+                name.to_owned(),
+                self.fields(path, base, ours, theirs),
+            )),
+            _ => None,
+        }
+    }
+
+    fn inner(
+        &mut self,
+        path: &IntermediatePath,
+        base: &Intermediate,
+        ours: &Intermediate,
+        theirs: &Intermediate,
+    ) -> Intermediate {
+        self.value(
+            &path.to_owned().inner(),
+            Some(base),
+            Some(ours),
+            Some(theirs),
+        )
+        .unwrap_or_else(|| base.to_owned())
+    }
+
+    fn map(
+        &mut self,
+        path: &IntermediatePath,
+        base: &[(Intermediate, Intermediate)],
+        ours: &[(Intermediate, Intermediate)],
+        theirs: &[(Intermediate, Intermediate)],
+    ) -> Vec<(Intermediate, Intermediate)> {
+        fn find<'a>(
+            entries: &'a [(Intermediate, Intermediate)],
+            key: &Intermediate,
+        ) -> Option<&'a Intermediate> {
+            entries.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+        }
+
+        let mut keys = Vec::<&Intermediate>::with_capacity(base.len());
+        for (key, _) in base.iter().chain(ours.iter()).chain(theirs.iter()) {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        keys.into_iter()
+            .filter_map(|key| {
+                let value = self.value(
+                    &path.to_owned().key(key.to_owned()),
+                    find(base, key),
+                    find(ours, key),
+                    find(theirs, key),
+                )?;
+                Some((key.to_owned(), value))
+            })
+            .collect()
+    }
+
+    fn fields(
+        &mut self,
+        path: &IntermediatePath,
+        base: &[(String, Intermediate)],
+        ours: &[(String, Intermediate)],
+        theirs: &[(String, Intermediate)],
+    ) -> Vec<(String, Intermediate)> {
+        fn find<'a>(fields: &'a [(String, Intermediate)], name: &str) -> Option<&'a Intermediate> {
+            fields.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+        }
+
+        let mut names = Vec::<&str>::with_capacity(base.len());
+        for (name, _) in base.iter().chain(ours.iter()).chain(theirs.iter()) {
+            if !names.contains(&name.as_str()) {
+                names.push(name);
+            }
+        }
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let value = self.value(
+                    &path.to_owned().field(name),
+                    find(base, name),
+                    find(ours, name),
+                    find(theirs, name),
+                )?;
+                Some((name.to_owned(), value))
+            })
+            .collect()
+    }
+
+    fn sequence(
+        &mut self,
+        path: &IntermediatePath,
+        base: &[Intermediate],
+        ours: &[Intermediate],
+        theirs: &[Intermediate],
+    ) -> Vec<Intermediate> {
+        let matches = match (
+            self.sequence_keys(base),
+            self.sequence_keys(ours),
+            self.sequence_keys(theirs),
+        ) {
+            (Some(base), Some(ours), Some(theirs)) => {
+                let options = DiffOptions::default();
+                let path = IntermediatePath::default();
+                (
+                    sequence_matches(&base, &ours, &options, &path),
+                    sequence_matches(&base, &theirs, &options, &path),
+                )
+            }
+            _ => (
+                sequence_matches(base, ours, self.options, path),
+                sequence_matches(base, theirs, self.options, path),
+            ),
+        };
+        let mut ours_matches = vec![None; base.len()];
+        for (index, matched) in matches.0 {
+            ours_matches[index] = Some(matched);
+        }
+        let mut theirs_matches = vec![None; base.len()];
+        for (index, matched) in matches.1 {
+            theirs_matches[index] = Some(matched);
+        }
+        let mut result = Vec::with_capacity(base.len().max(ours.len()).max(theirs.len()));
+        let (mut base_start, mut ours_start, mut theirs_start) = (0, 0, 0);
+        for index in 0..=base.len() {
+            let sync = if index == base.len() {
+                Some((ours.len(), theirs.len()))
+            } else {
+                ours_matches[index].zip(theirs_matches[index])
+            };
+            if let Some((ours_index, theirs_index)) = sync {
+                self.region(
+                    path,
+                    &mut result,
+                    &base[base_start..index],
+                    &ours[ours_start..ours_index],
+                    &theirs[theirs_start..theirs_index],
+                );
+                if let Some(item) = base.get(index) {
+                    // Matched items are same or share key, so they still can differ.
+                    let path = path.to_owned().index(result.len());
+                    if let Some(item) = self.value(
+                        &path,
+                        Some(item),
+                        Some(&ours[ours_index]),
+                        Some(&theirs[theirs_index]),
+                    ) {
+                        result.push(item);
+                    }
+                }
+                base_start = index + 1;
+                ours_start = ours_index + 1;
+                theirs_start = theirs_index + 1;
+            }
+        }
+        result
+    }
+
+    /// Extracts `DiffOptions::sequence_key` of items, fails if any item has no key or keys are
+    /// not unique.
+    fn sequence_keys(&self, items: &[Intermediate]) -> Option<Vec<Intermediate>> {
+        let key = self.options.sequence_key.as_ref()?;
+        let keys = items
+            .iter()
+            .map(|item| key.extract(item))
+            .collect::<Option<Vec<_>>>()?;
+        unique_lookup(&keys)?;
+        Some(keys)
+    }
+
+    fn region(
+        &mut self,
+        path: &IntermediatePath,
+        result: &mut Vec<Intermediate>,
+        base: &[Intermediate],
+        ours: &[Intermediate],
+        theirs: &[Intermediate],
+    ) {
+        let mut items_path = path.to_owned();
+        let mut equals = |prev: &[Intermediate], next: &[Intermediate]| {
+            prev == next || self.options.items_equal(&mut items_path, prev, next)
+        };
+        if equals(ours, theirs) || equals(theirs, base) {
+            result.extend(ours.iter().cloned());
+        } else if equals(ours, base) {
+            result.extend(theirs.iter().cloned());
+        } else if base.len() == ours.len() && base.len() == theirs.len() {
+            for ((base, ours), theirs) in base.iter().zip(ours.iter()).zip(theirs.iter()) {
+                let path = path.to_owned().index(result.len());
+                if let Some(item) = self.value(&path, Some(base), Some(ours), Some(theirs)) {
+                    result.push(item);
+                }
+            }
+        } else {
+            let path = path.to_owned().index(result.len());
+            match self.resolve(
+                &path,
+                Some(&Intermediate::Seq(base.to_owned())),
+                Some(&Intermediate::Seq(ours.to_owned())),
+                Some(&Intermediate::Seq(theirs.to_owned())),
+            ) {
+                Some(Intermediate::Seq(items)) => result.extend(items),
+                Some(item) => result.push(item),
+                None => {}
+            }
+        }
+    }
+}
+
+/// Maps items to their indices, fails if items are not unique.
+fn unique_lookup<T: Eq + Hash>(items: &[T]) -> Option<HashMap<&T, usize>> {
+    let mut result = HashMap::with_capacity(items.len());
+    for (index, item) in items.iter().enumerate() {
+        if result.insert(item, index).is_some() {
+            return None;
+        }
+    }
+    Some(result)
+}
+
+/// Marks items forming longest strictly increasing subsequence.
+fn longest_increasing_subsequence(values: &[usize]) -> Vec<bool> {
+    let mut tails = Vec::<usize>::new();
+    let mut parents = vec![None; values.len()];
+    for (index, value) in values.iter().enumerate() {
+        let position = tails.partition_point(|tail| values[*tail] < *value);
+        if position > 0 {
+            parents[index] = Some(tails[position - 1]);
+        }
+        if position == tails.len() {
+            tails.push(index);
+        } else {
+            tails[position] = index;
+        }
+    }
+    let mut result = vec![false; values.len()];
+    let mut current = tails.last().copied();
+    while let Some(index) = current {
+        result[index] = true;
+        current = parents[index];
+    }
+    result
+}
+
+/// Position right after item that precedes `target` in target sequence.
+fn insert_position(current: &[usize], target: usize) -> usize {
+    if target == 0 {
+        0
+    } else {
+        current
+            .iter()
+            .position(|t| *t == target - 1)
+            .map(|index| index + 1)
+            .unwrap_or(current.len())
+    }
+}
+
+/// Finds longest common subsequence of two sequences as list of matching `(prev, next)` indices.
+///
+/// Uses Myers' difference algorithm with linear space refinement, so memory usage stays
+/// proportional to sequences length. Items are compared by hash first, unless options
+/// use inexact comparison.
+fn sequence_matches(
+    prev: &[Intermediate],
+    next: &[Intermediate],
+    options: &DiffOptions,
+    path: &IntermediatePath,
+) -> Vec<(usize, usize)> {
+    let exact = options.is_exact();
+    let hashes = |items: &[Intermediate]| {
+        if exact {
+            items.iter().map(hash_intermediate).collect()
+        } else {
+            vec![]
+        }
+    };
+    let mut lcs = SequenceMatcher {
+        prev,
+        next,
+        prev_hashes: hashes(prev),
+        next_hashes: hashes(next),
+        options,
+        path,
+        result: Vec::with_capacity(prev.len().min(next.len())),
+    };
+    lcs.matches(0, prev.len(), 0, next.len());
+    lcs.result
+}
+
+fn with_segment<T>(
+    path: &mut IntermediatePath,
+    segment: IntermediatePathSegment,
+    f: impl FnOnce(&mut IntermediatePath) -> T,
+) -> T {
+    path.push(segment);
+    let result = f(path);
+    path.pop();
+    result
+}
+
+fn hash_intermediate(value: &Intermediate) -> u64 {
+    let mut state = DefaultHasher::new();
+    value.hash(&mut state);
+    state.finish()
+}
+
+struct SequenceMatcher<'a> {
+    prev: &'a [Intermediate],
+    next: &'a [Intermediate],
+    prev_hashes: Vec<u64>,
+    next_hashes: Vec<u64>,
+    options: &'a DiffOptions,
+    path: &'a IntermediatePath,
+    result: Vec<(usize, usize)>,
+}
+
+impl SequenceMatcher<'_> {
+    fn equals(&self, prev: usize, next: usize) -> bool {
+        if self.prev_hashes.is_empty() {
+            let mut path = self.path.to_owned().index(prev);
+            self.options
+                .equals(&mut path, &self.prev[prev], &self.next[next])
+        } else {
+            self.prev_hashes[prev] == self.next_hashes[next] && self.prev[prev] == self.next[next]
+        }
+    }
+
+    fn matches(
+        &mut self,
+        mut prev_start: usize,
+        mut prev_end: usize,
+        mut next_start: usize,
+        mut next_end: usize,
+    ) {
+        while prev_start < prev_end && next_start < next_end && self.equals(prev_start, next_start)
+        {
+            self.result.push((prev_start, next_start));
+            prev_start += 1;
+            next_start += 1;
+        }
+        let mut suffix = 0;
+        while prev_start < prev_end
+            && next_start < next_end
+            && self.equals(prev_end - 1, next_end - 1)
+        {
+            prev_end -= 1;
+            next_end -= 1;
+            suffix += 1;
+        }
+        if prev_start < prev_end && next_start < next_end {
+            if let Some((prev_split, next_split)) =
+                self.split(prev_start, prev_end, next_start, next_end)
+            {
+                self.matches(prev_start, prev_split, next_start, next_split);
+                self.matches(prev_split, prev_end, next_split, next_end);
+            }
+        }
+        for offset in 0..suffix {
+            self.result.push((prev_end + offset, next_end + offset));
+        }
+    }
+
+    /// Finds point where forward and backward searches of shortest edit path meet.
+    fn split(
+        &self,
+        prev_start: usize,
+        prev_end: usize,
+        next_start: usize,
+        next_end: usize,
+    ) -> Option<(usize, usize)> {
+        let prev_len = (prev_end - prev_start) as isize;
+        let next_len = (next_end - next_start) as isize;
+        let max_d = (prev_len + next_len + 1) / 2;
+        let offset = max_d;
+        let size = 2 * max_d as usize + 2;
+        let mut forward = vec![-1isize; size];
+        let mut backward = vec![-1isize; size];
+        forward[offset as usize + 1] = 0;
+        backward[offset as usize + 1] = 0;
+        let delta = prev_len - next_len;
+        let front = delta % 2 != 0;
+        let (mut k1_start, mut k1_end, mut k2_start, mut k2_end) = (0, 0, 0, 0);
+        for d in 0..max_d {
+            let mut k1 = -d + k1_start;
+            while k1 <= d - k1_end {
+                let k1_offset = (offset + k1) as usize;
+                let mut x1 =
+                    if k1 == -d || (k1 != d && forward[k1_offset - 1] < forward[k1_offset + 1]) {
+                        forward[k1_offset + 1]
+                    } else {
+                        forward[k1_offset - 1] + 1
+                    };
+                let mut y1 = x1 - k1;
+                while x1 < prev_len
+                    && y1 < next_len
+                    && self.equals(prev_start + x1 as usize, next_start + y1 as usize)
+                {
+                    x1 += 1;
+                    y1 += 1;
+                }
+                forward[k1_offset] = x1;
+                if x1 > prev_len {
+                    k1_end += 2;
+                } else if y1 > next_len {
+                    k1_start += 2;
+                } else if front {
+                    let k2_offset = offset + delta - k1;
+                    if k2_offset >= 0
+                        && (k2_offset as usize) < size
+                        && backward[k2_offset as usize] != -1
+                        && x1 >= prev_len - backward[k2_offset as usize]
+                    {
+                        return Some((prev_start + x1 as usize, next_start + y1 as usize));
+                    }
+                }
+                k1 += 2;
+            }
+            let mut k2 = -d + k2_start;
+            while k2 <= d - k2_end {
+                let k2_offset = (offset + k2) as usize;
+                let mut x2 =
+                    if k2 == -d || (k2 != d && backward[k2_offset - 1] < backward[k2_offset + 1]) {
+                        backward[k2_offset + 1]
+                    } else {
+                        backward[k2_offset - 1] + 1
+                    };
+                let mut y2 = x2 - k2;
+                while x2 < prev_len
+                    && y2 < next_len
+                    && self.equals(prev_end - 1 - x2 as usize, next_end - 1 - y2 as usize)
+                {
+                    x2 += 1;
+                    y2 += 1;
+                }
+                backward[k2_offset] = x2;
+                if x2 > prev_len {
+                    k2_end += 2;
+                } else if y2 > next_len {
+                    k2_start += 2;
+                } else if !front {
+                    let k1_offset = offset + delta - k2;
+                    if k1_offset >= 0 && (k1_offset as usize) < size {
+                        let x1 = forward[k1_offset as usize];
+                        if x1 != -1 && x1 >= prev_len - x2 {
+                            let y1 = offset + x1 - k1_offset;
+                            return Some((prev_start + x1 as usize, next_start + y1 as usize));
+                        }
+                    }
+                }
+                k2 += 2;
+            }
+        }
+        None
+    }
+}
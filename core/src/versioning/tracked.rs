@@ -0,0 +1,285 @@
+use crate::{
+    error::*,
+    ser::intermediate::serialize as to_intermediate,
+    value::{
+        intermediate::Intermediate,
+        path::{IntermediatePath, IntermediatePathSegment},
+    },
+    versioning::Change,
+};
+use serde::Serialize;
+use std::ops::{Deref, DerefMut};
+
+/// Wrapper over value that records changes of its parts mutated through guards, so they don't
+/// have to be found by diffing whole value afterwards.
+///
+/// Recorded change can be applied to other copy of value with `ReflectIntermediate::patch_change`.
+/// If mutated parts can't be put together (for example when paths don't match layout of value),
+/// whole value is reported as changed.
+///
+/// # Example
+/// ```rust
+/// use serde::{Serialize, Deserialize};
+/// use serde_intermediate::{Change, ReflectIntermediate, Tracked};
+///
+/// #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, ReflectIntermediate)]
+/// struct Foo {
+///     a: bool,
+///     b: Vec<usize>,
+/// }
+///
+/// let mut remote = Foo { a: false, b: vec![1, 2, 3] };
+/// let mut tracked = Tracked::new(remote.clone());
+/// *tracked.write_at("a".parse().unwrap(), |v| &mut v.a) = true;
+/// *tracked.write_at("b[1]".parse().unwrap(), |v| &mut v.b[1]) = 42;
+/// let change = tracked.take_change().unwrap();
+/// assert_eq!(
+///     change,
+///     Change::partial_struct()
+///         .partial_struct_item("a", Change::changed(true))
+///         .partial_struct_item(
+///             "b",
+///             Change::partial_seq().partial_seq_item(1, Change::changed(42usize)),
+///         ),
+/// );
+/// remote.patch_change(&change);
+/// assert_eq!(&remote, tracked.value());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Tracked<T> {
+    value: T,
+    change: Change,
+    invalid: bool,
+}
+
+impl<T> Default for Tracked<T>
+where
+    T: Default,
+{
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> Tracked<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            change: Change::Same,
+            invalid: false,
+        }
+    }
+
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    /// Tells if any part of value was mutated since last taken change.
+    pub fn is_changed(&self) -> bool {
+        self.invalid || !self.change.is_same()
+    }
+
+    /// Forgets recorded change, treating current value as unchanged.
+    pub fn reset(&mut self) {
+        self.change = Change::Same;
+        self.invalid = false;
+    }
+}
+
+impl<T> Tracked<T>
+where
+    T: Serialize,
+{
+    /// Gives mutable access to whole value, recorded as its replacement.
+    pub fn write(&mut self) -> TrackedMut<'_, T> {
+        self.write_at(Default::default(), |value| value)
+    }
+
+    /// Gives mutable access to part of value found under `path`.
+    ///
+    /// `access` has to point to the same part as `path` does in serialized value, otherwise
+    /// recorded change will apply mutation to wrong part of value.
+    pub fn write_at<F>(
+        &mut self,
+        path: IntermediatePath,
+        access: impl FnOnce(&mut T) -> &mut F,
+    ) -> TrackedMut<'_, F>
+    where
+        F: Serialize,
+    {
+        TrackedMut {
+            value: access(&mut self.value),
+            path,
+            change: &mut self.change,
+            invalid: &mut self.invalid,
+            written: false,
+        }
+    }
+
+    /// Change recorded since last taken change.
+    pub fn change(&self) -> Result<Change> {
+        if self.invalid {
+            Ok(Change::Changed(to_intermediate(&self.value)?))
+        } else {
+            Ok(self.change.to_owned())
+        }
+    }
+
+    /// Takes change recorded so far and starts recording from current value.
+    pub fn take_change(&mut self) -> Result<Change> {
+        let result = self.change()?;
+        self.reset();
+        Ok(result)
+    }
+}
+
+impl<T> Deref for Tracked<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T> From<T> for Tracked<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+/// Guarded mutable access to part of `Tracked` value.
+/// Change of that part gets recorded when guard is dropped, unless it wasn't mutably accessed.
+pub struct TrackedMut<'a, T>
+where
+    T: Serialize,
+{
+    value: &'a mut T,
+    path: IntermediatePath,
+    change: &'a mut Change,
+    invalid: &'a mut bool,
+    written: bool,
+}
+
+impl<T> TrackedMut<'_, T>
+where
+    T: Serialize,
+{
+    pub fn path(&self) -> &IntermediatePath {
+        &self.path
+    }
+}
+
+impl<T> Deref for TrackedMut<'_, T>
+where
+    T: Serialize,
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<T> DerefMut for TrackedMut<'_, T>
+where
+    T: Serialize,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.written = true;
+        self.value
+    }
+}
+
+impl<T> Drop for TrackedMut<'_, T>
+where
+    T: Serialize,
+{
+    fn drop(&mut self) {
+        if !self.written || *self.invalid {
+            return;
+        }
+        let recorded = match to_intermediate(&*self.value) {
+            Ok(value) => record(self.change, self.path.segments(), value),
+            Err(_) => false,
+        };
+        if !recorded {
+            *self.invalid = true;
+        }
+    }
+}
+
+/// Puts replacement of value under `path` into `change`.
+/// Returns false if `change` doesn't describe value that has this path.
+fn record(change: &mut Change, path: &[IntermediatePathSegment], value: Intermediate) -> bool {
+    let (segment, rest) = match path.split_first() {
+        Some(found) => found,
+        None => {
+            *change = Change::Changed(value);
+            return true;
+        }
+    };
+    match (change, segment) {
+        (change @ Change::Same, _) => {
+            *change = nested(path, value);
+            true
+        }
+        (Change::Changed(target) | Change::Added(target), _) => {
+            match nested(path, value).patch_strict(target) {
+                Ok(Some(patched)) => {
+                    *target = patched;
+                    true
+                }
+                _ => false,
+            }
+        }
+        (Change::PartialChange(change), IntermediatePathSegment::Inner) => {
+            record(change, rest, value)
+        }
+        (Change::PartialSeq(items), IntermediatePathSegment::Index(index)) => {
+            record_entry(items, index, rest, value)
+        }
+        (Change::PartialMap(entries), IntermediatePathSegment::Key(key)) => {
+            record_entry(entries, key, rest, value)
+        }
+        (Change::PartialStruct(fields), IntermediatePathSegment::Field(name)) => {
+            record_entry(fields, name, rest, value)
+        }
+        _ => false,
+    }
+}
+
+fn record_entry<K>(
+    entries: &mut Vec<(K, Change)>,
+    key: &K,
+    path: &[IntermediatePathSegment],
+    value: Intermediate,
+) -> bool
+where
+    K: Clone + PartialEq,
+{
+    match entries.iter_mut().find(|(k, _)| k == key) {
+        Some((_, change)) => record(change, path, value),
+        None => {
+            entries.push((key.to_owned(), nested(path, value)));
+            true
+        }
+    }
+}
+
+/// Change replacing value under `path`.
+fn nested(path: &[IntermediatePathSegment], value: Intermediate) -> Change {
+    path.iter()
+        .rev()
+        .fold(Change::Changed(value), |change, segment| match segment {
+            IntermediatePathSegment::Index(index) => Change::PartialSeq(vec![(*index, change)]),
+            IntermediatePathSegment::Key(key) => Change::PartialMap(vec![(key.to_owned(), change)]),
+            IntermediatePathSegment::Field(name) => {
+                Change::PartialStruct(vec![(name.to_owned(), change)])
+            }
+            IntermediatePathSegment::Inner => Change::PartialChange(Box::new(change)),
+        })
+}
@@ -0,0 +1,327 @@
+use crate::{
+    reflect::parse_field_attribs,
+    serde_attribs::{SerdeFieldAttribs, SerdeTypeAttribs, SerdeVariantAttribs},
+};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Field, Fields, GenericParam, Index};
+
+pub fn derive_intermediate(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    derive_diff(&parse_macro_input!(input as DeriveInput)).into()
+}
+
+fn derive_diff(ast: &DeriveInput) -> TokenStream {
+    let name = &ast.ident;
+    let mut generics = ast.generics.clone();
+    let params = generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(param) => Some(param.ident.to_owned()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let where_clause = generics.make_where_clause();
+    for param in params {
+        where_clause
+            .predicates
+            .push(parse_quote! { #param: serde_intermediate::DiffIntermediate });
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let serde = SerdeTypeAttribs::parse(&ast.attrs);
+    let body = if serde.opaque || !serde.is_externally_tagged() {
+        None
+    } else if serde.transparent {
+        transparent_diff_body(&ast.data)
+    } else {
+        diff_body(&ast.data, &serde)
+    };
+    let body = body.map(|body| {
+        quote! {
+            fn diff_with(
+                &self,
+                other: &Self,
+                options: &serde_intermediate::DiffOptions,
+            ) -> serde_intermediate::error::Result<serde_intermediate::Change> {
+                if !options.is_default() {
+                    return serde_intermediate::Change::data_difference(self, other, options);
+                }
+                #body
+            }
+        }
+    });
+    quote! {
+        impl #impl_generics serde_intermediate::DiffIntermediate for #name #ty_generics #where_clause {
+            #body
+        }
+    }
+}
+
+/// Body of typed diff of `#[serde(transparent)]` struct, which is diff of its serialized field.
+fn transparent_diff_body(data: &Data) -> Option<TokenStream> {
+    let fields = match data {
+        Data::Struct(data) => &data.fields,
+        _ => return None,
+    };
+    let mut serialized = fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| !SerdeFieldAttribs::parse(field, Default::default()).skip);
+    let (index, field) = serialized.next()?;
+    let member = match &field.ident {
+        Some(name) => name.to_token_stream(),
+        None => Index::from(index).to_token_stream(),
+    };
+    Some(field_diff_result(
+        field,
+        quote! { &self.#member },
+        quote! { &other.#member },
+    ))
+}
+
+/// Body of typed diff, or `None` if serialized layout can't be told from type definition.
+fn diff_body(data: &Data, serde: &SerdeTypeAttribs) -> Option<TokenStream> {
+    match data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => {
+                let mut items = vec![];
+                for field in &fields.named {
+                    let serde_attribs = SerdeFieldAttribs::parse(field, serde.rename_all);
+                    if serde_attribs.opaque || serde_attribs.flatten {
+                        return None;
+                    }
+                    if serde_attribs.skip {
+                        continue;
+                    }
+                    let name = field.ident.as_ref().unwrap();
+                    items.push(field_diff(
+                        field,
+                        serde_attribs.name.to_token_stream(),
+                        quote! { &self.#name },
+                        quote! { &other.#name },
+                    ));
+                }
+                Some(quote! {
+                    let mut result = vec![];
+                    #( #items )*
+                    if result.is_empty() {
+                        Ok(serde_intermediate::Change::Same)
+                    } else {
+                        Ok(serde_intermediate::Change::PartialStruct(result))
+                    }
+                })
+            }
+            Fields::Unnamed(fields) => {
+                if !is_plain_tuple(&fields.unnamed) {
+                    return None;
+                }
+                if fields.unnamed.len() == 1 {
+                    let field = &fields.unnamed[0];
+                    let change = single_field_diff(field, quote! { &self.0 }, quote! { &other.0 });
+                    Some(quote! {
+                        let change = #change;
+                        if change.is_same() {
+                            Ok(serde_intermediate::Change::Same)
+                        } else {
+                            Ok(serde_intermediate::Change::PartialChange(Box::new(change)))
+                        }
+                    })
+                } else {
+                    let items = fields.unnamed.iter().enumerate().map(|(index, field)| {
+                        let member = Index::from(index);
+                        let change = single_field_diff(
+                            field,
+                            quote! { &self.#member },
+                            quote! { &other.#member },
+                        );
+                        quote! {
+                            let change = #change;
+                            if !change.is_same() {
+                                result.push((#index, change));
+                            }
+                        }
+                    });
+                    Some(quote! {
+                        let mut result = vec![];
+                        #( #items )*
+                        if result.is_empty() {
+                            Ok(serde_intermediate::Change::Same)
+                        } else {
+                            Ok(serde_intermediate::Change::PartialSeq(result))
+                        }
+                    })
+                }
+            }
+            Fields::Unit => Some(quote! { Ok(serde_intermediate::Change::Same) }),
+        },
+        Data::Enum(data) => {
+            let mut variants = vec![];
+            for variant in &data.variants {
+                let serde_attribs = SerdeVariantAttribs::parse(variant, serde);
+                if serde_attribs.opaque || serde_attribs.skip {
+                    return None;
+                }
+                let name = &variant.ident;
+                if parse_field_attribs(&variant.attrs).ignore {
+                    variants.push(quote! {
+                        (Self::#name { .. }, Self::#name { .. }) => {
+                            serde_intermediate::Change::data_difference(self, other, options)
+                        }
+                    });
+                    continue;
+                }
+                match &variant.fields {
+                    Fields::Named(fields) => {
+                        let mut prev_bindings = vec![];
+                        let mut next_bindings = vec![];
+                        let mut items = vec![];
+                        for field in &fields.named {
+                            let field_serde_attribs =
+                                SerdeFieldAttribs::parse(field, serde_attribs.rename_all);
+                            if field_serde_attribs.opaque || field_serde_attribs.flatten {
+                                return None;
+                            }
+                            if field_serde_attribs.skip {
+                                continue;
+                            }
+                            let name = field.ident.as_ref().unwrap();
+                            let prev = format_ident!("__prev_{}", name);
+                            let next = format_ident!("__next_{}", name);
+                            prev_bindings.push(quote! { #name: #prev });
+                            next_bindings.push(quote! { #name: #next });
+                            items.push(field_diff(
+                                field,
+                                field_serde_attribs.name.to_token_stream(),
+                                prev.to_token_stream(),
+                                next.to_token_stream(),
+                            ));
+                        }
+                        variants.push(quote! {
+                            (
+                                Self::#name { #( #prev_bindings, )* .. },
+                                Self::#name { #( #next_bindings, )* .. },
+                            ) => {
+                                let mut result = vec![];
+                                #( #items )*
+                                if result.is_empty() {
+                                    Ok(serde_intermediate::Change::Same)
+                                } else {
+                                    Ok(serde_intermediate::Change::PartialStruct(result))
+                                }
+                            }
+                        });
+                    }
+                    Fields::Unnamed(fields) => {
+                        if !is_plain_tuple(&fields.unnamed) {
+                            return None;
+                        }
+                        let prev_bindings = (0..fields.unnamed.len())
+                            .map(|index| format_ident!("__prev_{}", index))
+                            .collect::<Vec<_>>();
+                        let next_bindings = (0..fields.unnamed.len())
+                            .map(|index| format_ident!("__next_{}", index))
+                            .collect::<Vec<_>>();
+                        if fields.unnamed.len() == 1 {
+                            let change = single_field_diff(
+                                &fields.unnamed[0],
+                                quote! { __prev_0 },
+                                quote! { __next_0 },
+                            );
+                            variants.push(quote! {
+                                (Self::#name(__prev_0), Self::#name(__next_0)) => {
+                                    let change = #change;
+                                    if change.is_same() {
+                                        Ok(serde_intermediate::Change::Same)
+                                    } else {
+                                        Ok(serde_intermediate::Change::PartialChange(Box::new(change)))
+                                    }
+                                }
+                            });
+                        } else {
+                            let items = fields.unnamed.iter().enumerate().map(|(index, field)| {
+                                let change = single_field_diff(
+                                    field,
+                                    prev_bindings[index].to_token_stream(),
+                                    next_bindings[index].to_token_stream(),
+                                );
+                                quote! {
+                                    let change = #change;
+                                    if !change.is_same() {
+                                        result.push((#index, change));
+                                    }
+                                }
+                            });
+                            variants.push(quote! {
+                                (
+                                    Self::#name( #( #prev_bindings ),* ),
+                                    Self::#name( #( #next_bindings ),* ),
+                                ) => {
+                                    let mut result = vec![];
+                                    #( #items )*
+                                    if result.is_empty() {
+                                        Ok(serde_intermediate::Change::Same)
+                                    } else {
+                                        Ok(serde_intermediate::Change::PartialSeq(result))
+                                    }
+                                }
+                            });
+                        }
+                    }
+                    Fields::Unit => {
+                        variants.push(quote! {
+                            (Self::#name, Self::#name) => Ok(serde_intermediate::Change::Same),
+                        });
+                    }
+                }
+            }
+            Some(quote! {
+                match (self, other) {
+                    #( #variants )*
+                    #[allow(unreachable_patterns)]
+                    _ => Ok(serde_intermediate::Change::Changed(
+                        serde_intermediate::to_intermediate(other)?,
+                    )),
+                }
+            })
+        }
+        Data::Union(_) => None,
+    }
+}
+
+/// Diff of named field, pushed into `result` list of field changes.
+fn field_diff(
+    field: &Field,
+    key: TokenStream,
+    prev: TokenStream,
+    next: TokenStream,
+) -> TokenStream {
+    let change = single_field_diff(field, prev, next);
+    quote! {
+        let change = #change;
+        if !change.is_same() {
+            result.push((#key.to_owned(), change));
+        }
+    }
+}
+
+fn single_field_diff(field: &Field, prev: TokenStream, next: TokenStream) -> TokenStream {
+    let result = field_diff_result(field, prev, next);
+    quote! { #result? }
+}
+
+fn field_diff_result(field: &Field, prev: TokenStream, next: TokenStream) -> TokenStream {
+    let attribs = parse_field_attribs(&field.attrs);
+    if attribs.ignore || attribs.indirect {
+        quote! { serde_intermediate::Change::data_difference(#prev, #next, options) }
+    } else {
+        quote! { serde_intermediate::DiffIntermediate::diff_with(#prev, #next, options) }
+    }
+}
+
+/// Tells if unnamed fields are serialized as they are, without any skipped.
+fn is_plain_tuple<'a>(fields: impl IntoIterator<Item = &'a Field>) -> bool {
+    fields.into_iter().all(|field| {
+        let attribs = SerdeFieldAttribs::parse(field, Default::default());
+        !attribs.opaque && !attribs.skip
+    })
+}
@@ -1,7 +1,11 @@
+#![allow(clippy::collapsible_match)]
+
 extern crate proc_macro;
 
+mod diff;
 mod reflect;
 mod schema;
+mod serde_attribs;
 
 use proc_macro::TokenStream;
 
@@ -10,6 +14,11 @@ pub fn derive_reflect_intermediate(input: TokenStream) -> TokenStream {
     crate::reflect::derive_intermediate(input)
 }
 
+#[proc_macro_derive(DiffIntermediate, attributes(reflect_intermediate))]
+pub fn derive_diff_intermediate(input: TokenStream) -> TokenStream {
+    crate::diff::derive_intermediate(input)
+}
+
 #[proc_macro_derive(SchemaIntermediate, attributes(schema_intermediate))]
 pub fn derive_schema_intermediate(input: TokenStream) -> TokenStream {
     crate::schema::derive_intermediate(input)
@@ -1,23 +1,72 @@
+use crate::serde_attribs::{RenameRule, SerdeFieldAttribs, SerdeTypeAttribs, SerdeVariantAttribs};
 use proc_macro::{Span, TokenStream};
 use quote::{quote, ToTokens};
 use syn::{
-    parse_macro_input, Attribute, Data, DeriveInput, Fields, Ident, Index, Lit, Meta, NestedMeta,
+    parse_macro_input, Attribute, Data, DataEnum, DeriveInput, Fields, FieldsNamed, FieldsUnnamed,
+    Ident, Index, Lit, Meta, NestedMeta, WherePredicate,
 };
 
 #[derive(Debug, Default)]
 struct TypeAttribs {
     before_patch_change: Option<Ident>,
     after_patch_change: Option<Ident>,
+    /// Type is `Clone`, so it can be patched copy-on-write when shared.
+    clone: bool,
 }
 
 #[derive(Debug, Default)]
-struct FieldAttribs {
-    ignore: bool,
-    indirect: bool,
+pub(crate) struct FieldAttribs {
+    pub ignore: bool,
+    pub indirect: bool,
+    pub on_change: Option<Ident>,
+}
+
+/// Patching hooks code shared by all kinds of implementations.
+struct Hooks {
+    /// `before_patch_change`, `after_patch_change` and `clone_for_patch` methods.
+    methods: proc_macro2::TokenStream,
+    /// Statements starting and ending `patch_change_with_report`, which call `on_change` hooks
+    /// of fields affected by applied changes.
+    on_change_start: proc_macro2::TokenStream,
+    on_change_end: proc_macro2::TokenStream,
+}
+
+impl Hooks {
+    fn new(
+        methods: proc_macro2::TokenStream,
+        on_change: Vec<(Ident, Vec<proc_macro2::TokenStream>)>,
+    ) -> Self {
+        if on_change.is_empty() {
+            return Self {
+                methods,
+                on_change_start: Default::default(),
+                on_change_end: Default::default(),
+            };
+        }
+        let calls = on_change.into_iter().map(|(hook, paths)| {
+            quote! {
+                if #( report.is_changed_since(applied, &#paths) )||* {
+                    self.#hook();
+                }
+            }
+        });
+        Self {
+            methods,
+            on_change_start: quote! {
+                let applied = report.applied.len();
+            },
+            on_change_end: quote! {
+                #( #calls )*
+            },
+        }
+    }
 }
 
 pub fn derive_intermediate(input: TokenStream) -> TokenStream {
-    let ast = parse_macro_input!(input as DeriveInput);
+    derive_reflect(parse_macro_input!(input as DeriveInput))
+}
+
+fn derive_reflect(mut ast: DeriveInput) -> TokenStream {
     let attribs = parse_type_attribs(&ast.attrs);
     let before_patch_change = match attribs.before_patch_change {
         Some(name) => {
@@ -39,282 +88,734 @@ pub fn derive_intermediate(input: TokenStream) -> TokenStream {
         }
         None => Default::default(),
     };
-    let name = &ast.ident;
-    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
-    match ast.data {
-        Data::Struct(data) => match data.fields {
+    let clone_for_patch = if attribs.clone {
+        let name = &ast.ident;
+        let (_, ty_generics, _) = ast.generics.split_for_impl();
+        let predicate: WherePredicate = syn::parse_quote! { #name #ty_generics: Clone };
+        ast.generics.make_where_clause().predicates.push(predicate);
+        quote! {
+            fn clone_for_patch(&self) -> Option<Self> {
+                Some(::std::clone::Clone::clone(self))
+            }
+        }
+    } else {
+        Default::default()
+    };
+    let serde = SerdeTypeAttribs::parse(&ast.attrs);
+    let hooks = Hooks::new(
+        quote! {
+            #before_patch_change
+
+            #after_patch_change
+
+            #clone_for_patch
+        },
+        on_change_hooks(&ast, &serde),
+    );
+    if serde.opaque || serde.untagged || has_opaque_fields(&ast.data) {
+        return derive_indirect(&ast, &hooks);
+    }
+    if serde.transparent {
+        return derive_transparent(&ast, &hooks);
+    }
+    match &ast.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields)
+                if fields
+                    .named
+                    .iter()
+                    .any(|field| SerdeFieldAttribs::parse(field, serde.rename_all).flatten) =>
+            {
+                return derive_flattened(&ast, fields, &serde, &hooks);
+            }
+            _ => {}
+        },
+        Data::Enum(data) if serde.tag.is_some() => {
+            return derive_tagged(&ast, data, &serde, &hooks);
+        }
+        _ => {}
+    }
+    match &ast.data {
+        Data::Struct(data) => match &data.fields {
             Fields::Named(fields) => {
-                let fields = fields.named.iter().filter_map(|field| {
-                    let attribs = parse_field_attribs(&field.attrs);
-                    if attribs.ignore {
-                        return None;
-                    }
-                    let name = field.ident.as_ref().unwrap();
-                    let key = name.to_string();
-                    if attribs.indirect {
-                        Some(quote! {
-                            #key => {
-                                if let Ok(serialized) = serde_intermediate::to_intermediate(&self.#name) {
-                                    if let Ok(Some(patched)) = change.patch(&serialized) {
-                                        if let Ok(deserialized) = serde_intermediate::from_intermediate(&patched) {
-                                            self.#name = deserialized;
-                                        }
-                                    }
-                                }
-                            }
-                        })
-                    } else {
-                        Some(quote! {
-                            #key => {
-                                self.#name.patch_change(change);
-                            }
-                        })
-                    }
-                }).collect::<Vec<_>>();
-                quote! {
-                    impl #impl_generics serde_intermediate::ReflectIntermediate for #name #ty_generics #where_clause {
-                        fn patch_change(&mut self, change: &serde_intermediate::Change) {
-                            self.before_patch_change();
-                            match change {
-                                serde_intermediate::Change::Changed(v) => {
-                                    if let Ok(v) = serde_intermediate::from_intermediate(v) {
-                                        *self = v;
-                                    }
-                                }
-                                serde_intermediate::Change::PartialStruct(v) => {
-                                    for (name, change) in v {
-                                        match name.as_str() {
-                                            #( #fields )*
-                                            _ => {}
-                                        }
-                                    }
-                                }
-                                _ => {}
+                let items = fields
+                    .named
+                    .iter()
+                    .filter_map(|field| {
+                        let serde_attribs = SerdeFieldAttribs::parse(field, serde.rename_all);
+                        if serde_attribs.skip {
+                            return None;
+                        }
+                        let name = field.ident.as_ref().unwrap();
+                        let key = serde_attribs.name;
+                        let patch = field_patch_report(
+                            &parse_field_attribs(&field.attrs),
+                            quote! { &mut self.#name },
+                        );
+                        Some(quote! { #key => #patch, })
+                    })
+                    .collect::<Vec<_>>();
+                derive_report_only(
+                    &ast,
+                    &hooks,
+                    quote! {
+                        serde_intermediate::Change::PartialStruct(v) => {
+                            for (name, change) in v {
+                                report.nested(
+                                    serde_intermediate::IntermediatePathSegment::Field(name.to_owned()),
+                                    |report| match name.as_str() {
+                                        #( #items )*
+                                        _ => report.reject_missing(),
+                                    },
+                                );
                             }
-                            self.after_patch_change();
                         }
-
-                        #before_patch_change
-
-                        #after_patch_change
-                    }
-                }.into()
+                        change => report.reject_unsupported(change),
+                    },
+                )
             }
             Fields::Unnamed(fields) => {
-                let multiple_fields = fields.unnamed.iter().enumerate().filter_map(|(index,field)| {
-                    let attribs = parse_field_attribs(&field.attrs);
-                    if attribs.ignore {
-                        return None;
-                    }
-                    let tuple_index = Index::from(index);
-                    if attribs.indirect {
-                        Some(quote! {
-                            #index => {
-                                if let Ok(serialized) = serde_intermediate::to_intermediate(&self.#tuple_index) {
-                                    if let Ok(Some(patched)) = change.patch(&serialized) {
-                                        if let Ok(deserialized) = serde_intermediate::from_intermediate(&patched) {
-                                            self.#tuple_index = deserialized;
-                                        }
-                                    }
-                                }
-                            }
-                        })
-                    } else {
-                        Some(quote! {
-                            #index => {
-                                self.#tuple_index.patch_change(change);
-                            }
-                        })
-                    }
-                }).collect::<Vec<_>>();
-                let single_field = fields.unnamed.iter().next().and_then(|field| {
-                    let attribs = parse_field_attribs(&field.attrs);
-                    if attribs.ignore {
-                        return None;
-                    }
-                    if attribs.indirect {
-                        Some(quote! {
-                            if let Ok(serialized) = serde_intermediate::to_intermediate(&self.0) {
-                                if let Ok(Some(patched)) = change.patch(&serialized) {
-                                    if let Ok(deserialized) = serde_intermediate::from_intermediate(&patched) {
-                                        self.0 = deserialized;
-                                    }
-                                }
-                            }
-                        })
-                    } else {
-                        Some(quote! {
-                            self.0.patch_change(change);
-                        })
-                    }
+                let indices = serialized_indices(fields);
+                let multiple_fields = fields
+                    .unnamed
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, field)| {
+                        let tuple_index = Index::from(index);
+                        let index = indices[index]?;
+                        let patch = field_patch_report(
+                            &parse_field_attribs(&field.attrs),
+                            quote! { &mut self.#tuple_index },
+                        );
+                        Some(quote! { #index => #patch, })
+                    })
+                    .collect::<Vec<_>>();
+                let single_field = fields.unnamed.iter().next().map(|field| {
+                    field_patch_report(&parse_field_attribs(&field.attrs), quote! { &mut self.0 })
                 });
-                quote! {
-                    impl #impl_generics serde_intermediate::ReflectIntermediate for #name #ty_generics #where_clause {
-                        fn patch_change(&mut self, change: &serde_intermediate::Change) {
-                            self.before_patch_change();
-                            match change {
-                                serde_intermediate::Change::Changed(v) => {
-                                    if let Ok(v) = serde_intermediate::from_intermediate(v) {
-                                        *self = v;
-                                    }
-                                }
-                                serde_intermediate::Change::PartialChange(change) => {
-                                    #single_field
-                                }
-                                serde_intermediate::Change::PartialSeq(v) => {
-                                    for (index, change) in v {
-                                        match *index {
-                                            #( #multiple_fields )*
-                                            _ => {}
-                                        }
-                                    }
-                                }
-                                _ => {}
+                derive_report_only(
+                    &ast,
+                    &hooks,
+                    quote! {
+                        serde_intermediate::Change::PartialChange(change) => {
+                            report.nested(
+                                serde_intermediate::IntermediatePathSegment::Inner,
+                                |report| { #single_field; },
+                            );
+                        }
+                        serde_intermediate::Change::PartialSeq(v) => {
+                            for (index, change) in v {
+                                report.nested(
+                                    serde_intermediate::IntermediatePathSegment::Index(*index),
+                                    |report| match *index {
+                                        #( #multiple_fields )*
+                                        _ => report.reject_missing(),
+                                    },
+                                );
                             }
-                            self.after_patch_change();
                         }
-
-                        #before_patch_change
-
-                        #after_patch_change
-                    }
-                }.into()
+                        change => report.reject_unsupported(change),
+                    },
+                )
             }
-            Fields::Unit => quote! {
-                impl #impl_generics serde_intermediate::ReflectIntermediate for #name #ty_generics #where_clause {}
-            }
-            .into(),
+            Fields::Unit => derive_report_only(
+                &ast,
+                &hooks,
+                quote! {
+                    change => report.reject_unsupported(change),
+                },
+            ),
         },
         Data::Enum(data) => {
-            let new_type_variants = data.variants.iter().filter_map(|variant| {
-                let name = &variant.ident;
-                if let Fields::Unnamed(_) = &variant.fields {
-                    if variant.fields.len() == 1 {
-                        let field = variant.fields.iter().next().unwrap();
-                        let attribs = parse_field_attribs(&field.attrs);
-                        if attribs.ignore {
-                            return None;
-                        }
-                        if attribs.indirect {
-                            Some(quote! {
-                                Self::#name(content) => {
-                                    if let Ok(serialized) = serde_intermediate::to_intermediate(content) {
-                                        if let Ok(Some(patched)) = change.patch(&serialized) {
-                                            if let Ok(deserialized) = serde_intermediate::from_intermediate(&patched) {
-                                                *content = deserialized;
-                                            }
-                                        }
-                                    }
-                                }
-                            })
-                        } else {
-                            Some(quote! {
-                                Self::#name(content) => {
-                                    content.patch_change(change);
-                                }
-                            })
+            let new_type_variants = data
+                .variants
+                .iter()
+                .filter_map(|variant| {
+                    if SerdeVariantAttribs::parse(variant, &serde).skip {
+                        return None;
+                    }
+                    let name = &variant.ident;
+                    match &variant.fields {
+                        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                            let attribs = parse_field_attribs(&fields.unnamed[0].attrs);
+                            if attribs.ignore || parse_field_attribs(&variant.attrs).ignore {
+                                Some(quote! { Self::#name(_) => {} })
+                            } else {
+                                let patch = field_patch_report(&attribs, quote! { content });
+                                Some(quote! {
+                                    Self::#name(content) => report.nested(
+                                        serde_intermediate::IntermediatePathSegment::Inner,
+                                        |report| #patch,
+                                    ),
+                                })
+                            }
                         }
-                    } else {
-                        None
+                        _ => None,
                     }
-                } else {
-                    None
-                }
-            }).collect::<Vec<_>>();
+                })
+                .collect::<Vec<_>>();
             let struct_variants = data.variants.iter().filter_map(|variant| {
-                let attribs = parse_field_attribs(&variant.attrs);
-                if attribs.ignore {
+                let name = &variant.ident;
+                let fields = match &variant.fields {
+                    Fields::Named(fields) => fields,
+                    _ => return None,
+                };
+                let serde_attribs = SerdeVariantAttribs::parse(variant, &serde);
+                if serde_attribs.skip {
                     return None;
                 }
-                let name = &variant.ident;
-                if let Fields::Named(fields) = &variant.fields {
-                    let field_names = fields
-                        .named
-                        .iter()
-                        .filter_map(|field| {
-                            let attribs = parse_field_attribs(&field.attrs);
-                            if attribs.ignore {
-                                return None;
-                            }
-                            Some(field.ident.as_ref().unwrap())
-                        })
-                        .collect::<Vec<_>>();
-                    let fields = fields.named.iter().filter_map(|field| {
+                if parse_field_attribs(&variant.attrs).ignore {
+                    return Some(quote! { Self::#name { .. } => {} });
+                }
+                let (bindings, items) = struct_fields_report(fields, &serde_attribs);
+                Some(quote! {
+                    Self::#name { #( #bindings , )* .. } => {
+                        for (name, change) in v {
+                            report.nested(
+                                serde_intermediate::IntermediatePathSegment::Field(name.to_owned()),
+                                |report| match name.as_str() {
+                                    #( #items )*
+                                    _ => report.reject_missing(),
+                                },
+                            );
+                        }
+                    }
+                })
+            }).collect::<Vec<_>>();
+            let tuple_variants = data
+                .variants
+                .iter()
+                .filter_map(|variant| {
+                    let name = &variant.ident;
+                    let fields = match &variant.fields {
+                        Fields::Unnamed(fields) if fields.unnamed.len() >= 2 => fields,
+                        _ => return None,
+                    };
+                    if SerdeVariantAttribs::parse(variant, &serde).skip {
+                        return None;
+                    }
+                    let indices = serialized_indices(fields);
+                    if parse_field_attribs(&variant.attrs).ignore {
+                        return Some(quote! { Self::#name(..) => {} });
+                    }
+                    let mut bindings = vec![];
+                    let mut items = vec![];
+                    for (index, field) in fields.unnamed.iter().enumerate() {
                         let attribs = parse_field_attribs(&field.attrs);
+                        let serialized_index = match indices[index] {
+                            Some(index) => index,
+                            None => {
+                                bindings.push(quote! { _ });
+                                continue;
+                            }
+                        };
                         if attribs.ignore {
-                            return None;
+                            bindings.push(quote! { _ });
+                            items.push(quote! { #serialized_index => {} });
+                            continue;
                         }
-                        let name = field.ident.as_ref().unwrap();
-                        let key = name.to_string();
-                        if attribs.indirect {
-                            Some(quote! {
-                                #key => {
-                                    if let Ok(serialized) = serde_intermediate::to_intermediate(#name) {
-                                        if let Ok(Some(patched)) = change.patch(&serialized) {
-                                            if let Ok(deserialized) = serde_intermediate::from_intermediate(&patched) {
-                                                *#name = deserialized;
-                                            }
-                                        }
-                                    }
-                                }
-                            })
-                        } else {
-                            Some(quote! {
-                                #key => {
-                                    #name.patch_change(change);
-                                }
-                            })
-                        }
-                    }).collect::<Vec<_>>();
+                        let binding =
+                            Ident::new(&format!("field_{}", index), Span::call_site().into());
+                        let patch = field_patch_report(&attribs, binding.to_token_stream());
+                        bindings.push(binding.to_token_stream());
+                        items.push(quote! { #serialized_index => #patch, });
+                    }
                     Some(quote! {
-                        Self::#name { #( #field_names , )* .. } => {
-                            for (name, change) in v {
-                                match name.as_str() {
-                                    #( #fields )*
-                                    _ => {}
-                                }
+                        Self::#name( #( #bindings ),* ) => {
+                            for (index, change) in v {
+                                report.nested(
+                                    serde_intermediate::IntermediatePathSegment::Index(*index),
+                                    |report| match *index {
+                                        #( #items )*
+                                        _ => report.reject_missing(),
+                                    },
+                                );
                             }
                         }
                     })
-                } else {
-                    None
+                })
+                .collect::<Vec<_>>();
+            derive_report_only(
+                &ast,
+                &hooks,
+                quote! {
+                    serde_intermediate::Change::PartialChange(change) => {
+                        match self {
+                            #( #new_type_variants )*
+                            #[allow(unreachable_patterns)]
+                            _ => report.reject_invalid("change doesn't match enum variant"),
+                        }
+                    }
+                    serde_intermediate::Change::PartialSeq(v) => {
+                        match self {
+                            #( #tuple_variants )*
+                            #[allow(unreachable_patterns)]
+                            _ => report.reject_invalid("change doesn't match enum variant"),
+                        }
+                    }
+                    serde_intermediate::Change::PartialStruct(v) => {
+                        match self {
+                            #( #struct_variants )*
+                            #[allow(unreachable_patterns)]
+                            _ => report.reject_invalid("change doesn't match enum variant"),
+                        }
+                    }
+                    change => report.reject_unsupported(change),
+                },
+            )
+        }
+        _ => panic!("ReflectIntermediate can be derived only for structs and enums"),
+    }
+}
+
+/// Implementation patching through `patch_change_with_report`, extended with `arms` matching
+/// changes other than `Same` and `Changed`.
+fn derive_report_only(
+    ast: &DeriveInput,
+    hooks: &Hooks,
+    arms: proc_macro2::TokenStream,
+) -> TokenStream {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let Hooks {
+        methods,
+        on_change_start,
+        on_change_end,
+    } = hooks;
+    quote! {
+        impl #impl_generics serde_intermediate::ReflectIntermediate for #name #ty_generics #where_clause {
+            fn patch_change(&mut self, change: &serde_intermediate::Change) {
+                self.patch_change_with_report(change, &mut Default::default());
+            }
+
+            fn patch_change_with_report(
+                &mut self,
+                change: &serde_intermediate::Change,
+                report: &mut serde_intermediate::PatchReport,
+            ) {
+                #on_change_start
+                self.before_patch_change();
+                match change {
+                    serde_intermediate::Change::Same => {}
+                    serde_intermediate::Change::Changed(v) => report.replace(self, v),
+                    #arms
                 }
-            }).collect::<Vec<_>>();
-            quote! {
-                impl #impl_generics serde_intermediate::ReflectIntermediate for #name #ty_generics #where_clause {
-                    fn patch_change(&mut self, change: &serde_intermediate::Change) {
-                        self.before_patch_change();
-                        match change {
-                            serde_intermediate::Change::Changed(v) => {
-                                if let Ok(v) = serde_intermediate::from_intermediate(v) {
-                                    *self = v;
-                                }
-                            }
-                            serde_intermediate::Change::PartialChange(change) => {
-                                match self {
-                                    #( #new_type_variants )*
-                                    _ => {}
-                                }
-                            }
-                            serde_intermediate::Change::PartialStruct(v) => {
-                                match self {
-                                    #( #struct_variants )*
-                                    _ => {}
+                #on_change_end
+                self.after_patch_change();
+            }
+
+            #methods
+        }
+    }
+    .into()
+}
+
+/// Serialized layout can't be followed field by field (untagged enums, custom serialization),
+/// so changes are applied to serialized value.
+fn derive_indirect(ast: &DeriveInput, hooks: &Hooks) -> TokenStream {
+    derive_report_only(
+        ast,
+        hooks,
+        quote! {
+            change => report.patch_indirect(self, change),
+        },
+    )
+}
+
+/// `#[serde(transparent)]` type is serialized as its only serialized field.
+fn derive_transparent(ast: &DeriveInput, hooks: &Hooks) -> TokenStream {
+    let fields = match &ast.data {
+        Data::Struct(data) => &data.fields,
+        _ => panic!("Only structs can be transparent"),
+    };
+    let (index, field) = fields
+        .iter()
+        .enumerate()
+        .find(|(_, field)| !SerdeFieldAttribs::parse(field, Default::default()).skip)
+        .expect("Transparent struct has to have field to serialize");
+    let member = match &field.ident {
+        Some(name) => name.to_token_stream(),
+        None => Index::from(index).to_token_stream(),
+    };
+    let patch = field_patch_report(
+        &parse_field_attribs(&field.attrs),
+        quote! { &mut self.#member },
+    );
+    derive_report_only(
+        ast,
+        hooks,
+        quote! {
+            change => #patch,
+        },
+    )
+}
+
+/// Struct with `#[serde(flatten)]` fields is serialized as map with content of flattened fields
+/// merged in. Entries that don't belong to other fields are handed over to flattened ones.
+fn derive_flattened(
+    ast: &DeriveInput,
+    fields: &FieldsNamed,
+    serde: &SerdeTypeAttribs,
+    hooks: &Hooks,
+) -> TokenStream {
+    let mut flattened = vec![];
+    let mut items = vec![];
+    for field in &fields.named {
+        let serde_attribs = SerdeFieldAttribs::parse(field, serde.rename_all);
+        let name = field.ident.as_ref().unwrap();
+        if serde_attribs.skip {
+            continue;
+        }
+        if serde_attribs.flatten {
+            if !parse_field_attribs(&field.attrs).ignore {
+                flattened.push(name);
+            }
+            continue;
+        }
+        let key = serde_attribs.name;
+        let patch = field_patch_report(
+            &parse_field_attribs(&field.attrs),
+            quote! { &mut self.#name },
+        );
+        items.push(quote! { Some(#key) => #patch, });
+    }
+    derive_report_only(
+        ast,
+        hooks,
+        quote! {
+            serde_intermediate::Change::PartialMap(v) => {
+                for (key, change) in v {
+                    report.nested(
+                        serde_intermediate::IntermediatePathSegment::Key(key.to_owned()),
+                        |report| {
+                            let name = match key {
+                                serde_intermediate::Intermediate::String(name) => Some(name.as_str()),
+                                _ => None,
+                            };
+                            match name {
+                                #( #items )*
+                                _ => {
+                                    let change = serde_intermediate::Change::PartialMap(vec![
+                                        (key.to_owned(), change.to_owned()),
+                                    ]);
+                                    #[allow(unused_mut, unused_variables)]
+                                    if let Some(mut patched) = report.patch_serialized(&*self, &change) {
+                                        #( std::mem::swap(&mut self.#flattened, &mut patched.#flattened); )*
+                                    }
                                 }
                             }
-                            _ => {}
+                        },
+                    );
+                }
+            }
+            change => report.reject_unsupported(change),
+        },
+    )
+}
+
+/// Internally (`#[serde(tag = "...")]`) and adjacently (`#[serde(tag = "...", content = "...")]`)
+/// tagged enums. Changes of tag mean changed variant, so they are applied to serialized value.
+fn derive_tagged(
+    ast: &DeriveInput,
+    data: &DataEnum,
+    serde: &SerdeTypeAttribs,
+    hooks: &Hooks,
+) -> TokenStream {
+    let tag = serde.tag.as_ref().unwrap();
+    // Arms matching variant of `self` with its content change: `PartialStruct` and `PartialMap`
+    // for internally tagged enums, content field change for adjacently tagged ones.
+    let mut struct_arms = vec![];
+    let mut map_arms = vec![];
+    let mut content_arms = vec![];
+    for variant in &data.variants {
+        let serde_attribs = SerdeVariantAttribs::parse(variant, serde);
+        if serde_attribs.skip {
+            continue;
+        }
+        let name = &variant.ident;
+        if parse_field_attribs(&variant.attrs).ignore {
+            struct_arms.push(quote! { Self::#name { .. } => {} });
+            map_arms.push(quote! { Self::#name { .. } => {} });
+            content_arms.push(quote! { (Self::#name { .. }, _) => {} });
+            continue;
+        }
+        match &variant.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let patch = field_patch_report(
+                    &parse_field_attribs(&fields.unnamed[0].attrs),
+                    quote! { content },
+                );
+                struct_arms.push(quote! { Self::#name(content) => #patch, });
+                map_arms.push(quote! { Self::#name(content) => #patch, });
+                content_arms.push(quote! { (Self::#name(content), change) => #patch, });
+            }
+            Fields::Unnamed(fields) => {
+                let (bindings, items) = tuple_fields_report(fields);
+                content_arms.push(quote! {
+                    (Self::#name( #( #bindings ),* ), serde_intermediate::Change::PartialSeq(v))
+                        if v.iter().all(|(_, change)| !matches!(
+                            change,
+                            serde_intermediate::Change::Added(_)
+                                | serde_intermediate::Change::Removed
+                                | serde_intermediate::Change::Moved(_)
+                        )) =>
+                    {
+                        for (index, change) in v {
+                            report.nested(
+                                serde_intermediate::IntermediatePathSegment::Index(*index),
+                                |report| match *index {
+                                    #( #items )*
+                                    _ => report.reject_missing(),
+                                },
+                            );
                         }
-                        self.after_patch_change();
                     }
+                });
+            }
+            Fields::Named(fields) => {
+                let (bindings, items) = struct_fields_report(fields, &serde_attribs);
+                let patch = quote! {
+                    for (name, change) in v {
+                        report.nested(
+                            serde_intermediate::IntermediatePathSegment::Field(name.to_owned()),
+                            |report| match name.as_str() {
+                                #( #items )*
+                                _ => report.reject_missing(),
+                            },
+                        );
+                    }
+                };
+                struct_arms.push(quote! {
+                    Self::#name { #( #bindings , )* .. } => { #patch }
+                });
+                content_arms.push(quote! {
+                    (
+                        Self::#name { #( #bindings , )* .. },
+                        serde_intermediate::Change::PartialStruct(v),
+                    ) => { #patch }
+                });
+            }
+            Fields::Unit => {}
+        }
+    }
+    let arms = match &serde.content {
+        None => quote! {
+            serde_intermediate::Change::PartialStruct(v) if v.iter().all(|(name, _)| name != #tag) => {
+                match self {
+                    #( #struct_arms )*
+                    #[allow(unreachable_patterns)]
+                    _ => report.reject_invalid("change doesn't match enum variant"),
+                }
+            }
+            serde_intermediate::Change::PartialMap(v)
+                if v.iter().all(|(key, _)| {
+                    !matches!(key, serde_intermediate::Intermediate::String(key) if key == #tag)
+                }) =>
+            {
+                match self {
+                    #( #map_arms )*
+                    #[allow(unreachable_patterns)]
+                    _ => report.reject_invalid("change doesn't match enum variant"),
+                }
+            }
+            change => report.patch_indirect(self, change),
+        },
+        Some(content) => quote! {
+            serde_intermediate::Change::PartialStruct(v) if v.iter().all(|(name, _)| name == #content) => {
+                for (name, change) in v {
+                    report.nested(
+                        serde_intermediate::IntermediatePathSegment::Field(name.to_owned()),
+                        |report| match (&mut *self, change) {
+                            #( #content_arms )*
+                            #[allow(unreachable_patterns)]
+                            (_, change) => {
+                                let change = serde_intermediate::Change::PartialStruct(vec![
+                                    (name.to_owned(), change.to_owned()),
+                                ]);
+                                if let Some(patched) = report.patch_serialized(&*self, &change) {
+                                    *self = patched;
+                                }
+                            }
+                        },
+                    );
+                }
+            }
+            change => report.patch_indirect(self, change),
+        },
+    };
+    derive_report_only(ast, hooks, arms)
+}
+
+/// Field bindings and arms patching named fields matched by their serialized names.
+fn struct_fields_report(
+    fields: &FieldsNamed,
+    serde: &SerdeVariantAttribs,
+) -> (Vec<proc_macro2::TokenStream>, Vec<proc_macro2::TokenStream>) {
+    let mut bindings = vec![];
+    let mut items = vec![];
+    for field in &fields.named {
+        let serde_attribs = SerdeFieldAttribs::parse(field, serde.rename_all);
+        if serde_attribs.skip {
+            continue;
+        }
+        let binding = field.ident.as_ref().unwrap();
+        let key = serde_attribs.name;
+        let attribs = parse_field_attribs(&field.attrs);
+        if !attribs.ignore {
+            bindings.push(binding.to_token_stream());
+        }
+        let patch = field_patch_report(&attribs, binding.to_token_stream());
+        items.push(quote! { #key => #patch, });
+    }
+    (bindings, items)
+}
 
-                    #before_patch_change
+/// Field bindings and arms patching unnamed fields matched by their serialized indices.
+fn tuple_fields_report(
+    fields: &FieldsUnnamed,
+) -> (Vec<proc_macro2::TokenStream>, Vec<proc_macro2::TokenStream>) {
+    let indices = serialized_indices(fields);
+    let mut bindings = vec![];
+    let mut items = vec![];
+    for (index, field) in fields.unnamed.iter().enumerate() {
+        let attribs = parse_field_attribs(&field.attrs);
+        let serialized_index = match indices[index] {
+            Some(index) if !attribs.ignore => index,
+            _ => {
+                bindings.push(quote! { _ });
+                continue;
+            }
+        };
+        let binding = Ident::new(&format!("field_{}", index), Span::call_site().into());
+        let patch = field_patch_report(&attribs, binding.to_token_stream());
+        bindings.push(binding.to_token_stream());
+        items.push(quote! { #serialized_index => #patch, });
+    }
+    (bindings, items)
+}
 
-                    #after_patch_change
+fn field_patch_report(
+    attribs: &FieldAttribs,
+    target: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    if attribs.ignore {
+        quote! { {} }
+    } else if attribs.indirect {
+        quote! { report.patch_indirect(#target, change) }
+    } else {
+        quote! { (#target).patch_change_with_report(change, report) }
+    }
+}
+
+/// Indices of unnamed fields in serialized tuple, `None` for fields skipped by serde.
+fn serialized_indices(fields: &FieldsUnnamed) -> Vec<Option<usize>> {
+    let mut next = 0;
+    fields
+        .unnamed
+        .iter()
+        .map(|field| {
+            if SerdeFieldAttribs::parse(field, Default::default()).skip {
+                None
+            } else {
+                next += 1;
+                Some(next - 1)
+            }
+        })
+        .collect()
+}
+
+/// Tells if type has fields or variants serialized in ways derive can't follow.
+fn has_opaque_fields(data: &Data) -> bool {
+    let opaque = |fields: &Fields, flatten: bool| {
+        fields.iter().any(|field| {
+            let attribs = SerdeFieldAttribs::parse(field, Default::default());
+            attribs.opaque || (flatten && attribs.flatten)
+        })
+    };
+    match data {
+        Data::Struct(data) => opaque(&data.fields, false),
+        Data::Enum(data) => data.variants.iter().any(|variant| {
+            SerdeVariantAttribs::parse(variant, &Default::default()).opaque
+                || opaque(&variant.fields, true)
+        }),
+        Data::Union(_) => false,
+    }
+}
+
+/// `on_change` hooks of fields, each with paths of fields it observes. Fields that can't be
+/// found in serialized layout of type are observed by their whole parent.
+fn on_change_hooks(
+    ast: &DeriveInput,
+    serde: &SerdeTypeAttribs,
+) -> Vec<(Ident, Vec<proc_macro2::TokenStream>)> {
+    let whole = serde.opaque
+        || serde.transparent
+        || !serde.is_externally_tagged()
+        || has_opaque_fields(&ast.data);
+    let mut observed = vec![];
+    let mut observe = |fields: &Fields, rename_all: RenameRule, flattened: bool| {
+        let indices = match fields {
+            Fields::Unnamed(fields) => serialized_indices(fields),
+            _ => vec![],
+        };
+        for (index, field) in fields.iter().enumerate() {
+            let hook = match parse_field_attribs(&field.attrs).on_change {
+                Some(hook) => hook,
+                None => continue,
+            };
+            let serde_attribs = SerdeFieldAttribs::parse(field, rename_all);
+            let segment = if whole || serde_attribs.flatten {
+                None
+            } else if field.ident.is_some() {
+                let name = serde_attribs.name;
+                if flattened {
+                    Some(quote! {
+                        Key(serde_intermediate::Intermediate::String(#name.to_owned()))
+                    })
+                } else {
+                    Some(quote! { Field(#name.to_owned()) })
                 }
-            }.into()
+            } else if fields.len() == 1 {
+                Some(quote! { Inner })
+            } else {
+                indices[index].map(|index| quote! { Index(#index) })
+            };
+            let path = match segment {
+                Some(segment) => quote! {
+                    report
+                        .location()
+                        .to_owned()
+                        .segment(serde_intermediate::IntermediatePathSegment::#segment)
+                },
+                None => quote! { report.location().to_owned() },
+            };
+            observed.push((hook, path));
+        }
+    };
+    match &ast.data {
+        Data::Struct(data) => {
+            let flattened = data
+                .fields
+                .iter()
+                .any(|field| SerdeFieldAttribs::parse(field, serde.rename_all).flatten);
+            observe(&data.fields, serde.rename_all, flattened);
+        }
+        Data::Enum(data) => {
+            for variant in &data.variants {
+                let rename_all = SerdeVariantAttribs::parse(variant, serde).rename_all;
+                observe(&variant.fields, rename_all, false);
+            }
+        }
+        Data::Union(_) => {}
+    }
+    let mut result: Vec<(Ident, Vec<proc_macro2::TokenStream>)> = vec![];
+    for (hook, path) in observed {
+        match result.iter_mut().find(|(other, _)| other == &hook) {
+            Some((_, paths)) => paths.push(path),
+            None => result.push((hook, vec![path])),
         }
-        _ => panic!("ReflectIntermediate can be derived only for structs and enums"),
     }
+    result
 }
 
 fn parse_type_attribs(attrs: &[Attribute]) -> TypeAttribs {
@@ -329,6 +830,11 @@ fn parse_type_attribs(attrs: &[Attribute]) -> TypeAttribs {
             Ok(Meta::List(meta)) => {
                 if meta.path.is_ident("reflect_intermediate") {
                     for meta in meta.nested {
+                        if let NestedMeta::Meta(Meta::Path(path)) = &meta {
+                            if path.is_ident("clone") {
+                                result.clone = true;
+                            }
+                        }
                         if let NestedMeta::Meta(Meta::NameValue(meta)) = &meta {
                             if meta.path.is_ident("before_patch_change") {
                                 if let Lit::Str(value) = &meta.lit {
@@ -351,7 +857,7 @@ fn parse_type_attribs(attrs: &[Attribute]) -> TypeAttribs {
     result
 }
 
-fn parse_field_attribs(attrs: &[Attribute]) -> FieldAttribs {
+pub(crate) fn parse_field_attribs(attrs: &[Attribute]) -> FieldAttribs {
     let mut result = FieldAttribs::default();
     for attrib in attrs {
         match attrib.parse_meta() {
@@ -363,12 +869,25 @@ fn parse_field_attribs(attrs: &[Attribute]) -> FieldAttribs {
             Ok(Meta::List(meta)) => {
                 if meta.path.is_ident("reflect_intermediate") {
                     for meta in meta.nested {
-                        if let NestedMeta::Meta(Meta::Path(path)) = &meta {
-                            if path.is_ident("ignore") {
-                                result.ignore = true;
-                            } else if path.is_ident("indirect") {
-                                result.indirect = true;
+                        match &meta {
+                            NestedMeta::Meta(Meta::Path(path)) => {
+                                if path.is_ident("ignore") {
+                                    result.ignore = true;
+                                } else if path.is_ident("indirect") {
+                                    result.indirect = true;
+                                }
+                            }
+                            NestedMeta::Meta(Meta::NameValue(meta)) => {
+                                if meta.path.is_ident("on_change") {
+                                    if let Lit::Str(value) = &meta.lit {
+                                        result.on_change = Some(Ident::new(
+                                            &value.value(),
+                                            Span::call_site().into(),
+                                        ));
+                                    }
+                                }
                             }
+                            _ => {}
                         }
                     }
                 }
@@ -28,7 +28,7 @@ pub fn derive_intermediate(input: TokenStream) -> TokenStream {
         .map(|content| {
             let ty = syn::parse_str::<Type>(content).unwrap();
             quote! {
-                #ty::schema(package);
+                <#ty as serde_intermediate::SchemaIntermediate>::schema(package);
             }
         })
         .collect::<Vec<_>>();
@@ -48,7 +48,7 @@ pub fn derive_intermediate(input: TokenStream) -> TokenStream {
                     let ty = &field.ty;
                     let id = if attribs.package {
                         quote! {
-                            #ty::schema(package)
+                            <#ty as serde_intermediate::SchemaIntermediate>::schema(package)
                         }
                     } else {
                         quote! {
@@ -92,7 +92,7 @@ pub fn derive_intermediate(input: TokenStream) -> TokenStream {
                     let ty = &field.ty;
                     let id = if attribs.package {
                         quote! {
-                            #ty::schema(package)
+                            <#ty as serde_intermediate::SchemaIntermediate>::schema(package)
                         }
                     } else {
                         quote! {
@@ -156,7 +156,7 @@ pub fn derive_intermediate(input: TokenStream) -> TokenStream {
                             let ty = &field.ty;
                             let id = if attribs.package {
                                 quote! {
-                                    #ty::schema(package)
+                                    <#ty as serde_intermediate::SchemaIntermediate>::schema(package)
                                 }
                             } else {
                                 quote! {
@@ -189,7 +189,7 @@ pub fn derive_intermediate(input: TokenStream) -> TokenStream {
                             let ty = &field.ty;
                             let id = if attribs.package {
                                 quote! {
-                                    #ty::schema(package)
+                                    <#ty as serde_intermediate::SchemaIntermediate>::schema(package)
                                 }
                             } else {
                                 quote! {
@@ -344,7 +344,7 @@ fn traverse_type(ty: &Type, filters: &[Ident], result: &mut Vec<proc_macro2::Tok
             if let Some(segment) = path.path.segments.last() {
                 if filters.iter().any(|filter| &segment.ident == filter) {
                     result.push(quote! {
-                        #ty::schema(package);
+                        <#ty as serde_intermediate::SchemaIntermediate>::schema(package);
                     });
                 } else if let PathArguments::AngleBracketed(generics) = &segment.arguments {
                     for arg in &generics.args {
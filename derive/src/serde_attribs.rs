@@ -0,0 +1,290 @@
+use quote::ToTokens;
+use syn::{Attribute, Field, Lit, Meta, NestedMeta, Variant};
+
+/// Serde attributes that do not affect how value gets serialized.
+const NEUTRAL_TYPE_ATTRIBS: &[&str] = &[
+    "rename",
+    "rename_all",
+    "rename_all_fields",
+    "bound",
+    "default",
+    "deny_unknown_fields",
+    "crate",
+];
+const NEUTRAL_VARIANT_ATTRIBS: &[&str] = &[
+    "rename",
+    "rename_all",
+    "alias",
+    "bound",
+    "deserialize_with",
+    "skip_deserializing",
+    "other",
+];
+const NEUTRAL_FIELD_ATTRIBS: &[&str] = &[
+    "rename",
+    "alias",
+    "bound",
+    "default",
+    "deserialize_with",
+    "skip",
+    "skip_serializing",
+    "skip_deserializing",
+    "flatten",
+];
+
+/// Case convention of `#[serde(rename_all = "...")]`.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum RenameRule {
+    #[default]
+    None,
+    LowerCase,
+    UpperCase,
+    PascalCase,
+    CamelCase,
+    SnakeCase,
+    ScreamingSnakeCase,
+    KebabCase,
+    ScreamingKebabCase,
+}
+
+impl RenameRule {
+    fn parse(rule: &str) -> Self {
+        match rule {
+            "lowercase" => Self::LowerCase,
+            "UPPERCASE" => Self::UpperCase,
+            "PascalCase" => Self::PascalCase,
+            "camelCase" => Self::CamelCase,
+            "snake_case" => Self::SnakeCase,
+            "SCREAMING_SNAKE_CASE" => Self::ScreamingSnakeCase,
+            "kebab-case" => Self::KebabCase,
+            "SCREAMING-KEBAB-CASE" => Self::ScreamingKebabCase,
+            _ => panic!("Unknown serde rename rule: `{}`", rule),
+        }
+    }
+
+    /// Applies rule to variant name, assumed to be written in `PascalCase`.
+    pub fn apply_to_variant(self, variant: &str) -> String {
+        match self {
+            Self::None | Self::PascalCase => variant.to_owned(),
+            Self::LowerCase => variant.to_ascii_lowercase(),
+            Self::UpperCase => variant.to_ascii_uppercase(),
+            Self::CamelCase => variant[..1].to_ascii_lowercase() + &variant[1..],
+            Self::SnakeCase => {
+                let mut result = String::new();
+                for (index, c) in variant.char_indices() {
+                    if index > 0 && c.is_uppercase() {
+                        result.push('_');
+                    }
+                    result.push(c.to_ascii_lowercase());
+                }
+                result
+            }
+            Self::ScreamingSnakeCase => Self::SnakeCase
+                .apply_to_variant(variant)
+                .to_ascii_uppercase(),
+            Self::KebabCase => Self::SnakeCase.apply_to_variant(variant).replace('_', "-"),
+            Self::ScreamingKebabCase => Self::ScreamingSnakeCase
+                .apply_to_variant(variant)
+                .replace('_', "-"),
+        }
+    }
+
+    /// Applies rule to field name, assumed to be written in `snake_case`.
+    pub fn apply_to_field(self, field: &str) -> String {
+        match self {
+            Self::None | Self::LowerCase | Self::SnakeCase => field.to_owned(),
+            Self::UpperCase | Self::ScreamingSnakeCase => field.to_ascii_uppercase(),
+            Self::PascalCase => {
+                let mut result = String::new();
+                let mut capitalize = true;
+                for c in field.chars() {
+                    if c == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        result.push(c.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        result.push(c);
+                    }
+                }
+                result
+            }
+            Self::CamelCase => {
+                let pascal = Self::PascalCase.apply_to_field(field);
+                pascal[..1].to_ascii_lowercase() + &pascal[1..]
+            }
+            Self::KebabCase => field.replace('_', "-"),
+            Self::ScreamingKebabCase => Self::ScreamingSnakeCase
+                .apply_to_field(field)
+                .replace('_', "-"),
+        }
+    }
+}
+
+/// Serde attributes of structure or enum.
+#[derive(Debug, Default)]
+pub struct SerdeTypeAttribs {
+    pub rename_all: RenameRule,
+    pub rename_all_fields: RenameRule,
+    pub tag: Option<String>,
+    pub content: Option<String>,
+    pub untagged: bool,
+    pub transparent: bool,
+    /// Has attributes that change serialized layout in ways derive can't follow.
+    pub opaque: bool,
+}
+
+impl SerdeTypeAttribs {
+    pub fn parse(attrs: &[Attribute]) -> Self {
+        let mut result = Self::default();
+        for meta in serde_metas(attrs) {
+            if meta.path().is_ident("rename_all") {
+                result.rename_all = parse_rename_rule(&meta);
+            } else if meta.path().is_ident("rename_all_fields") {
+                result.rename_all_fields = parse_rename_rule(&meta);
+            } else if meta.path().is_ident("tag") {
+                result.tag = parse_serialize_name(&meta);
+            } else if meta.path().is_ident("content") {
+                result.content = parse_serialize_name(&meta);
+            } else if meta.path().is_ident("untagged") {
+                result.untagged = true;
+            } else if meta.path().is_ident("transparent") {
+                result.transparent = true;
+            } else if !is_neutral(&meta, NEUTRAL_TYPE_ATTRIBS) {
+                result.opaque = true;
+            }
+        }
+        result
+    }
+
+    /// Tells if enum is serialized in default, externally tagged representation.
+    pub fn is_externally_tagged(&self) -> bool {
+        self.tag.is_none() && !self.untagged
+    }
+}
+
+/// Serde attributes of enum variant.
+#[derive(Debug, Default)]
+pub struct SerdeVariantAttribs {
+    pub name: String,
+    pub rename_all: RenameRule,
+    pub skip: bool,
+    pub opaque: bool,
+}
+
+impl SerdeVariantAttribs {
+    pub fn parse(variant: &Variant, container: &SerdeTypeAttribs) -> Self {
+        let mut rename = None;
+        let mut result = Self {
+            rename_all: container.rename_all_fields,
+            ..Default::default()
+        };
+        for meta in serde_metas(&variant.attrs) {
+            if meta.path().is_ident("rename") {
+                rename = parse_serialize_name(&meta);
+            } else if meta.path().is_ident("rename_all") {
+                result.rename_all = parse_rename_rule(&meta);
+            } else if meta.path().is_ident("skip") || meta.path().is_ident("skip_serializing") {
+                result.skip = true;
+            } else if !is_neutral(&meta, NEUTRAL_VARIANT_ATTRIBS) {
+                result.opaque = true;
+            }
+        }
+        result.name = rename.unwrap_or_else(|| {
+            container
+                .rename_all
+                .apply_to_variant(&unraw(&variant.ident.to_string()))
+        });
+        result
+    }
+}
+
+/// Serde attributes of structure or enum variant field.
+#[derive(Debug, Default)]
+pub struct SerdeFieldAttribs {
+    /// Serialized name of named field.
+    pub name: String,
+    pub skip: bool,
+    pub flatten: bool,
+    pub opaque: bool,
+}
+
+impl SerdeFieldAttribs {
+    pub fn parse(field: &Field, rename_all: RenameRule) -> Self {
+        let mut rename = None;
+        let mut result = Self::default();
+        for meta in serde_metas(&field.attrs) {
+            if meta.path().is_ident("rename") {
+                rename = parse_serialize_name(&meta);
+            } else if meta.path().is_ident("skip") || meta.path().is_ident("skip_serializing") {
+                result.skip = true;
+            } else if meta.path().is_ident("flatten") {
+                result.flatten = true;
+            } else if !is_neutral(&meta, NEUTRAL_FIELD_ATTRIBS) {
+                result.opaque = true;
+            }
+        }
+        if let Some(ident) = &field.ident {
+            result.name =
+                rename.unwrap_or_else(|| rename_all.apply_to_field(&unraw(&ident.to_string())));
+        }
+        result
+    }
+}
+
+fn serde_metas(attrs: &[Attribute]) -> Vec<Meta> {
+    attrs
+        .iter()
+        .filter(|attrib| attrib.path.is_ident("serde"))
+        .flat_map(|attrib| match attrib.parse_meta() {
+            Ok(Meta::List(meta)) => meta
+                .nested
+                .into_iter()
+                .filter_map(|meta| match meta {
+                    NestedMeta::Meta(meta) => Some(meta),
+                    _ => None,
+                })
+                .collect(),
+            Ok(_) => vec![],
+            Err(error) => panic!(
+                "Could not parse attribute `{}`: {:?}",
+                attrib.to_token_stream(),
+                error
+            ),
+        })
+        .collect()
+}
+
+fn is_neutral(meta: &Meta, neutral: &[&str]) -> bool {
+    neutral.iter().any(|name| meta.path().is_ident(name))
+}
+
+/// Reads name used for serialization from `name = "..."` or `name(serialize = "...")`.
+fn parse_serialize_name(meta: &Meta) -> Option<String> {
+    match meta {
+        Meta::NameValue(meta) => match &meta.lit {
+            Lit::Str(value) => Some(value.value()),
+            _ => None,
+        },
+        Meta::List(meta) => meta.nested.iter().find_map(|meta| match meta {
+            NestedMeta::Meta(Meta::NameValue(meta)) if meta.path.is_ident("serialize") => {
+                match &meta.lit {
+                    Lit::Str(value) => Some(value.value()),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }),
+        Meta::Path(_) => None,
+    }
+}
+
+fn parse_rename_rule(meta: &Meta) -> RenameRule {
+    parse_serialize_name(meta)
+        .map(|rule| RenameRule::parse(&rule))
+        .unwrap_or_default()
+}
+
+fn unraw(name: &str) -> String {
+    name.trim_start_matches("r#").to_owned()
+}